@@ -1,107 +1,115 @@
 // examples/mcp_client_example.rs
-use serde::{Deserialize, Serialize};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
 use url::Url;
 use futures_util::{SinkExt, StreamExt};
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "method", content = "params")]
-pub enum McpRequest {
-    #[serde(rename = "tools/list")]
-    ListTools {},
-    #[serde(rename = "tools/call")]
-    CallTool {
-        name: String,
-        arguments: Option<std::collections::HashMap<String, serde_json::Value>>,
-    },
-    #[serde(rename = "server/info")]
-    GetServerInfo {},
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+// Send a JSON-RPC request and wait for the response with the same id,
+// printing any notifications that arrive in between
+async fn request(
+    socket: &mut Socket,
+    id: u64,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let request_json = serde_json::to_string(&serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": params
+    }))?;
+    socket.send(Message::Text(request_json.into())).await?;
+
+    while let Some(msg) = socket.next().await {
+        if let Message::Text(text) = msg? {
+            let value: serde_json::Value = serde_json::from_str(&text)?;
+            if value.get("id").and_then(|v| v.as_u64()) == Some(id) {
+                return Ok(value);
+            }
+            println!("Notification: {}", text);
+        }
+    }
+
+    Err("Connection closed before response".into())
+}
+
+// Perform the MCP handshake, optionally resuming a previous session
+async fn initialize(
+    socket: &mut Socket,
+    resume: Option<&str>,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let mut capabilities = serde_json::json!({});
+    if let Some(session_id) = resume {
+        capabilities["experimental"] = serde_json::json!({
+            "cerebus-rex/session": { "resume": session_id }
+        });
+    }
+
+    let response = request(socket, 1, "initialize", serde_json::json!({
+        "protocolVersion": "2025-03-26",
+        "capabilities": capabilities,
+        "clientInfo": { "name": "mcp-client-example", "version": "0.1.0" }
+    })).await?;
+
+    let initialized = serde_json::to_string(&serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/initialized"
+    }))?;
+    socket.send(Message::Text(initialized.into())).await?;
+
+    Ok(response["result"]["capabilities"]["experimental"]["cerebus-rex/session"].clone())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Connecting to Cerebus-Rex MCP server...");
-    
+
     // Connect to the MCP server WebSocket endpoint
     let url = Url::parse("ws://localhost:3000/mcp/ws")?;
-    
-    match connect_async(url).await {
-        Ok((ws_stream, _)) => {
-            println!("Connected to Cerebus-Rex MCP server");
-            
-            let (mut write, mut read) = ws_stream.split();
-
-            // Request server info
-            let info_request = McpRequest::GetServerInfo {};
-            let request_json = serde_json::to_string(&serde_json::json!({
-                "method": "server/info",
-                "id": "1"
-            }))?;
-            write.send(Message::Text(request_json)).await?;
-            println!("Sent server info request");
-
-            // Read response
-            if let Some(msg) = read.next().await {
-                match msg? {
-                    Message::Text(text) => {
-                        println!("Server info response: {}", text);
-                    },
-                    _ => println!("Unexpected message type for server info"),
-                }
-            }
 
-            // Request available tools
-            let tools_request_json = serde_json::to_string(&serde_json::json!({
-                "method": "tools/list",
-                "id": "2"
-            }))?;
-            write.send(Message::Text(tools_request_json)).await?;
-            println!("Sent tools list request");
-
-            // Read response
-            if let Some(msg) = read.next().await {
-                match msg? {
-                    Message::Text(text) => {
-                        println!("Tools list response: {}", text);
-                    },
-                    _ => println!("Unexpected message type for tools list"),
-                }
-            }
-
-            // Call a tool (example search)
-            let search_request_json = serde_json::to_string(&serde_json::json!({
-                "method": "tools/call",
-                "params": {
-                    "name": "search",
-                    "arguments": {
-                        "query": "example search query",
-                        "context": "documentation"
-                    }
-                },
-                "id": "3"
-            }))?;
-            write.send(Message::Text(search_request_json)).await?;
-            println!("Sent search tool request");
-
-            // Read response
-            if let Some(msg) = read.next().await {
-                match msg? {
-                    Message::Text(text) => {
-                        println!("Search tool response: {}", text);
-                    },
-                    _ => println!("Unexpected message type for search tool"),
-                }
-            }
-
-            // Close connection
-            write.close().await?;
-            println!("Disconnected from Cerebus-Rex MCP server");
-        },
+    let mut socket = match connect_async(url.as_str()).await {
+        Ok((ws_stream, _)) => ws_stream,
         Err(e) => {
             eprintln!("Failed to connect to Cerebus-Rex MCP server: {}", e);
             println!("Make sure the server is running on ws://localhost:3000/mcp/ws");
+            return Ok(());
         }
-    }
+    };
+    println!("Connected to Cerebus-Rex MCP server");
+
+    let session = initialize(&mut socket, None).await?;
+    println!("Session: {}", session);
+    let session_id = session["id"].as_str().unwrap_or_default().to_string();
+
+    // Request available tools
+    let tools = request(&mut socket, 2, "tools/list", serde_json::json!({})).await?;
+    println!("Tools list response: {}", tools);
+
+    // Get notified whenever indexed code changes
+    request(&mut socket, 3, "resources/subscribe", serde_json::json!({ "uri": "cerebus-rex://code" })).await?;
+
+    // Call a tool (example search)
+    let search = request(&mut socket, 4, "tools/call", serde_json::json!({
+        "name": "search",
+        "arguments": {
+            "query": "example search query",
+            "context": "documentation"
+        }
+    })).await?;
+    println!("Search tool response: {}", search);
+
+    // Drop the connection and resume the same session on a new one
+    socket.close(None).await?;
+    println!("Disconnected, reconnecting...");
+
+    let (mut socket, _) = connect_async(url.as_str()).await?;
+    let resumed = initialize(&mut socket, Some(&session_id)).await?;
+    println!("Resumed session: {}", resumed);
+
+    // Close connection
+    socket.close(None).await?;
+    println!("Disconnected from Cerebus-Rex MCP server");
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -6,16 +6,19 @@ use futures_util::{SinkExt, StreamExt};
 
 #[derive(Serialize, Deserialize, Debug)]
 struct McpRequest {
+    jsonrpc: String,
     method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     params: Option<serde_json::Value>,
-    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct McpResponse {
     result: Option<serde_json::Value>,
     error: Option<McpError>,
-    id: Option<String>,
+    id: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -24,24 +27,53 @@ struct McpError {
     message: String,
 }
 
+impl McpRequest {
+    fn new(method: &str, params: Option<serde_json::Value>, id: Option<u64>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+            id,
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let url = Url::parse("ws://localhost:3000/mcp/ws")?;
-    
-    let (ws_stream, _) = connect_async(url).await?;
+
+    let (ws_stream, _) = connect_async(url.as_str()).await?;
     println!("Connected to Cerebus-Rex MCP server");
 
     let (mut write, mut read) = ws_stream.split();
-    
+
+    // Every MCP session starts with the initialize handshake
+    let initialize_request = McpRequest::new(
+        "initialize",
+        Some(serde_json::json!({
+            "protocolVersion": "2025-03-26",
+            "capabilities": {},
+            "clientInfo": { "name": "simple-client", "version": "0.1.0" }
+        })),
+        Some(1),
+    );
+    write.send(Message::Text(serde_json::to_string(&initialize_request)?.into())).await?;
+
+    if let Some(msg) = read.next().await {
+        if let Message::Text(text) = msg? {
+            let response: McpResponse = serde_json::from_str(&text)?;
+            println!("Initialized: {:?}", response.result);
+        }
+    }
+
+    let initialized = McpRequest::new("notifications/initialized", None, None);
+    write.send(Message::Text(serde_json::to_string(&initialized)?.into())).await?;
+
     // Request available tools
-    let tools_request = McpRequest {
-        method: "list_tools".to_string(),
-        params: None,
-        id: Some("1".to_string()),
-    };
+    let tools_request = McpRequest::new("tools/list", None, Some(2));
 
     let request_json = serde_json::to_string(&tools_request)?;
-    write.send(Message::Text(request_json)).await?;
+    write.send(Message::Text(request_json.into())).await?;
     println!("Sent tools request");
 
     if let Some(msg) = read.next().await {
@@ -55,24 +87,27 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Perform a search
-    let search_request = McpRequest {
-        method: "search".to_string(),
-        params: Some(serde_json::json!({
-            "query": "example search",
-            "context": "documentation"
+    let search_request = McpRequest::new(
+        "tools/call",
+        Some(serde_json::json!({
+            "name": "search",
+            "arguments": {
+                "query": "example search",
+                "context": "documentation"
+            }
         })),
-        id: Some("2".to_string()),
-    };
+        Some(3),
+    );
 
     let request_json = serde_json::to_string(&search_request)?;
-    write.send(Message::Text(request_json)).await?;
+    write.send(Message::Text(request_json.into())).await?;
     println!("Sent search request");
 
     if let Some(msg) = read.next().await {
         match msg? {
             Message::Text(text) => {
                 let response: McpResponse = serde_json::from_str(&text)?;
-                println!("Received search response: {:?}", response.result);
+                println!("Received search response: {:?}", response.result.or(response.error.map(|e| serde_json::json!(e))));
             },
             _ => println!("Unexpected message type"),
         }
@@ -83,4 +118,4 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Disconnected from Cerebus-Rex MCP server");
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,3 +1,4 @@
 // src/agents/mod.rs
 pub mod sync;
+pub mod session;
 
@@ -0,0 +1,445 @@
+// src/agents/session.rs
+use crate::agents::sync::AgentCoordinator;
+use crate::utils::config::AgentConfig;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// A server-initiated message delivered to a connected client
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub method: String,
+    pub params: serde_json::Value,
+}
+
+impl Notification {
+    pub fn new(method: &str, params: serde_json::Value) -> Self {
+        Self {
+            method: method.to_string(),
+            params,
+        }
+    }
+
+    /// MCP `notifications/resources/updated` for a subscribed resource
+    pub fn resource_updated(uri: &str) -> Self {
+        Self::new(
+            "notifications/resources/updated",
+            serde_json::json!({ "uri": uri }),
+        )
+    }
+}
+
+/// A connection's hold on a session. Each attach gets a new generation, so a
+/// connection that was replaced cannot detach its successor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub session_id: String,
+    generation: u64,
+}
+
+/// State kept for a client session, surviving disconnects for the grace period
+#[derive(Debug)]
+pub struct SessionState {
+    pub id: String,
+    pub agent_id: Option<String>,
+    pub subscriptions: HashSet<String>,
+    pending: VecDeque<Notification>,
+    dropped: u64,
+    sender: Option<mpsc::UnboundedSender<Notification>>,
+    generation: u64,
+    detached_at: Option<Instant>,
+}
+
+impl SessionState {
+    fn new(id: String, sender: mpsc::UnboundedSender<Notification>) -> Self {
+        Self {
+            id,
+            agent_id: None,
+            subscriptions: HashSet::new(),
+            pending: VecDeque::new(),
+            dropped: 0,
+            sender: Some(sender),
+            generation: 0,
+            detached_at: None,
+        }
+    }
+
+    fn attachment(&self) -> Attachment {
+        Attachment {
+            session_id: self.id.clone(),
+            generation: self.generation,
+        }
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Deliver to the live connection, or buffer while detached
+    fn deliver(&mut self, notification: Notification, max_pending: usize) {
+        let notification = match &self.sender {
+            Some(sender) => match sender.send(notification) {
+                Ok(()) => return,
+                Err(mpsc::error::SendError(notification)) => {
+                    // The connection went away without detaching; buffer from now on
+                    self.sender = None;
+                    self.detached_at = Some(Instant::now());
+                    notification
+                }
+            },
+            None => notification,
+        };
+        self.buffer(notification, max_pending);
+    }
+
+    fn buffer(&mut self, notification: Notification, max_pending: usize) {
+        if max_pending == 0 {
+            self.dropped += 1;
+            return;
+        }
+        while self.pending.len() >= max_pending {
+            self.pending.pop_front();
+            self.dropped += 1;
+        }
+        self.pending.push_back(notification);
+    }
+}
+
+/// Summary returned to a client that resumed an existing session
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResumedSession {
+    pub session_id: String,
+    pub agent_id: Option<String>,
+    pub subscriptions: Vec<String>,
+    pub replayed: usize,
+    pub dropped: u64,
+}
+
+/// Tracks client sessions so agents can reconnect without losing their
+/// registration, subscriptions or the notifications sent while they were away
+pub struct SessionManager {
+    sessions: Arc<DashMap<String, SessionState>>,
+    coordinator: Arc<AgentCoordinator>,
+    grace_period: Duration,
+    max_pending: usize,
+}
+
+impl SessionManager {
+    pub fn new(coordinator: Arc<AgentCoordinator>, grace_period: Duration, max_pending: usize) -> Self {
+        Self {
+            sessions: Arc::new(DashMap::new()),
+            coordinator,
+            grace_period,
+            max_pending,
+        }
+    }
+
+    pub fn from_config(coordinator: Arc<AgentCoordinator>, config: &AgentConfig) -> Self {
+        Self::new(
+            coordinator,
+            Duration::from_secs(config.session_grace_period_seconds),
+            config.max_pending_notifications,
+        )
+    }
+
+    pub fn coordinator(&self) -> &Arc<AgentCoordinator> {
+        &self.coordinator
+    }
+
+    /// Open a fresh session for a new connection
+    pub fn open(&self) -> (Attachment, mpsc::UnboundedReceiver<Notification>) {
+        let session_id = Uuid::new_v4().to_string();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let session = SessionState::new(session_id.clone(), sender);
+        let attachment = session.attachment();
+        self.sessions.insert(session_id, session);
+        (attachment, receiver)
+    }
+
+    /// Reattach a connection to an existing session, replaying buffered notifications.
+    /// A connection still attached is superseded: its notification stream ends
+    /// and its later detach is ignored. Returns `None` if the session is
+    /// unknown or its grace period has elapsed; an expired session is removed
+    /// and its agent deregistered as if the reaper had reached it first.
+    pub async fn resume(
+        &self,
+        session_id: &str,
+    ) -> Option<(ResumedSession, Attachment, mpsc::UnboundedReceiver<Notification>)> {
+        let mut session = self.sessions.get_mut(session_id)?;
+        if self.is_expired(&session) {
+            drop(session);
+            if let Some(agent_id) = self.expire(session_id) {
+                self.coordinator.deregister_agent(&agent_id).await;
+            }
+            return None;
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let replayed = session.pending.len();
+        for notification in session.pending.drain(..) {
+            // The receiver is still in scope, so this cannot fail
+            let _ = sender.send(notification);
+        }
+        // Replacing the sender closes the previous connection's stream
+        session.sender = Some(sender);
+        session.generation += 1;
+        session.detached_at = None;
+        let attachment = session.attachment();
+
+        let resumed = ResumedSession {
+            session_id: session.id.clone(),
+            agent_id: session.agent_id.clone(),
+            subscriptions: session.subscriptions.iter().cloned().collect(),
+            replayed,
+            dropped: std::mem::take(&mut session.dropped),
+        };
+        drop(session);
+
+        Some((resumed, attachment, receiver))
+    }
+
+    /// Mark a session as disconnected; it is kept until the grace period elapses.
+    /// Does nothing if another connection has resumed the session since.
+    pub fn detach(&self, attachment: &Attachment) {
+        if let Some(mut session) = self.sessions.get_mut(&attachment.session_id) {
+            if session.generation != attachment.generation {
+                return;
+            }
+            session.sender = None;
+            session.detached_at = Some(Instant::now());
+        }
+    }
+
+    /// Drop a session immediately without deregistering its agent
+    pub fn discard(&self, session_id: &str) {
+        self.sessions.remove(session_id);
+    }
+
+    /// Bind a registered agent to the session
+    pub fn set_agent(&self, session_id: &str, agent_id: String) -> bool {
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.agent_id = Some(agent_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn subscribe(&self, session_id: &str, uri: &str) -> bool {
+        self.sessions
+            .get_mut(session_id)
+            .map(|mut session| session.subscriptions.insert(uri.to_string()))
+            .unwrap_or(false)
+    }
+
+    pub fn unsubscribe(&self, session_id: &str, uri: &str) -> bool {
+        self.sessions
+            .get_mut(session_id)
+            .map(|mut session| session.subscriptions.remove(uri))
+            .unwrap_or(false)
+    }
+
+    /// Send a notification to every session subscribed to `uri`.
+    /// Subscriptions match exactly or as a parent path, so `cerebus-rex://code`
+    /// also receives updates for `cerebus-rex://code/src/main.rs` but not for
+    /// `cerebus-rex://codex`.
+    pub fn publish(&self, uri: &str, notification: Notification) -> usize {
+        let mut delivered = 0;
+        for mut session in self.sessions.iter_mut() {
+            let subscribed = session
+                .subscriptions
+                .iter()
+                .any(|subscription| covers(subscription, uri));
+            if subscribed {
+                session.deliver(notification.clone(), self.max_pending);
+                delivered += 1;
+            }
+        }
+        delivered
+    }
+
+    /// Remove detached sessions whose grace period has elapsed and deregister their agents
+    pub async fn expire_stale(&self) -> Vec<String> {
+        let expired: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|session| self.is_expired(session))
+            .map(|session| session.id.clone())
+            .collect();
+
+        for session_id in &expired {
+            if let Some(agent_id) = self.expire(session_id) {
+                self.coordinator.deregister_agent(&agent_id).await;
+            }
+        }
+
+        expired
+    }
+
+    /// Periodically expire stale sessions in the background
+    pub fn spawn_reaper(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let expired = self.expire_stale().await;
+                if !expired.is_empty() {
+                    tracing::debug!("Expired {} stale session(s)", expired.len());
+                }
+            }
+        })
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<dashmap::mapref::one::Ref<'_, String, SessionState>> {
+        self.sessions.get(session_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    fn is_expired(&self, session: &SessionState) -> bool {
+        session
+            .detached_at
+            .map(|detached_at| detached_at.elapsed() >= self.grace_period)
+            .unwrap_or(false)
+    }
+
+    fn expire(&self, session_id: &str) -> Option<String> {
+        self.sessions
+            .remove(session_id)
+            .and_then(|(_, session)| session.agent_id)
+    }
+}
+
+/// Whether a subscription to `subscription` covers updates to `uri`
+fn covers(subscription: &str, uri: &str) -> bool {
+    match uri.strip_prefix(subscription) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/') || subscription.ends_with('/'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::types::{AgentContext, AgentSession};
+
+    fn manager(grace_period: Duration, max_pending: usize) -> SessionManager {
+        SessionManager::new(Arc::new(AgentCoordinator::new()), grace_period, max_pending)
+    }
+
+    #[tokio::test]
+    async fn test_live_session_receives_notifications() {
+        let sessions = manager(Duration::from_secs(60), 8);
+        let (attachment, mut receiver) = sessions.open();
+        sessions.subscribe(&attachment.session_id, "cerebus-rex://code");
+
+        let sibling = Notification::resource_updated("cerebus-rex://codex");
+        assert_eq!(sessions.publish("cerebus-rex://codex", sibling), 0);
+        let delivered = sessions.publish(
+            "cerebus-rex://code/src/main.rs",
+            Notification::resource_updated("cerebus-rex://code/src/main.rs"),
+        );
+        assert_eq!(delivered, 1);
+        assert_eq!(
+            receiver.recv().await.unwrap().params["uri"],
+            "cerebus-rex://code/src/main.rs"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resume_replays_bounded_buffer() {
+        let sessions = manager(Duration::from_secs(60), 2);
+        let (attachment, receiver) = sessions.open();
+        let session_id = attachment.session_id.clone();
+        sessions.set_agent(&session_id, "agent-1".to_string());
+        sessions.subscribe(&session_id, "cerebus-rex://docs");
+        drop(receiver);
+        sessions.detach(&attachment);
+
+        for i in 0..3 {
+            sessions.publish(
+                "cerebus-rex://docs",
+                Notification::new("notifications/message", serde_json::json!({ "seq": i })),
+            );
+        }
+        assert_eq!(sessions.get(&session_id).unwrap().pending_count(), 2);
+
+        let (resumed, _, mut receiver) = sessions.resume(&session_id).await.unwrap();
+        assert_eq!(resumed.agent_id.as_deref(), Some("agent-1"));
+        assert_eq!(resumed.subscriptions, vec!["cerebus-rex://docs".to_string()]);
+        assert_eq!(resumed.replayed, 2);
+        assert_eq!(resumed.dropped, 1);
+        assert_eq!(receiver.recv().await.unwrap().params["seq"], 1);
+        assert_eq!(receiver.recv().await.unwrap().params["seq"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_cannot_resume() {
+        let sessions = manager(Duration::ZERO, 8);
+        let agent = AgentSession {
+            id: String::new(),
+            name: "Test Agent".to_string(),
+            capabilities: Vec::new(),
+            last_seen: 0,
+            context: AgentContext {
+                id: "context".to_string(),
+                name: "Test Context".to_string(),
+                data: std::collections::HashMap::new(),
+                created_at: 0,
+                updated_at: 0,
+            },
+        };
+
+        // Resuming after the grace period deregisters the agent without the reaper
+        let (attachment, _receiver) = sessions.open();
+        let agent_id = sessions.coordinator().register_agent(agent.clone()).await;
+        sessions.set_agent(&attachment.session_id, agent_id);
+        sessions.detach(&attachment);
+        assert!(sessions.resume(&attachment.session_id).await.is_none());
+        assert!(sessions.coordinator().get_active_agents().await.is_empty());
+        assert!(sessions.is_empty());
+
+        let (attachment, _receiver) = sessions.open();
+        let agent_id = sessions.coordinator().register_agent(agent).await;
+        sessions.set_agent(&attachment.session_id, agent_id);
+        sessions.detach(&attachment);
+        assert_eq!(sessions.expire_stale().await, vec![attachment.session_id.clone()]);
+        assert!(sessions.coordinator().get_active_agents().await.is_empty());
+        assert!(sessions.resume(&attachment.session_id).await.is_none());
+        assert!(sessions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resume_before_old_connection_drops() {
+        let sessions = manager(Duration::ZERO, 8);
+        let (old, mut old_receiver) = sessions.open();
+        sessions.subscribe(&old.session_id, "cerebus-rex://code");
+
+        // The client reconnects before the old connection is torn down
+        let (_, new, mut new_receiver) = sessions.resume(&old.session_id).await.unwrap();
+        assert!(old_receiver.recv().await.is_none());
+        sessions.detach(&old);
+        assert!(sessions.get(&old.session_id).unwrap().is_attached());
+        assert!(sessions.expire_stale().await.is_empty());
+
+        sessions.publish("cerebus-rex://code", Notification::resource_updated("cerebus-rex://code"));
+        assert_eq!(new_receiver.recv().await.unwrap().params["uri"], "cerebus-rex://code");
+
+        sessions.detach(&new);
+        assert!(!sessions.get(&old.session_id).unwrap().is_attached());
+    }
+}
@@ -1,14 +1,10 @@
-mod mcp;
-mod agents;
-mod rag;
-mod tools;
-mod utils;
-mod mcp_server;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
 
-use tracing_subscriber::{self};
-
-use crate::{
-    rag::client::RagClient,
+use cerebus_rex::{
+    agents::{session::SessionManager, sync::AgentCoordinator},
+    rag::{client::RagClient, embedding, jobs::JobQueue, manifest::ManifestStore, registry::RepoRegistry, rerank, store, watcher::RepoWatchers},
     tools::{filesystem::FilesystemTool, search::SearchTool, code_context::ThreadsafeCodeContextTool},
     utils::config::{Settings, init_tracing},
     mcp_server::CerebusRexMcpServer,
@@ -23,34 +19,165 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load settings
     let settings = Settings::new().unwrap_or_else(|_| Settings::default());
 
+    // Registered repositories keep their ids across restarts
+    let registry = RepoRegistry::open(&settings.indexing.registry_path).await?;
+
     // Create the RAG client
-    let mut rag_client = RagClient::new(settings.qdrant.endpoint.clone(), settings.qdrant.api_key.clone());
+    let mut rag_client = RagClient::new(settings.qdrant.endpoint.clone(), settings.qdrant.api_key.clone())
+        .with_collections(settings.rag.collections.clone())
+        .with_manifests(ManifestStore::open(&settings.rag.manifest_path))
+        .with_registry(registry)
+        .with_indexing(settings.indexing.clone())
+        .with_chunking(settings.chunking.clone());
+
+    // Load the embedding model; without it documents cannot be indexed or searched
+    match embedding::from_config(&settings.embedding) {
+        Ok(embedder) => {
+            tracing::info!("Using embedding model {} ({} dimensions)", embedder.model_id(), embedder.dimension());
+            rag_client = rag_client.with_embedder(embedder);
+        }
+        Err(e) => tracing::warn!("Failed to load embedding model: {}", e),
+    }
+
+    // The reranker is optional; searches fall back to retrieval order without it
+    if settings.reranker.enabled {
+        match rerank::from_config(&settings.reranker, &settings.rag.collections) {
+            Ok(stages) => {
+                let stage = stages.default_stage();
+                tracing::info!("Reranking the top {} search hits with {}", stage.top_n(), stage.reranker().model_id());
+                rag_client = rag_client.with_reranker(stages);
+            }
+            Err(e) => tracing::warn!("Failed to load reranker: {}", e),
+        }
+    }
+
+    // Qdrant by default; the embedded store needs no external service
+    match store::from_settings(&settings).await {
+        Ok(store) => {
+            tracing::info!("Using the {} vector store", store.name());
+            rag_client = rag_client.with_store(store);
+        }
+        Err(e) => tracing::warn!("Failed to open vector store: {}", e),
+    }
 
     // Initialize the RAG client (this might fail if Qdrant is not available)
     if let Err(e) = rag_client.initialize().await {
         tracing::warn!("Failed to initialize RAG client: {}", e);
     }
 
+    // Collections embedded with another model are rebuilt in the background;
+    // searches keep using the old vectors until each one is swapped in.
+    // Collections with the legacy layout cannot be used at all, so they are
+    // always rebuilt.
+    let outdated = rag_client.outdated_collections().to_vec();
+    let mut rebuilt = rag_client.legacy_collections().to_vec();
+    if !outdated.is_empty() && !settings.rag.migrate_on_model_change {
+        tracing::warn!(
+            "Collections {} were embedded with another model; dense search in them is unreliable",
+            outdated.join(", ")
+        );
+    } else {
+        rebuilt.extend(outdated);
+    }
+    if !rebuilt.is_empty() {
+        let mut migrations = Vec::new();
+        for name in &rebuilt {
+            match rag_client.migration(name).await {
+                Ok(migration) => migrations.push(migration),
+                Err(e) => tracing::warn!("Cannot migrate collection {}: {}", name, e),
+            }
+        }
+        // One at a time, so the migrations don't compete for the embedding model
+        tokio::spawn(async move {
+            for migration in migrations {
+                tracing::info!("Re-embedding {} into {}", migration.alias, migration.target);
+                match migration.run().await {
+                    Ok(report) => tracing::info!(
+                        "Collection {} now served by {} ({} points re-embedded, {} skipped)",
+                        report.alias,
+                        report.collection,
+                        report.migrated,
+                        report.skipped
+                    ),
+                    Err(e) => tracing::warn!("Migration failed: {}", e),
+                }
+            }
+        });
+    }
+
+    // Tools and handlers share the one initialized client
+    let rag_client = Arc::new(RwLock::new(rag_client));
+
     // Create tools
-    let search_tool = SearchTool::new(
-        RagClient::new(settings.qdrant.endpoint.clone(), settings.qdrant.api_key.clone())
-    );
+    let search_tool = SearchTool::new(rag_client.clone());
     let filesystem_tool = FilesystemTool;
     let context_tool = ThreadsafeCodeContextTool::new();
 
+    // Watchers re-index repositories in the background as their files change
+    let watchers = Arc::new(RepoWatchers::new(rag_client.clone(), &settings.indexing));
+
+    // Indexing jobs run on a bounded pool; ones interrupted by a restart resume here
+    let jobs = JobQueue::open(
+        rag_client.clone(),
+        watchers.clone(),
+        settings.indexing.workers,
+        &settings.indexing.jobs_path,
+    )
+    .await?;
+
+    // Sessions outlive their connections for a grace period so agents can resume
+    let coordinator = Arc::new(AgentCoordinator::new());
+    let sessions = Arc::new(SessionManager::from_config(coordinator, &settings.agents));
+    sessions.clone().spawn_reaper(Duration::from_secs(settings.agents.sync_interval_seconds.max(1)));
+
     // Create the MCP server
-    let _server = CerebusRexMcpServer::new(
+    let server = Arc::new(CerebusRexMcpServer::new(
         rag_client,
         search_tool,
         filesystem_tool,
         context_tool,
-    );
+        watchers,
+        jobs,
+        sessions,
+    ));
+    server.spawn_job_notifications();
 
-    // In a real implementation, you would set up a WebSocket server to handle connections
-    tracing::info!("Cerebus-Rex MCP server initialized");
+    if settings.server.transport == "stdio" {
+        tracing::info!("Cerebus-Rex MCP server serving on stdio");
+        server.serve_stdio().await?;
+        return Ok(());
+    }
+
+    let addr = format!("{}:{}", settings.server.host, settings.server.port);
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    tracing::info!("Cerebus-Rex MCP server listening on ws://{}", addr);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!("Failed to accept connection: {}", e);
+                        continue;
+                    }
+                };
+                let server = server.clone();
+                tokio::spawn(async move {
+                    match tokio_tungstenite::accept_async(stream).await {
+                        Ok(websocket) => {
+                            if let Err(e) = server.serve_websocket(websocket).await {
+                                tracing::debug!("Connection from {} closed with error: {}", peer, e);
+                            }
+                        }
+                        Err(e) => tracing::warn!("WebSocket handshake with {} failed: {}", peer, e),
+                    }
+                });
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
 
-    // For now, just keep the server running
-    tokio::signal::ctrl_c().await?;
     tracing::info!("Cerebus-Rex MCP server shutting down");
 
     Ok(())
@@ -3,73 +3,77 @@ use super::protocol::{McpRequest, McpResponse};
 use super::types::*;
 use std::collections::HashMap;
 use async_trait::async_trait;
-use crate::{rag::client::RagClient, agents::sync::AgentCoordinator};
+use crate::rag::client::RagClient;
+use crate::tools::code_context::{CodeContextRequest, ThreadsafeCodeContextTool};
 use tokio::sync::RwLock;
 use std::sync::Arc;
 
+/// JSON-RPC error code for an unknown method
+pub const METHOD_NOT_FOUND: i32 = -32601;
+/// JSON-RPC error code for malformed parameters
+pub const INVALID_PARAMS: i32 = -32602;
+/// JSON-RPC error code for failures inside a handler
+pub const INTERNAL_ERROR: i32 = -32603;
+
 // Define a more general state type for handlers
 pub struct HandlerState {
     pub rag_client: Arc<RwLock<RagClient>>,
-    pub agent_coordinator: Arc<AgentCoordinator>,
+    pub context_tool: Arc<ThreadsafeCodeContextTool>,
 }
 
 #[async_trait]
-pub trait RequestHandler {
+pub trait RequestHandler: Send + Sync {
     async fn handle(&self, state: &HandlerState, request: McpRequest) -> McpResponse;
 }
 
-pub struct SearchHandler;
-pub struct IndexHandler;
-pub struct ContextHandler;
-pub struct ToolHandler;
+/// Maps method names to the handler responsible for them
+#[derive(Clone, Default)]
+pub struct MethodRouter {
+    handlers: HashMap<String, Arc<dyn RequestHandler>>,
+}
 
-#[async_trait]
-impl RequestHandler for SearchHandler {
-    async fn handle(&self, state: &HandlerState, request: McpRequest) -> McpResponse {
-        // Extract query from params
-        let search_req = if let Some(params) = request.params {
-            match serde_json::from_value::<SearchRequest>(params) {
-                Ok(req) => req,
-                Err(_) => {
-                    return McpResponse::error(
-                        -1,
-                        "Invalid search request parameters".to_string(),
-                        request.id,
-                    );
-                }
-            }
-        } else {
-            return McpResponse::error(-1, "Missing parameters".to_string(), request.id);
-        };
+impl MethodRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        // Perform search using RAG client
-        let rag_client = state.rag_client.read().await;
-        // In a real implementation, we would call the RAG system here
-        // For now, return mock results
-        drop(rag_client);
-
-        let results = vec![
-            SearchResult {
-                id: "1".to_string(),
-                content: format!("Search result for query: {}", search_req.query),
-                source: "mock.md".to_string(),
-                score: 0.95,
-                metadata: Some(HashMap::new()),
-            },
-            SearchResult {
-                id: "2".to_string(),
-                content: "Another relevant result".to_string(),
-                source: "project-structure.md".to_string(),
-                score: 0.85,
-                metadata: Some(HashMap::new()),
-            },
-        ];
-
-        let result_json = serde_json::to_value(results).unwrap_or_default();
-        McpResponse::success(result_json, request.id)
+    /// Router with the built-in handlers behind the `index_files` and `get_context` tools
+    pub fn with_default_handlers() -> Self {
+        Self::new()
+            .route("index_files", IndexHandler)
+            .route("get_context", ContextHandler)
+    }
+
+    pub fn route(mut self, method: &str, handler: impl RequestHandler + 'static) -> Self {
+        self.handlers.insert(method.to_string(), Arc::new(handler));
+        self
+    }
+
+    pub fn handles(&self, method: &str) -> bool {
+        self.handlers.contains_key(method)
+    }
+
+    pub fn methods(&self) -> Vec<String> {
+        let mut methods: Vec<String> = self.handlers.keys().cloned().collect();
+        methods.sort();
+        methods
+    }
+
+    pub async fn dispatch(&self, state: &HandlerState, request: McpRequest) -> McpResponse {
+        match self.handlers.get(&request.method) {
+            Some(handler) => handler.handle(state, request).await,
+            None => McpResponse::error(
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", request.method),
+                request.id,
+            ),
+        }
     }
 }
 
+pub struct IndexHandler;
+pub struct ContextHandler;
+
 #[async_trait]
 impl RequestHandler for IndexHandler {
     async fn handle(&self, state: &HandlerState, request: McpRequest) -> McpResponse {
@@ -79,19 +83,33 @@ impl RequestHandler for IndexHandler {
                 Ok(req) => req,
                 Err(_) => {
                     return McpResponse::error(
-                        -1,
+                        INVALID_PARAMS,
                         "Invalid index request parameters".to_string(),
                         request.id,
                     );
                 }
             }
         } else {
-            return McpResponse::error(-1, "Missing parameters".to_string(), request.id);
+            return McpResponse::error(INVALID_PARAMS, "Missing parameters".to_string(), request.id);
         };
 
-        // In a real implementation, we would update the repository index via the RAG client
-        // For now, return a success message
-        McpResponse::success(serde_json::json!({"status": "indexed", "repo_id": index_req.repo_id}), request.id)
+        let indexed_files_count = index_req.files.len();
+        let rag_client = state.rag_client.read().await;
+        match rag_client.index_files(&index_req.repo_id, index_req.files).await {
+            Ok(report) => McpResponse::success(
+                serde_json::json!({
+                    "status": "indexed",
+                    "repo_id": index_req.repo_id,
+                    "indexed_files_count": indexed_files_count,
+                    "added": report.added,
+                    "updated": report.updated,
+                    "removed": report.removed,
+                    "unchanged": report.unchanged,
+                }),
+                request.id,
+            ),
+            Err(e) => McpResponse::error(INTERNAL_ERROR, e.to_string(), request.id),
+        }
     }
 }
 
@@ -102,56 +120,114 @@ impl RequestHandler for ContextHandler {
         let params = if let Some(params) = request.params {
             params
         } else {
-            return McpResponse::error(-1, "Missing parameters".to_string(), request.id);
+            return McpResponse::error(INVALID_PARAMS, "Missing parameters".to_string(), request.id);
         };
 
         let key = if let Some(key_val) = params.get("key").and_then(|v| v.as_str()) {
             key_val.to_string()
         } else {
             return McpResponse::error(
-                -1,
+                INVALID_PARAMS,
                 "Missing key parameter".to_string(),
                 request.id,
             );
         };
 
-        // In a real implementation, we would retrieve context from the store
-        // For now, return mock result
-        let result = serde_json::json!({
-            "key": key,
-            "value": format!("Context value for {}", key)
-        });
+        let context_request = CodeContextRequest {
+            operation: "get".to_string(),
+            key: key.clone(),
+            value: None,
+            metadata: None,
+        };
 
-        McpResponse::success(result, request.id)
+        match state.context_tool.execute(context_request).await {
+            Ok(response) if response.success => {
+                McpResponse::success(response.data.unwrap_or_default(), request.id)
+            }
+            Ok(response) => McpResponse::error(INVALID_PARAMS, response.message, request.id),
+            Err(e) => McpResponse::error(INTERNAL_ERROR, e.to_string(), request.id),
+        }
     }
 }
 
-#[async_trait]
-impl RequestHandler for ToolHandler {
-    async fn handle(&self, state: &HandlerState, request: McpRequest) -> McpResponse {
-        // Return list of available tools
-        let tools = vec![
-            Tool {
-                id: "search".to_string(),
-                name: "Documentation Search".to_string(),
-                description: "Search documentation and codebase".to_string(),
-                input_schema: None,
-            },
-            Tool {
-                id: "index_repo".to_string(),
-                name: "Repository Indexer".to_string(),
-                description: "Index repository files for quick access".to_string(),
-                input_schema: None,
-            },
-            Tool {
-                id: "get_context".to_string(),
-                name: "Context Manager".to_string(),
-                description: "Access and manage context information".to_string(),
-                input_schema: None,
-            },
-        ];
-
-        let result = serde_json::to_value(tools).unwrap_or_default();
-        McpResponse::success(result, request.id)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::client::chunk_point_id;
+    use crate::rag::manifest::content_hash;
+    use crate::rag::test_support::{temp_dir, test_client};
+
+    fn state(rag_client: RagClient) -> HandlerState {
+        HandlerState {
+            rag_client: Arc::new(RwLock::new(rag_client)),
+            context_tool: Arc::new(ThreadsafeCodeContextTool::new()),
+        }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_unknown_method() {
+        let router = MethodRouter::with_default_handlers();
+        let request = McpRequest::new("missing".to_string(), None, Some("1".to_string()));
+
+        let response = router.dispatch(&state(RagClient::default()), request).await;
+        assert_eq!(response.error.unwrap().code, METHOD_NOT_FOUND);
+        assert_eq!(response.id.as_deref(), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn test_context_handler_reads_store() {
+        let router = MethodRouter::with_default_handlers();
+        let state = state(RagClient::default());
+        state
+            .context_tool
+            .execute(CodeContextRequest {
+                operation: "set".to_string(),
+                key: "branch".to_string(),
+                value: Some(serde_json::json!("main")),
+                metadata: None,
+            })
+            .await
+            .unwrap();
+
+        let request = McpRequest::new(
+            "get_context".to_string(),
+            Some(serde_json::json!({ "key": "branch" })),
+            None,
+        );
+        let response = router.dispatch(&state, request).await;
+        assert_eq!(response.result.unwrap()["value"], "main");
+    }
+
+    #[tokio::test]
+    async fn test_index_handler_indexes_files() {
+        let directory = temp_dir("handlers");
+        let (rag_client, store) = test_client(&directory).await;
+        let router = MethodRouter::with_default_handlers();
+        let state = state(rag_client);
+
+        let content = "fn load_settings() {}\n";
+        let params = serde_json::json!({
+            "repo_id": "repo",
+            "files": [{
+                "path": "src/config.rs",
+                "content": content,
+                "language": null,
+                "hash": content_hash(content),
+            }],
+        });
+        let request = McpRequest::new("index_files".to_string(), Some(params.clone()), Some("1".to_string()));
+        let result = router.dispatch(&state, request).await.result.unwrap();
+        assert_eq!((result["indexed_files_count"].as_u64(), result["added"].as_u64()), (Some(1), Some(1)));
+
+        let stored = store.get("code", &chunk_point_id("repo", "src/config.rs", 0)).await.unwrap().unwrap();
+        assert_eq!(stored["content"], "fn load_settings() {}");
+        assert_eq!(stored["language"], "rust");
+
+        // Sending the same files again leaves the index as it is
+        let request = McpRequest::new("index_files".to_string(), Some(params), None);
+        let result = router.dispatch(&state, request).await.result.unwrap();
+        assert_eq!((result["added"].as_u64(), result["unchanged"].as_u64()), (Some(0), Some(1)));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
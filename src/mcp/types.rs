@@ -1,4 +1,5 @@
 // src/mcp/types.rs
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -17,6 +18,47 @@ pub struct SearchRequest {
     pub query: String,
     pub context: Option<String>,
     pub limit: Option<u32>,
+    /// Drop results scoring below this similarity. In hybrid mode it applies
+    /// to the dense candidates before fusion.
+    #[serde(default)]
+    pub score_threshold: Option<f32>,
+    /// Retrieval strategy; hybrid when omitted
+    #[serde(default)]
+    pub mode: Option<SearchMode>,
+    /// Restrict results to matching files
+    #[serde(default)]
+    pub filter: Option<SearchFilter>,
+}
+
+/// Payload conditions a search result must satisfy; every set field must match
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SearchFilter {
+    pub repo_id: Option<String>,
+    pub language: Option<String>,
+    /// Directory or file the result must sit under, e.g. `src/rag`
+    pub path_prefix: Option<String>,
+    /// Glob the file path must match, e.g. `src/**/*.rs`
+    pub path_glob: Option<String>,
+    /// File extension, with or without the leading dot
+    pub extension: Option<String>,
+    /// Only files modified after this time (RFC 3339)
+    pub modified_after: Option<DateTime<Utc>>,
+    /// Payload fields that must equal the given string, integer or boolean
+    pub payload: HashMap<String, serde_json::Value>,
+}
+
+/// How a search retrieves candidates
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Embedding similarity only
+    Dense,
+    /// BM25 lexical match only; finds exact identifiers and error codes
+    Sparse,
+    /// Both, merged by reciprocal rank fusion
+    #[default]
+    Hybrid,
 }
 
 /// Search result structure
@@ -43,6 +85,31 @@ pub struct IndexedFile {
     pub content: String,
     pub language: Option<String>,
     pub hash: String,
+    /// Last modification time; the indexing time when omitted
+    #[serde(default)]
+    pub modified_at: Option<DateTime<Utc>>,
+    /// Commit the content was read from, when indexing a git revision
+    #[serde(default)]
+    pub commit: Option<String>,
+}
+
+/// A file named in an index request. Content given inline, such as an
+/// unsaved editor buffer, is indexed instead of the file on disk.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RepoFile {
+    pub path: String,
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Last modification time of inline content; read from disk otherwise
+    #[serde(default)]
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+/// A file an index request could not index, and why
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileFailure {
+    pub path: String,
+    pub error: String,
 }
 
 /// Context information for agents
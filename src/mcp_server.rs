@@ -1,202 +1,256 @@
 // src/mcp_server.rs
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use tokio::io::AsyncBufReadExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, RwLock};
 use futures_util::{SinkExt, StreamExt};
-use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::{self, Message};
+use rmcp::{
+    model::*,
+    service::{NotificationContext, RequestContext},
+    ErrorData as McpError, RoleServer, ServerHandler, ServiceExt,
+};
+use serde_json::{Map, Value};
 
+use crate::agents::session::{Attachment, Notification, SessionManager};
+use crate::mcp::handlers::{HandlerState, MethodRouter};
+use crate::mcp::protocol::McpRequest;
+use crate::mcp::types::{AgentContext, AgentSession, RepoFile};
+use crate::rag::classify::FileClass;
 use crate::rag::client::RagClient;
+use crate::rag::jobs::{IndexJobRequest, JobError, JobQueue, JobState};
+use crate::rag::registry::{RepoRecord, RepoSettings};
+use crate::rag::watcher::RepoWatchers;
 use crate::tools::search::SearchTool;
 use crate::tools::filesystem::FilesystemTool;
 use crate::tools::code_context::ThreadsafeCodeContextTool;
 
-/// MCP Server for Cerebus-Rex that follows the Model Context Protocol specification
+/// Experimental capability used to hand out and resume session ids during `initialize`
+pub const SESSION_CAPABILITY: &str = "cerebus-rex/session";
+
+/// Names of the tools listed by `tools/list`; calls to any other name are rejected
+const TOOL_NAMES: &[&str] = &[
+    "search",
+    "filesystem_read",
+    "index_repo",
+    "initialize_context",
+    "watch_repo",
+    "watch_status",
+    "register_repo",
+    "list_repos",
+    "remove_repo",
+    "repo_config",
+    "index_status",
+    "index_cancel",
+    "index_jobs",
+    "index_files",
+    "get_context",
+];
+
+/// Resources served from the index, by URI, with the collection behind each
+const RESOURCES: &[(&str, &str)] = &[("cerebus-rex://docs", "documentation"), ("cerebus-rex://code", "code")];
+
+/// MCP Server for Cerebus-Rex that follows the Model Context Protocol specification.
+///
+/// Protocol handling is delegated to rmcp through [`ServerHandler`]; each
+/// connection gets its own clone via [`CerebusRexMcpServer::for_connection`]
+/// so it can track which session it is attached to.
+#[derive(Clone)]
 pub struct CerebusRexMcpServer {
-    rag_client: Arc<RwLock<RagClient>>,
+    state: Arc<HandlerState>,
+    router: Arc<MethodRouter>,
     search_tool: Arc<SearchTool>,
     filesystem_tool: Arc<FilesystemTool>,
-    context_tool: Arc<ThreadsafeCodeContextTool>,
+    watchers: Arc<RepoWatchers>,
+    jobs: Arc<JobQueue>,
+    sessions: Arc<SessionManager>,
+    connection: Arc<Mutex<Connection>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "method", content = "params")]
-pub enum McpRequest {
-    #[serde(rename = "tools/list")]
-    ListTools {},
-    #[serde(rename = "tools/call")]
-    CallTool {
-        name: String,
-        arguments: Option<HashMap<String, serde_json::Value>>,
-    },
-    #[serde(rename = "resources/list")]
-    ListResources {},
-    #[serde(rename = "resources/read")]
-    ReadResource {
-        uri: String,
-    },
-    #[serde(rename = "prompts/list")]
-    ListPrompts {},
-    #[serde(rename = "prompts/get")]
-    GetPrompt {
-        name: String,
-        arguments: Option<HashMap<String, serde_json::Value>>,
-    },
-    #[serde(rename = "server/info")]
-    GetServerInfo {},
+/// Per-connection state: the session the transport is currently attached to
+#[derive(Default)]
+struct Connection {
+    attachment: Option<Attachment>,
+    notifications: Option<mpsc::UnboundedReceiver<Notification>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(untagged)]
-pub enum McpResponse {
-    Success {
-        result: serde_json::Value,
-        id: Option<String>,
-    },
-    Error {
-        error: McpError,
-        id: Option<String>,
-    },
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct McpError {
-    pub code: i32,
-    pub message: String,
+/// Errors raised by the WebSocket transport adapter
+#[derive(Debug, thiserror::Error)]
+pub enum WebSocketTransportError {
+    #[error("WebSocket error: {0}")]
+    WebSocket(#[from] tungstenite::Error),
+    #[error("Failed to encode message: {0}")]
+    Encode(#[from] serde_json::Error),
 }
 
 impl CerebusRexMcpServer {
     pub fn new(
-        rag_client: RagClient,
+        rag_client: Arc<RwLock<RagClient>>,
         search_tool: SearchTool,
         filesystem_tool: FilesystemTool,
         context_tool: ThreadsafeCodeContextTool,
+        watchers: Arc<RepoWatchers>,
+        jobs: Arc<JobQueue>,
+        sessions: Arc<SessionManager>,
     ) -> Self {
+        let state = HandlerState {
+            rag_client,
+            context_tool: Arc::new(context_tool),
+        };
+
         Self {
-            rag_client: Arc::new(RwLock::new(rag_client)),
+            state: Arc::new(state),
+            router: Arc::new(MethodRouter::with_default_handlers()),
             search_tool: Arc::new(search_tool),
             filesystem_tool: Arc::new(filesystem_tool),
-            context_tool: Arc::new(context_tool),
+            watchers,
+            jobs,
+            sessions,
+            connection: Arc::new(Mutex::new(Connection::default())),
         }
     }
 
+    /// Tell subscribed sessions about the collections each completed job changed
+    pub fn spawn_job_notifications(&self) -> tokio::task::JoinHandle<()> {
+        let mut finished = self.jobs.subscribe();
+        let sessions = self.sessions.clone();
+        tokio::spawn(async move {
+            loop {
+                let job = match finished.recv().await {
+                    Ok(job) => job,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                };
+                if job.state != JobState::Completed {
+                    continue;
+                }
+                sessions.publish("cerebus-rex://code", Notification::resource_updated("cerebus-rex://code"));
+                if job.request.classes.contains(&FileClass::Documentation) {
+                    sessions.publish("cerebus-rex://docs", Notification::resource_updated("cerebus-rex://docs"));
+                }
+            }
+        })
+    }
+
+    /// Clone the server with fresh per-connection state
+    pub fn for_connection(&self) -> Self {
+        Self {
+            connection: Arc::new(Mutex::new(Connection::default())),
+            ..self.clone()
+        }
+    }
+
+    /// Serve MCP (JSON-RPC 2.0) over an accepted WebSocket, one message per text frame
     pub async fn serve_websocket(
         self: Arc<Self>,
         websocket: tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let (mut sender, mut receiver) = websocket.split();
-        
-        while let Some(msg) = receiver.next().await {
-            let msg = msg?;
-            if msg.is_text() || msg.is_binary() {
-                let text = msg.to_text().unwrap().to_string();
-                
-                // Parse the incoming request
-                let response = self.handle_request(&text).await;
-                
-                // Send the response
-                let response_text = serde_json::to_string(&response)?;
-                sender.send(Message::Text(response_text.into())).await?;
-            }
+        let (sink, stream) = websocket.split();
+
+        let sink = sink.with(|message: ServerJsonRpcMessage| {
+            futures::future::ready(
+                serde_json::to_string(&message)
+                    .map(|text| Message::Text(text.into()))
+                    .map_err(WebSocketTransportError::from),
+            )
+        });
+        let stream = stream.filter_map(|frame| {
+            futures::future::ready(match frame {
+                Ok(Message::Text(text)) => parse_client_message(text.as_str()),
+                Ok(Message::Binary(bytes)) => std::str::from_utf8(&bytes).ok().and_then(parse_client_message),
+                Ok(_) => None,
+                Err(e) => {
+                    tracing::debug!("WebSocket read error: {}", e);
+                    None
+                }
+            })
+        });
+
+        let handler = self.for_connection();
+        let connection = handler.connection.clone();
+        let running = handler.serve((sink, stream)).await?;
+        let quit_reason = running.waiting().await;
+
+        // Keep the session around so the client can resume after reconnecting
+        if let Some(attachment) = connection.lock().unwrap().attachment.take() {
+            self.sessions.detach(&attachment);
         }
-        
+
+        tracing::debug!("MCP connection closed: {:?}", quit_reason);
         Ok(())
     }
 
-    async fn handle_request(&self, request_str: &str) -> McpResponse {
-        match serde_json::from_str::<McpRequest>(request_str) {
-            Ok(request) => {
-                let id = extract_id(request_str); // Extract ID from original JSON
-                
-                match request {
-                    McpRequest::ListTools {} => {
-                        let tools = self.list_tools().await;
-                        McpResponse::Success {
-                            result: serde_json::json!(tools),
-                            id,
-                        }
-                    },
-                    McpRequest::CallTool { name, arguments } => {
-                        let result = self.call_tool(&name, arguments.unwrap_or_default()).await;
-                        match result {
-                            Ok(content) => McpResponse::Success {
-                                result: serde_json::json!({ "content": content }),
-                                id,
-                            },
-                            Err(e) => McpResponse::Error {
-                                error: McpError { code: -32000, message: e },
-                                id,
-                            },
-                        }
-                    },
-                    McpRequest::ListResources {} => {
-                        let resources = self.list_resources().await;
-                        McpResponse::Success {
-                            result: serde_json::json!(resources),
-                            id,
-                        }
-                    },
-                    McpRequest::ReadResource { uri } => {
-                        let content = self.read_resource(&uri).await;
-                        match content {
-                            Ok(content) => McpResponse::Success {
-                                result: serde_json::json!({ "content": content }),
-                                id,
-                            },
-                            Err(e) => McpResponse::Error {
-                                error: McpError { code: -32000, message: e },
-                                id,
-                            },
-                        }
-                    },
-                    McpRequest::ListPrompts {} => {
-                        let prompts = self.list_prompts().await;
-                        McpResponse::Success {
-                            result: serde_json::json!(prompts),
-                            id,
-                        }
-                    },
-                    McpRequest::GetPrompt { name, arguments } => {
-                        let prompt = self.get_prompt(&name, arguments.unwrap_or_default()).await;
-                        match prompt {
-                            Ok(prompt) => McpResponse::Success {
-                                result: serde_json::json!(prompt),
-                                id,
-                            },
-                            Err(e) => McpResponse::Error {
-                                error: McpError { code: -32000, message: e },
-                                id,
-                            },
-                        }
-                    },
-                    McpRequest::GetServerInfo {} => {
-                        let info = self.get_server_info().await;
-                        McpResponse::Success {
-                            result: serde_json::json!(info),
-                            id,
-                        }
-                    },
-                }
-            }
-            Err(e) => {
-                McpResponse::Error {
-                    error: McpError { 
-                        code: -32700, // Parse error
-                        message: format!("Invalid JSON: {}", e) 
-                    },
-                    id: extract_id(request_str),
-                }
+    /// Serve MCP over stdin/stdout for clients that spawn the server as a subprocess
+    pub async fn serve_stdio(self: Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        let handler = self.for_connection();
+        let connection = handler.connection.clone();
+        let running = handler.serve(rmcp::transport::stdio()).await?;
+        running.waiting().await?;
+
+        if let Some(attachment) = connection.lock().unwrap().attachment.take() {
+            self.sessions.detach(&attachment);
+        }
+
+        Ok(())
+    }
+
+    fn session_id(&self) -> Option<String> {
+        let connection = self.connection.lock().unwrap();
+        connection.attachment.as_ref().map(|attachment| attachment.session_id.clone())
+    }
+
+    /// Attach this connection to a resumed session, or open a new one
+    async fn attach_session(&self, request: &InitializeRequestParam) -> Map<String, Value> {
+        let resume_id = request
+            .capabilities
+            .experimental
+            .as_ref()
+            .and_then(|experimental| experimental.get(SESSION_CAPABILITY))
+            .and_then(|session| session.get("resume"))
+            .and_then(|id| id.as_str());
+
+        let resumed = match resume_id {
+            Some(id) => self.sessions.resume(id).await,
+            None => None,
+        };
+        if let Some((resumed, attachment, notifications)) = resumed {
+            if let Some(agent_id) = &resumed.agent_id {
+                self.sessions.coordinator().update_agent_presence(agent_id).await;
             }
+
+            let mut connection = self.connection.lock().unwrap();
+            connection.attachment = Some(attachment);
+            connection.notifications = Some(notifications);
+
+            let mut session = object(serde_json::json!(resumed));
+            session.insert("id".to_string(), Value::String(resumed.session_id));
+            session.insert("resumed".to_string(), Value::Bool(true));
+            return session;
         }
+
+        let (attachment, notifications) = self.sessions.open();
+        let session_id = attachment.session_id.clone();
+        let agent_id = self
+            .register_agent(&session_id, request.client_info.name.clone(), Vec::new())
+            .await;
+
+        let mut connection = self.connection.lock().unwrap();
+        connection.attachment = Some(attachment);
+        connection.notifications = Some(notifications);
+
+        object(serde_json::json!({
+            "id": session_id,
+            "agent_id": agent_id,
+            "resumed": false,
+            "resume_requested": resume_id.is_some(),
+        }))
     }
 
-    async fn list_tools(&self) -> Vec<Tool> {
+    fn tools(&self) -> Vec<Tool> {
         vec![
-            Tool {
-                name: "search".to_string(),
-                description: Some("Search documentation and code".to_string()),
-                input_schema: Some(serde_json::json!({
+            Tool::new(
+                "search",
+                "Search documentation and code",
+                object(serde_json::json!({
                     "type": "object",
                     "properties": {
                         "query": {
@@ -207,15 +261,67 @@ impl CerebusRexMcpServer {
                             "type": "string",
                             "description": "Search context (documentation, code, etc.)",
                             "enum": ["documentation", "code", "config"]
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": "Maximum number of results (default 5)",
+                            "minimum": 1
+                        },
+                        "score_threshold": {
+                            "type": "number",
+                            "description": "Minimum similarity score for a result to be returned"
+                        },
+                        "mode": {
+                            "type": "string",
+                            "description": "Retrieval strategy: embedding similarity, exact terms, or both fused (default)",
+                            "enum": ["dense", "sparse", "hybrid"]
+                        },
+                        "filter": {
+                            "type": "object",
+                            "description": "Only return results from files matching every given field",
+                            "properties": {
+                                "repo_id": {
+                                    "type": "string",
+                                    "description": "Repository identifier"
+                                },
+                                "language": {
+                                    "type": "string",
+                                    "description": "Language of the file, e.g. rust or markdown"
+                                },
+                                "path_prefix": {
+                                    "type": "string",
+                                    "description": "Directory or file the result must be under, e.g. src/rag"
+                                },
+                                "path_glob": {
+                                    "type": "string",
+                                    "description": "Glob the file path must match, e.g. src/**/*.rs"
+                                },
+                                "extension": {
+                                    "type": "string",
+                                    "description": "File extension, e.g. rs"
+                                },
+                                "modified_after": {
+                                    "type": "string",
+                                    "format": "date-time",
+                                    "description": "Only files modified after this RFC 3339 time"
+                                },
+                                "payload": {
+                                    "type": "object",
+                                    "description": "Payload fields that must equal the given string, integer or boolean",
+                                    "additionalProperties": {
+                                        "type": ["string", "integer", "boolean"]
+                                    }
+                                }
+                            }
                         }
                     },
                     "required": ["query"]
                 })),
-            },
-            Tool {
-                name: "filesystem_read".to_string(),
-                description: Some("Read a file from the filesystem".to_string()),
-                input_schema: Some(serde_json::json!({
+            ),
+            Tool::new(
+                "filesystem_read",
+                "Read a file from the filesystem",
+                object(serde_json::json!({
                     "type": "object",
                     "properties": {
                         "path": {
@@ -225,56 +331,296 @@ impl CerebusRexMcpServer {
                     },
                     "required": ["path"]
                 })),
-            },
-            Tool {
-                name: "index_repo".to_string(),
-                description: Some("Index repository files for quick access".to_string()),
-                input_schema: Some(serde_json::json!({
+            ),
+            Tool::new(
+                "index_repo",
+                "Index repository files for quick access",
+                object(serde_json::json!({
                     "type": "object",
                     "properties": {
                         "repo_id": {
                             "type": "string",
                             "description": "Repository identifier"
                         },
+                        "repo_path": {
+                            "type": "string",
+                            "description": "Repository root the file paths are relative to (defaults to the root registered by initialize_context)"
+                        },
                         "files": {
                             "type": "array",
                             "items": {
-                                "type": "string"
+                                "oneOf": [
+                                    { "type": "string" },
+                                    {
+                                        "type": "object",
+                                        "properties": {
+                                            "path": { "type": "string" },
+                                            "content": {
+                                                "type": "string",
+                                                "description": "Content to index instead of the file on disk, e.g. an unsaved buffer"
+                                            },
+                                            "modified_at": {
+                                                "type": "string",
+                                                "description": "Modification time of inline content (RFC 3339); defaults to now"
+                                            }
+                                        },
+                                        "required": ["path"]
+                                    }
+                                ]
                             },
-                            "description": "List of file paths to index"
+                            "description": "Files to index: paths, or objects with a path and optional inline content"
                         }
                     },
                     "required": ["repo_id", "files"]
                 })),
-            },
-            Tool {
-                name: "initialize_context".to_string(),
-                description: Some("Initialize AI agent context by indexing repository and documentation".to_string()),
-                input_schema: Some(serde_json::json!({
+            ),
+            Tool::new(
+                "initialize_context",
+                "Initialize AI agent context by queueing a background job that indexes a repository and its documentation",
+                object(serde_json::json!({
                     "type": "object",
                     "properties": {
                         "repo_path": {
                             "type": "string",
-                            "description": "Path to repository to index"
+                            "description": "Path to repository to index (optional when repo_id is registered)"
+                        },
+                        "repo_id": {
+                            "type": "string",
+                            "description": "Repository identifier (defaults to the id the checkout is registered under, or one named after its remote or directory)"
                         },
                         "include_docs": {
                             "type": "boolean",
-                            "description": "Whether to index documentation files",
-                            "default": true
+                            "description": "Whether to index documentation files (defaults to the repository's saved settings, else true)"
                         },
                         "include_code": {
                             "type": "boolean",
-                            "description": "Whether to index code files",
+                            "description": "Whether to index code files (defaults to the repository's saved settings, else true)"
+                        },
+                        "include_config": {
+                            "type": "boolean",
+                            "description": "Whether to index configuration and build files (defaults to the repository's saved settings, else true)"
+                        },
+                        "include": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Only index files matching one of these globs, e.g. src/**"
+                        },
+                        "exclude": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Skip files matching any of these globs, in addition to .gitignore and .ignore"
+                        },
+                        "watch": {
+                            "type": "boolean",
+                            "description": "Keep the index current by re-indexing files as they change (defaults to the server's indexing.watch setting)"
+                        },
+                        "rev": {
+                            "type": "string",
+                            "description": "Index this git branch, tag or commit from the repository's object database instead of the working tree"
+                        },
+                        "base_rev": {
+                            "type": "string",
+                            "description": "With rev, only re-index files changed between this revision and rev; defaults to the revision last indexed. Ignored unless the index is at this revision"
+                        }
+                    }
+                })),
+            ),
+            Tool::new(
+                "watch_repo",
+                "Start or stop re-indexing an indexed repository's files as they change",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "repo_id": {
+                            "type": "string",
+                            "description": "Identifier of a registered repository"
+                        },
+                        "enabled": {
+                            "type": "boolean",
+                            "description": "Whether to watch the repository",
                             "default": true
                         }
                     },
+                    "required": ["repo_id"]
+                })),
+            ),
+            Tool::new(
+                "watch_status",
+                "Report which repositories are watched and their latest re-indexing",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {}
+                })),
+            ),
+            Tool::new(
+                "register_repo",
+                "Register a repository checkout under a stable id, with the settings it is indexed with",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "repo_path": {
+                            "type": "string",
+                            "description": "Path to the repository checkout"
+                        },
+                        "repo_id": {
+                            "type": "string",
+                            "description": "Identifier to register the repository under (defaults to its existing id, or one named after its remote or directory)"
+                        },
+                        "include_docs": {
+                            "type": "boolean",
+                            "description": "Whether to index documentation files"
+                        },
+                        "include_code": {
+                            "type": "boolean",
+                            "description": "Whether to index code files"
+                        },
+                        "include_config": {
+                            "type": "boolean",
+                            "description": "Whether to index configuration and build files"
+                        },
+                        "include": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Only index files matching one of these globs"
+                        },
+                        "exclude": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Skip files matching any of these globs"
+                        },
+                        "watch": {
+                            "type": "boolean",
+                            "description": "Whether initialize_context keeps the repository watched"
+                        }
+                    },
                     "required": ["repo_path"]
                 })),
-            },
+            ),
+            Tool::new(
+                "list_repos",
+                "List registered repositories with their settings and last indexing",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {}
+                })),
+            ),
+            Tool::new(
+                "remove_repo",
+                "Forget a repository and delete everything indexed from it",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "repo_id": {
+                            "type": "string",
+                            "description": "Identifier of the repository to remove"
+                        }
+                    },
+                    "required": ["repo_id"]
+                })),
+            ),
+            Tool::new(
+                "repo_config",
+                "Report a repository's effective indexing configuration, and explain whether and why a file is indexed",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "repo_id": {
+                            "type": "string",
+                            "description": "Identifier of a registered repository"
+                        },
+                        "repo_path": {
+                            "type": "string",
+                            "description": "Path to the repository checkout (optional when repo_id is registered)"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Repository file to explain, relative to its root"
+                        }
+                    }
+                })),
+            ),
+            Tool::new(
+                "index_status",
+                "Report the state and outcome of an indexing job",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "job_id": {
+                            "type": "string",
+                            "description": "Job identifier returned by initialize_context"
+                        }
+                    },
+                    "required": ["job_id"]
+                })),
+            ),
+            Tool::new(
+                "index_cancel",
+                "Cancel a queued or running indexing job",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "job_id": {
+                            "type": "string",
+                            "description": "Job identifier returned by initialize_context"
+                        }
+                    },
+                    "required": ["job_id"]
+                })),
+            ),
+            Tool::new(
+                "index_jobs",
+                "List recent indexing jobs, oldest first",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {}
+                })),
+            ),
+            Tool::new(
+                "index_files",
+                "Index files whose content is supplied inline, such as unsaved editor buffers",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "repo_id": {
+                            "type": "string",
+                            "description": "Repository identifier"
+                        },
+                        "files": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "path": { "type": "string" },
+                                    "content": { "type": "string" },
+                                    "language": { "type": "string" },
+                                    "hash": { "type": "string" }
+                                },
+                                "required": ["path", "content", "hash"]
+                            },
+                            "description": "Files to index"
+                        }
+                    },
+                    "required": ["repo_id", "files"]
+                })),
+            ),
+            Tool::new(
+                "get_context",
+                "Read a value from the shared code context store",
+                object(serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "key": {
+                            "type": "string",
+                            "description": "Context key"
+                        }
+                    },
+                    "required": ["key"]
+                })),
+            ),
         ]
     }
 
-    async fn call_tool(&self, name: &str, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value, String> {
+    async fn execute_tool(&self, name: &str, arguments: Map<String, Value>) -> Result<Value, String> {
         match name {
             "search" => {
                 let query = arguments
@@ -289,25 +635,37 @@ impl CerebusRexMcpServer {
                     .unwrap_or("documentation") // default context
                     .to_string();
 
-                // Create a search request for our search tool
+                let limit = arguments
+                    .get("limit")
+                    .and_then(|v| v.as_u64())
+                    .map(|limit| limit as u32);
+
+                let score_threshold = arguments
+                    .get("score_threshold")
+                    .and_then(|v| v.as_f64())
+                    .map(|threshold| threshold as f32);
+
+                let mode = arguments
+                    .get("mode")
+                    .map(|v| serde_json::from_value(v.clone()))
+                    .transpose()
+                    .map_err(|_| "Invalid mode parameter; expected dense, sparse or hybrid".to_string())?;
+
+                let filter = arguments
+                    .get("filter")
+                    .map(|v| serde_json::from_value(v.clone()))
+                    .transpose()
+                    .map_err(|e| format!("Invalid filter parameter: {}", e))?;
+
                 let search_request = crate::tools::search::SearchRequest {
                     query,
                     context: Some(context),
-                    limit: Some(5), // reasonable default
+                    limit: limit.or(Some(5)), // reasonable default
+                    score_threshold,
+                    mode,
+                    filter,
                 };
-
-                match self.search_tool.execute(search_request).await {
-                    Ok(results) => {
-                        let mut output = String::new();
-                        for result in results.results {
-                            output.push_str(&format!("- Source: {}\n", result.source));
-                            output.push_str(&format!("  Content: {}\n", result.content));
-                            output.push_str(&format!("  Score: {}\n\n", result.score));
-                        }
-                        Ok(serde_json::json!(output))
-                    }
-                    Err(e) => Err(e.to_string()),
-                }
+                self.search(search_request).await.map(Value::String)
             }
             "filesystem_read" => {
                 let path = arguments
@@ -331,174 +689,336 @@ impl CerebusRexMcpServer {
                     .and_then(|v| v.as_array())
                     .ok_or_else(|| "Missing files parameter as array".to_string())?;
 
-                let files: Vec<String> = files_array
+                let files = files_array
                     .iter()
-                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                    .collect();
+                    .map(|v| match v {
+                        Value::String(path) => Ok(RepoFile {
+                            path: path.clone(),
+                            content: None,
+                            modified_at: None,
+                        }),
+                        _ => serde_json::from_value(v.clone()).map_err(|e| format!("Invalid file entry: {}", e)),
+                    })
+                    .collect::<Result<Vec<RepoFile>, String>>()?;
 
-                // Convert files to the format expected by the RAG client
-                let files_with_content: Vec<(String, String)> = files.iter()
-                    .map(|file_path| (file_path.clone(), "content placeholder".to_string()))
-                    .collect();
+                if let Some(repo_path) = arguments.get("repo_path").and_then(|v| v.as_str()) {
+                    let registered = self.search_tool.register_root(&repo_id, Path::new(repo_path)).await;
+                    registered.map_err(|e| e.to_string())?;
+                }
 
-                match self.search_tool.index_repo_files(&repo_id, files_with_content).await {
-                    Ok(_) => Ok(serde_json::json!({"status": "indexed", "repo_id": repo_id})),
+                match self.search_tool.index_repo_paths(&repo_id, files).await {
+                    Ok((report, failures)) => {
+                        self.sessions.publish("cerebus-rex://code", Notification::resource_updated("cerebus-rex://code"));
+                        Ok(serde_json::json!({
+                            "status": if failures.is_empty() { "indexed" } else { "partially_indexed" },
+                            "repo_id": repo_id,
+                            "added": report.added,
+                            "updated": report.updated,
+                            "removed": report.removed,
+                            "unchanged": report.unchanged,
+                            "failed": failures,
+                        }))
+                    },
                     Err(e) => Err(e.to_string()),
                 }
             }
             "initialize_context" => {
-                let repo_path = arguments
-                    .get("repo_path")
+                let repo_id = arguments.get("repo_id").and_then(|v| v.as_str());
+
+                // A registered repository is indexed with its saved settings
+                // unless the call overrides them
+                let (registered, root) = self.find_repo(&arguments).await?;
+                let defaults = match &registered {
+                    Some(repo) => repo.settings.clone(),
+                    None => RepoSettings {
+                        watch: self.watchers.watch_by_default(),
+                        ..Default::default()
+                    },
+                };
+                let settings = repo_settings(&arguments, defaults);
+
+                let rev = arguments.get("rev").and_then(|v| v.as_str()).map(str::to_string);
+                let base_rev = arguments.get("base_rev").and_then(|v| v.as_str()).map(str::to_string);
+                if base_rev.is_some() && rev.is_none() {
+                    return Err("base_rev requires rev".to_string());
+                }
+
+                let repo = self
+                    .search_tool
+                    .register_repo(repo_id, &root, Some(settings.clone()))
+                    .await
+                    .map_err(|e| e.to_string())?;
+
+                // A revision does not change as the working tree does, so there is nothing to watch
+                let watch = rev.is_none() && settings.watch;
+
+                // Indexing runs as a background job; poll index_status for its outcome
+                let request = IndexJobRequest {
+                    repo_id: repo.id,
+                    repo_path: repo.root.display().to_string(),
+                    classes: settings.classes.clone(),
+                    include: settings.include,
+                    exclude: settings.exclude,
+                    rev,
+                    base_rev,
+                    watch,
+                };
+                match self.jobs.submit(request).await {
+                    Ok(job) => Ok(serde_json::json!({
+                        "status": "queued",
+                        "job_id": job.id,
+                        "repo_id": job.request.repo_id,
+                        "included_docs": settings.classes.contains(&FileClass::Documentation),
+                        "included_code": settings.classes.contains(&FileClass::Code),
+                        "included_config": settings.classes.contains(&FileClass::Config),
+                        "watch": watch,
+                        "message": "Indexing queued. Poll index_status with the job_id to follow it."
+                    })),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            "watch_repo" => {
+                let repo_id = arguments
+                    .get("repo_id")
                     .and_then(|v| v.as_str())
-                    .ok_or_else(|| "Missing repo_path parameter".to_string())?
+                    .ok_or_else(|| "Missing repo_id parameter".to_string())?
                     .to_string();
 
-                let include_docs = arguments
-                    .get("include_docs")
+                let enabled = arguments
+                    .get("enabled")
                     .and_then(|v| v.as_bool())
                     .unwrap_or(true);
 
-                let include_code = arguments
-                    .get("include_code")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(true);
+                let repo = self
+                    .search_tool
+                    .repo(&repo_id)
+                    .await
+                    .ok_or_else(|| format!("Repository {} is not registered", repo_id))?;
 
-                // Perform repository indexing
-                let mut files_to_index = Vec::new();
-
-                if include_docs {
-                    // Add common documentation file patterns
-                    files_to_index.extend(vec![
-                        format!("{}/README.md", repo_path),
-                        format!("{}/CHANGELOG.md", repo_path),
-                        format!("{}/CONTRIBUTING.md", repo_path),
-                        format!("{}/LICENSE", repo_path),
-                    ]);
+                // The choice is saved, so the next initialize_context keeps it
+                let settings = RepoSettings {
+                    watch: enabled,
+                    ..repo.settings
+                };
+                let registered = self.search_tool.register_repo(Some(&repo_id), &repo.root, Some(settings.clone())).await;
+                registered.map_err(|e| e.to_string())?;
+
+                if !enabled {
+                    let was_watching = self.watchers.unwatch(&repo_id);
+                    return Ok(serde_json::json!({
+                        "repo_id": repo_id,
+                        "watching": false,
+                        "was_watching": was_watching,
+                    }));
                 }
 
-                if include_code {
-                    // Add common code file patterns (this is a simplified example)
-                    // In a real implementation, this would scan the directory for code files
-                    files_to_index.extend(vec![
-                        format!("{}/src/main.rs", repo_path),
-                        format!("{}/src/lib.rs", repo_path),
-                    ]);
+                match self.watchers.watch(&repo_id, &settings.include, &settings.exclude, settings.classes).await {
+                    Ok(status) => Ok(serde_json::json!({
+                        "repo_id": repo_id,
+                        "watching": true,
+                        "status": status,
+                    })),
+                    Err(e) => Err(e.to_string()),
                 }
+            }
+            "watch_status" => Ok(serde_json::json!({ "watchers": self.watchers.statuses() })),
+            "index_status" => {
+                let job_id = arguments
+                    .get("job_id")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| "Missing job_id parameter".to_string())?;
 
-                // Process the gathered files
-                let repo_id = format!("repo_{}", uuid::Uuid::new_v4());
-                let files_with_content: Vec<(String, String)> = files_to_index
-                    .iter()
-                    .filter(|file_path| std::path::Path::new(file_path).exists())
-                    .map(|file_path| {
-                        let content = match std::fs::read_to_string(file_path) {
-                            Ok(content) => content,
-                            Err(_) => "Could not read file content".to_string(),
-                        };
-                        (file_path.clone(), content)
-                    })
-                    .collect();
+                match self.jobs.status(job_id) {
+                    Some(job) => serde_json::to_value(job).map_err(|e| e.to_string()),
+                    None => Err(JobError::NotFound(job_id.to_string()).to_string()),
+                }
+            }
+            "index_cancel" => {
+                let job_id = arguments
+                    .get("job_id")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| "Missing job_id parameter".to_string())?;
 
-                let indexed_files_count = files_with_content.len();
+                match self.jobs.cancel(job_id).await {
+                    Ok(job) => serde_json::to_value(job).map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            "index_jobs" => Ok(serde_json::json!({ "jobs": self.jobs.jobs() })),
+            "register_repo" => {
+                let repo_path = arguments
+                    .get("repo_path")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| "Missing repo_path parameter".to_string())?;
+                let repo_id = arguments.get("repo_id").and_then(|v| v.as_str());
 
-                // Index the repository files
-                match self.search_tool.index_repo_files(&repo_id, files_with_content).await {
-                    Ok(_) => {
-                        // Also initialize other context elements here
-                        Ok(serde_json::json!({
-                            "status": "context_initialized",
-                            "repo_id": repo_id,
-                            "indexed_files_count": indexed_files_count,
-                            "included_docs": include_docs,
-                            "included_code": include_code,
-                            "message": "Context initialized successfully. Repository indexed for semantic search."
-                        }))
+                let root = Path::new(repo_path);
+                let registered = match repo_id {
+                    Some(repo_id) => self.search_tool.repo(repo_id).await,
+                    None => self.search_tool.repo_at(root).await,
+                };
+                let defaults = match registered {
+                    Some(repo) => repo.settings,
+                    None => RepoSettings {
+                        watch: self.watchers.watch_by_default(),
+                        ..Default::default()
                     },
-                    Err(e) => Err(e.to_string()),
+                };
+                let settings = repo_settings(&arguments, defaults);
+
+                let repo = self
+                    .search_tool
+                    .register_repo(repo_id, root, Some(settings))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                // A running watcher picks up the new selection
+                if self.watchers.is_watching(&repo.id) {
+                    let settings = &repo.settings;
+                    let watched = self.watchers.watch(&repo.id, &settings.include, &settings.exclude, settings.classes.clone()).await;
+                    watched.map_err(|e| e.to_string())?;
+                }
+                serde_json::to_value(repo).map_err(|e| e.to_string())
+            }
+            "list_repos" => Ok(serde_json::json!({ "repos": self.search_tool.repos().await })),
+            "repo_config" => {
+                let repo_id = arguments.get("repo_id").and_then(|v| v.as_str());
+                let (registered, root) = self.find_repo(&arguments).await?;
+                let config = self.search_tool.repo_config(&root).await.map_err(|e| e.to_string())?;
+
+                // Explain one file with the settings the repository is indexed with
+                let file = match arguments.get("path").and_then(|v| v.as_str()) {
+                    Some(path) => {
+                        let settings = registered.as_ref().map(|repo| repo.settings.clone()).unwrap_or_default();
+                        let id = registered.as_ref().map(|repo| repo.id.as_str()).or(repo_id).unwrap_or_default();
+                        let explained = self.search_tool.explain_path(id, &root, path, &settings).await;
+                        Some(explained.map_err(|e| e.to_string())?)
+                    }
+                    None => None,
+                };
+
+                Ok(serde_json::json!({
+                    "repo_id": registered.as_ref().map(|repo| &repo.id),
+                    "root": root,
+                    "settings": registered.as_ref().map(|repo| &repo.settings),
+                    "config": config,
+                    "file": file,
+                }))
+            }
+            "remove_repo" => {
+                let repo_id = arguments
+                    .get("repo_id")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| "Missing repo_id parameter".to_string())?
+                    .to_string();
+
+                // Stop anything that would index the repository again
+                let was_watching = self.watchers.unwatch(&repo_id);
+                let mut cancelled_jobs = Vec::new();
+                for job in self.jobs.jobs() {
+                    if job.request.repo_id == repo_id && !job.state.is_finished() && self.jobs.cancel(&job.id).await.is_ok() {
+                        cancelled_jobs.push(job.id);
+                    }
                 }
+
+                let removed = self.search_tool.remove_repo(&repo_id).await;
+                let (report, repo) = removed.map_err(|e| e.to_string())?;
+                if repo.is_none() && report.removed == 0 {
+                    return Err(format!("Repository {} is not registered", repo_id));
+                }
+                self.sessions.publish("cerebus-rex://code", Notification::resource_updated("cerebus-rex://code"));
+                self.sessions.publish("cerebus-rex://docs", Notification::resource_updated("cerebus-rex://docs"));
+
+                Ok(serde_json::json!({
+                    "status": "removed",
+                    "repo_id": repo_id,
+                    "removed": report.removed,
+                    "was_registered": repo.is_some(),
+                    "was_watching": was_watching,
+                    "cancelled_jobs": cancelled_jobs,
+                }))
             }
+            name if self.router.handles(name) => self.dispatch(name, arguments).await,
             _ => Err(format!("Unknown tool: {}", name)),
         }
     }
 
-    async fn list_resources(&self) -> Vec<ResourceTemplate> {
-        vec![
-            ResourceTemplate {
-                uri_template: "cerebus-rex://docs".to_string(),
-                name: "Documentation".to_string(),
-                description: Some("Documentation resources from RAG system".to_string()),
-            },
-            ResourceTemplate {
-                uri_template: "cerebus-rex://code".to_string(),
-                name: "Code".to_string(),
-                description: Some("Code resources from repository".to_string()),
-            },
-        ]
+    /// The registered repository a call names by `repo_id` or `repo_path`,
+    /// and the checkout directory to use: `repo_path` if given, else the
+    /// registered root
+    async fn find_repo(&self, arguments: &Map<String, Value>) -> Result<(Option<RepoRecord>, PathBuf), String> {
+        let repo_id = arguments.get("repo_id").and_then(|v| v.as_str());
+        let repo_path = arguments.get("repo_path").and_then(|v| v.as_str());
+        let registered = match (repo_id, repo_path) {
+            (Some(repo_id), _) => self.search_tool.repo(repo_id).await,
+            (None, Some(repo_path)) => self.search_tool.repo_at(Path::new(repo_path)).await,
+            (None, None) => return Err("Missing repo_path parameter".to_string()),
+        };
+        let root = match (repo_path, &registered) {
+            (Some(repo_path), _) => PathBuf::from(repo_path),
+            (None, Some(repo)) => repo.root.clone(),
+            (None, None) => {
+                return Err(format!("Repository {} is not registered; pass repo_path", repo_id.unwrap_or_default()))
+            }
+        };
+        Ok((registered, root))
     }
 
-    async fn read_resource(&self, uri: &str) -> Result<String, String> {
-        match uri {
-            uri if uri.starts_with("cerebus-rex://docs") => {
-                Ok("Documentation content from RAG system".to_string())
-            },
-            uri if uri.starts_with("cerebus-rex://code") => {
-                Ok("Code content from repository".to_string())
-            },
-            _ => Err(format!("Resource not found: {}", uri)),
+    /// Route a tool call through the request handler stack
+    async fn dispatch(&self, method: &str, arguments: Map<String, Value>) -> Result<Value, String> {
+        let request = McpRequest::new(method.to_string(), Some(Value::Object(arguments)), None);
+        let response = self.router.dispatch(&self.state, request).await;
+
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(error.message),
+            (Some(result), None) => Ok(result),
+            (None, None) => Ok(Value::Null),
         }
     }
 
-    async fn list_prompts(&self) -> Vec<PromptTemplate> {
-        vec![
-            PromptTemplate {
-                name: "code_search".to_string(),
-                description: Some("Search for code snippets".to_string()),
-                parameters: Some(serde_json::json!({
-                    "type": "object",
-                    "properties": {
-                        "query": {
-                            "type": "string",
-                            "description": "Search query"
-                        }
-                    },
-                    "required": ["query"]
-                })),
+    /// Run a search and render the results as text for the agent
+    async fn search(&self, search_request: crate::tools::search::SearchRequest) -> Result<String, String> {
+        match self.search_tool.execute(search_request).await {
+            Ok(results) => {
+                let mut output = String::new();
+                for result in results.results {
+                    output.push_str(&format!("- Source: {}\n", result.source));
+                    output.push_str(&format!("  Content: {}\n", result.content));
+                    output.push_str(&format!("  Score: {}\n\n", result.score));
+                }
+                Ok(output)
             }
-        ]
-    }
-
-    async fn get_prompt(&self, name: &str, _arguments: HashMap<String, serde_json::Value>) -> Result<Prompt, String> {
-        match name {
-            "code_search" => {
-                Ok(Prompt {
-                    messages: vec![MessageContent {
-                        role: "user".to_string(),
-                        content: "Search Results:\nCode search results here".to_string(),
-                    }],
-                })
-            },
-            _ => Err(format!("Prompt not found: {}", name)),
+            Err(e) => Err(e.to_string()),
         }
     }
 
-    async fn get_server_info(&self) -> ServerInfo {
-        ServerInfo {
-            name: "Cerebus-Rex MCP Server".to_string(),
-            version: env!("CARGO_PKG_VERSION").to_string(),
-            capabilities: Capabilities {
-                list_resources: true,
-                read_resource: true,
-                list_prompts: true,
-                get_prompt: true,
-                list_tools: true,
-                call_tool: true,
+    async fn register_agent(&self, session_id: &str, name: String, capabilities: Vec<String>) -> String {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let agent = AgentSession {
+            id: String::new(), // Assigned by the coordinator
+            name: name.clone(),
+            capabilities,
+            last_seen: now,
+            context: AgentContext {
+                id: session_id.to_string(),
+                name,
+                data: std::collections::HashMap::new(),
+                created_at: now,
+                updated_at: now,
             },
-        }
+        };
+
+        let agent_id = self.sessions.coordinator().register_agent(agent).await;
+        self.sessions.set_agent(session_id, agent_id.clone());
+        agent_id
     }
 
     async fn read_filesystem(&self, path: &str) -> Result<String, String> {
         use crate::tools::filesystem::{FileOperationRequest, FileOperationResponse};
-        
+
         let fs_request = FileOperationRequest {
             operation: "read".to_string(),
             path: path.to_string(),
@@ -531,65 +1051,485 @@ impl CerebusRexMcpServer {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Tool {
-    pub name: String,
-    pub description: Option<String>,
-    pub input_schema: Option<serde_json::Value>,
-}
+impl ServerHandler for CerebusRexMcpServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            protocol_version: ProtocolVersion::default(),
+            capabilities: ServerCapabilities::builder()
+                .enable_experimental()
+                .enable_prompts()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_tools()
+                .build(),
+            server_info: Implementation {
+                name: "Cerebus-Rex MCP Server".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                ..Implementation::from_build_env()
+            },
+            instructions: Some(
+                "Use `initialize_context` to index a repository, then `search` to query its documentation and code."
+                    .to_string(),
+            ),
+        }
+    }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct ResourceTemplate {
-    pub uri_template: String,
-    pub name: String,
-    pub description: Option<String>,
-}
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, McpError> {
+        let session = self.attach_session(&request).await;
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct PromptTemplate {
-    pub name: String,
-    pub description: Option<String>,
-    pub parameters: Option<serde_json::Value>,
-}
+        let mut info = self.get_info();
+        info.capabilities
+            .experimental
+            .get_or_insert_with(Default::default)
+            .insert(SESSION_CAPABILITY.to_string(), session);
+        Ok(info)
+    }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Prompt {
-    pub messages: Vec<MessageContent>,
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        // Forward session notifications (including any replayed after a resume)
+        // once the client has finished the handshake
+        let Some(mut notifications) = self.connection.lock().unwrap().notifications.take() else {
+            return;
+        };
+        let peer = context.peer;
+        tokio::spawn(async move {
+            while let Some(notification) = notifications.recv().await {
+                let notification: ServerNotification = match serde_json::to_value(&notification)
+                    .and_then(serde_json::from_value)
+                {
+                    Ok(notification) => notification,
+                    Err(e) => {
+                        tracing::warn!("Dropping unsupported notification {}: {}", notification.method, e);
+                        continue;
+                    }
+                };
+                if peer.send_notification(notification).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(self.tools()))
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        if !TOOL_NAMES.contains(&request.name.as_ref()) {
+            return Err(McpError::invalid_params(format!("Unknown tool: {}", request.name), None));
+        }
+
+        match self.execute_tool(&request.name, request.arguments.unwrap_or_default()).await {
+            Ok(Value::String(text)) => Ok(CallToolResult::success(vec![Content::text(text)])),
+            Ok(value) => Ok(CallToolResult::structured(value)),
+            // Tool failures are reported in the result so the agent can see them
+            Err(e) => Ok(CallToolResult::error(vec![Content::text(e)])),
+        }
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let resources = vec![
+            RawResource {
+                description: Some(
+                    "Indexed documentation files; read cerebus-rex://docs/<repo_id>/<path> for a file's chunks".to_string(),
+                ),
+                ..RawResource::new("cerebus-rex://docs", "Documentation")
+            }
+            .no_annotation(),
+            RawResource {
+                description: Some(
+                    "Indexed code files; read cerebus-rex://code/<repo_id>/<path> for a file's chunks".to_string(),
+                ),
+                ..RawResource::new("cerebus-rex://code", "Code")
+            }
+            .no_annotation(),
+        ];
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let not_found = || McpError::resource_not_found(format!("Resource not found: {}", request.uri), None);
+        let internal = |e: Box<dyn std::error::Error>| McpError::internal_error(e.to_string(), None);
+        let (base, collection, rest) = RESOURCES
+            .iter()
+            .find_map(|(base, collection)| Some((*base, *collection, request.uri.strip_prefix(base)?)))
+            .ok_or_else(not_found)?;
+
+        let rag_client = self.state.rag_client.read().await;
+        let text = match rest {
+            // The collection: every file indexed into it, with its own URI
+            "" => {
+                let paths = rag_client.indexed_paths(collection).await.map_err(internal)?;
+                let files: Vec<Value> = paths
+                    .into_iter()
+                    .map(|(repo_id, path)| {
+                        serde_json::json!({
+                            "uri": format!("{}/{}/{}", base, repo_id, path),
+                            "repo_id": repo_id,
+                            "path": path,
+                        })
+                    })
+                    .collect();
+                serde_json::to_string_pretty(&files).map_err(|e| McpError::internal_error(e.to_string(), None))?
+            }
+            // One file: its indexed chunks in order
+            rest => {
+                let (repo_id, path) = rest.strip_prefix('/').and_then(|file| file.split_once('/')).ok_or_else(not_found)?;
+                match rag_client.indexed_chunks(repo_id, path).await.map_err(internal)? {
+                    Some((stored, chunks)) if stored == collection => chunks.join("\n\n"),
+                    _ => return Err(not_found()),
+                }
+            }
+        };
+
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::text(text, request.uri)],
+        })
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let session_id = self
+            .session_id()
+            .ok_or_else(|| McpError::invalid_request("Session not initialized", None))?;
+        self.sessions.subscribe(&session_id, &request.uri);
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let session_id = self
+            .session_id()
+            .ok_or_else(|| McpError::invalid_request("Session not initialized", None))?;
+        self.sessions.unsubscribe(&session_id, &request.uri);
+        Ok(())
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        let prompts = vec![Prompt::new(
+            "code_search",
+            Some("Search for code snippets"),
+            Some(vec![PromptArgument {
+                name: "query".to_string(),
+                title: None,
+                description: Some("Search query".to_string()),
+                required: Some(true),
+            }]),
+        )];
+        Ok(ListPromptsResult::with_all_items(prompts))
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        match request.name.as_str() {
+            "code_search" => {
+                let query = request
+                    .arguments
+                    .as_ref()
+                    .and_then(|arguments| arguments.get("query"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| McpError::invalid_params("Missing query argument", None))?
+                    .to_string();
+
+                let search_request = crate::tools::search::SearchRequest {
+                    query: query.clone(),
+                    context: Some("code".to_string()),
+                    limit: Some(5),
+                    score_threshold: None,
+                    mode: None,
+                    filter: None,
+                };
+                let results = self
+                    .search(search_request)
+                    .await
+                    .unwrap_or_else(|e| format!("Search unavailable: {}", e));
+
+                Ok(GetPromptResult {
+                    description: Some(format!("Code search results for: {}", query)),
+                    messages: vec![PromptMessage::new_text(
+                        PromptMessageRole::User,
+                        format!("Search Results:\n{}", results),
+                    )],
+                })
+            },
+            name => Err(McpError::invalid_params(format!("Prompt not found: {}", name), None)),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct MessageContent {
-    pub role: String,
-    pub content: String,
+/// String items of an array argument; missing or non-string items are ignored
+fn string_array(arguments: &Map<String, Value>, key: &str) -> Vec<String> {
+    arguments
+        .get(key)
+        .and_then(|v| v.as_array())
+        .map(|items| items.iter().filter_map(|item| item.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct ServerInfo {
-    pub name: String,
-    pub version: String,
-    pub capabilities: Capabilities,
+/// Settings for indexing a repository from tool arguments, falling back to
+/// `defaults` for those not given
+fn repo_settings(arguments: &Map<String, Value>, defaults: RepoSettings) -> RepoSettings {
+    let flag = |key: &str, class: FileClass| {
+        arguments
+            .get(key)
+            .and_then(|v| v.as_bool())
+            .unwrap_or_else(|| defaults.classes.contains(&class))
+    };
+    let classes = [
+        ("include_docs", FileClass::Documentation),
+        ("include_code", FileClass::Code),
+        ("include_config", FileClass::Config),
+    ]
+    .into_iter()
+    .filter(|(key, class)| flag(key, *class))
+    .map(|(_, class)| class)
+    .collect();
+
+    let globs = |key: &str, defaults: &[String]| {
+        if arguments.contains_key(key) {
+            string_array(arguments, key)
+        } else {
+            defaults.to_vec()
+        }
+    };
+    RepoSettings {
+        classes,
+        include: globs("include", &defaults.include),
+        exclude: globs("exclude", &defaults.exclude),
+        watch: arguments.get("watch").and_then(|v| v.as_bool()).unwrap_or(defaults.watch),
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Capabilities {
-    pub list_resources: bool,
-    pub read_resource: bool,
-    pub list_prompts: bool,
-    pub get_prompt: bool,
-    pub list_tools: bool,
-    pub call_tool: bool,
+// Helper function to decode a client JSON-RPC message from a WebSocket frame
+fn parse_client_message(text: &str) -> Option<ClientJsonRpcMessage> {
+    match serde_json::from_str(text) {
+        Ok(message) => Some(message),
+        Err(e) => {
+            tracing::warn!("Ignoring invalid MCP message: {}", e);
+            None
+        }
+    }
 }
 
-// Helper function to extract request ID from JSON string
-fn extract_id(request_str: &str) -> Option<String> {
-    if let Ok(value) = serde_json::from_str::<serde_json::Value>(request_str) {
-        if let Some(id_val) = value.get("id") {
-            if let Some(id_str) = id_val.as_str() {
-                return Some(id_str.to_string());
-            } else if let Some(id_num) = id_val.as_i64() {
-                return Some(id_num.to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::sync::AgentCoordinator;
+    use crate::rag::test_support::{temp_dir, test_client};
+    use crate::utils::config::IndexingConfig;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, Lines, ReadHalf, WriteHalf};
+
+    /// Client end of an in-memory connection, speaking line-delimited JSON-RPC
+    struct TestPeer {
+        lines: Lines<BufReader<ReadHalf<DuplexStream>>>,
+        writer: WriteHalf<DuplexStream>,
+        next_id: u64,
+    }
+
+    impl TestPeer {
+        async fn connect(server: &CerebusRexMcpServer) -> Self {
+            let (client, transport) = tokio::io::duplex(1 << 16);
+            let handler = server.for_connection();
+            tokio::spawn(async move {
+                if let Ok(running) = handler.serve(transport).await {
+                    let _ = running.waiting().await;
+                }
+            });
+            let (reader, writer) = tokio::io::split(client);
+            Self {
+                lines: BufReader::new(reader).lines(),
+                writer,
+                next_id: 0,
+            }
+        }
+
+        async fn send(&mut self, message: Value) {
+            let line = format!("{}\n", message);
+            self.writer.write_all(line.as_bytes()).await.unwrap();
+        }
+
+        /// Send a request and wait for its response, skipping notifications
+        async fn request(&mut self, method: &str, params: Value) -> Value {
+            self.next_id += 1;
+            let id = self.next_id;
+            self.send(serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+                .await;
+            loop {
+                let line = self.lines.next_line().await.unwrap().expect("connection closed");
+                let message: Value = serde_json::from_str(&line).unwrap();
+                if message["id"] == id {
+                    return message;
+                }
+            }
+        }
+
+        /// Complete the handshake, resuming `resume` if given; returns the initialize result
+        async fn initialize(&mut self, resume: Option<&str>) -> Value {
+            let mut capabilities = serde_json::json!({});
+            if let Some(session_id) = resume {
+                capabilities["experimental"] = serde_json::json!({ SESSION_CAPABILITY: { "resume": session_id } });
             }
+            let response = self
+                .request(
+                    "initialize",
+                    serde_json::json!({
+                        "protocolVersion": "2024-11-05",
+                        "capabilities": capabilities,
+                        "clientInfo": { "name": "test-agent", "version": "1.0" },
+                    }),
+                )
+                .await;
+            self.send(serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+                .await;
+            response["result"].clone()
         }
     }
-    None
-}
\ No newline at end of file
+
+    async fn server(root: &Path) -> (CerebusRexMcpServer, Arc<RwLock<RagClient>>) {
+        let rag_client = Arc::new(RwLock::new(test_client(root).await.0));
+        let watchers = Arc::new(RepoWatchers::new(rag_client.clone(), &IndexingConfig::default()));
+        let jobs = JobQueue::in_memory(rag_client.clone(), watchers.clone(), 1);
+        let sessions = Arc::new(SessionManager::new(Arc::new(AgentCoordinator::new()), Duration::from_secs(60), 8));
+        let server = CerebusRexMcpServer::new(
+            rag_client.clone(),
+            SearchTool::new(rag_client.clone()),
+            FilesystemTool,
+            ThreadsafeCodeContextTool::new(),
+            watchers,
+            jobs,
+            sessions,
+        );
+        (server, rag_client)
+    }
+
+    #[tokio::test]
+    async fn test_initialize_advertises_session_and_resumes() {
+        let root = temp_dir("server");
+        let (server, _) = server(&root).await;
+
+        let mut first = TestPeer::connect(&server).await;
+        let result = first.initialize(None).await;
+        let session = &result["capabilities"]["experimental"][SESSION_CAPABILITY];
+        assert_eq!(session["resumed"], false);
+        let session_id = session["id"].as_str().unwrap().to_string();
+        assert!(session["agent_id"].is_string());
+
+        // Every listed tool is accepted by `tools/call` and no other
+        let listed = first.request("tools/list", serde_json::json!({})).await;
+        let names: Vec<&str> = listed["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, TOOL_NAMES);
+
+        let mut second = TestPeer::connect(&server).await;
+        let result = second.initialize(Some(&session_id)).await;
+        let session = &result["capabilities"]["experimental"][SESSION_CAPABILITY];
+        assert_eq!((session["id"].as_str(), &session["resumed"]), (Some(session_id.as_str()), &Value::Bool(true)));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_call_tool_maps_errors() {
+        let root = temp_dir("server");
+        let (server, _) = server(&root).await;
+        let mut peer = TestPeer::connect(&server).await;
+        peer.initialize(None).await;
+
+        // An unknown tool is a protocol error
+        let response = peer
+            .request("tools/call", serde_json::json!({ "name": "format_disk", "arguments": {} }))
+            .await;
+        assert_eq!(response["error"]["code"], -32602);
+        assert!(response["error"]["message"].as_str().unwrap().contains("format_disk"));
+
+        // A failing tool reports the failure in its result for the agent to read
+        let response = peer
+            .request("tools/call", serde_json::json!({ "name": "search", "arguments": {} }))
+            .await;
+        assert_eq!(response["result"]["isError"], true);
+        assert_eq!(response["result"]["content"][0]["text"], "Missing query parameter");
+
+        let response = peer
+            .request("tools/call", serde_json::json!({ "name": "index_jobs", "arguments": {} }))
+            .await;
+        assert_eq!(response["result"]["structuredContent"]["jobs"], serde_json::json!([]));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_resource_serves_indexed_files() {
+        let root = temp_dir("server");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        let (server, rag_client) = server(&root).await;
+        rag_client
+            .read()
+            .await
+            .index_directory("repo", &root, &[], &[], vec![FileClass::Code])
+            .await
+            .unwrap();
+        let mut peer = TestPeer::connect(&server).await;
+        peer.initialize(None).await;
+
+        let response = peer
+            .request("resources/read", serde_json::json!({ "uri": "cerebus-rex://code" }))
+            .await;
+        let listing: Value = serde_json::from_str(response["result"]["contents"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(listing[0]["uri"], "cerebus-rex://code/repo/src/main.rs");
+
+        let response = peer
+            .request("resources/read", serde_json::json!({ "uri": "cerebus-rex://code/repo/src/main.rs" }))
+            .await;
+        assert_eq!(response["result"]["contents"][0]["text"], "fn main() {}");
+
+        // The file is not in the documentation collection
+        let response = peer
+            .request("resources/read", serde_json::json!({ "uri": "cerebus-rex://docs/repo/src/main.rs" }))
+            .await;
+        assert!(response["error"].is_object());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
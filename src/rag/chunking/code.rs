@@ -0,0 +1,415 @@
+// src/rag/chunking/code.rs
+use super::{estimate_tokens, split_lines, Chunk};
+use tree_sitter::{Language, Node, Parser};
+
+/// Syntax node kinds that drive chunking for one language
+struct LanguageSpec {
+    language: fn() -> Language,
+    /// Nodes that become chunks of their own
+    definitions: &'static [&'static str],
+    /// Definitions searched for nested definitions when they exceed the cap
+    containers: &'static [&'static str],
+    /// Nodes that wrap a definition, with the field holding it
+    wrappers: &'static [(&'static str, &'static str)],
+    /// Comments and attributes that belong to the definition right after them
+    attached: &'static [&'static str],
+    /// Joins a container's name to its members in symbol names
+    separator: &'static str,
+}
+
+fn language_spec(language: &str) -> Option<LanguageSpec> {
+    let spec = match language {
+        "rust" => LanguageSpec {
+            language: || tree_sitter_rust::LANGUAGE.into(),
+            definitions: &[
+                "function_item",
+                "impl_item",
+                "trait_item",
+                "struct_item",
+                "enum_item",
+                "union_item",
+                "mod_item",
+                "macro_definition",
+            ],
+            containers: &["impl_item", "trait_item", "mod_item"],
+            wrappers: &[],
+            attached: &["line_comment", "block_comment", "attribute_item"],
+            separator: "::",
+        },
+        "python" => LanguageSpec {
+            language: || tree_sitter_python::LANGUAGE.into(),
+            definitions: &["function_definition", "class_definition"],
+            containers: &["class_definition"],
+            wrappers: &[("decorated_definition", "definition")],
+            attached: &["comment"],
+            separator: ".",
+        },
+        "typescript" | "tsx" | "javascript" => LanguageSpec {
+            language: match language {
+                "typescript" => || tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+                "tsx" => || tree_sitter_typescript::LANGUAGE_TSX.into(),
+                _ => || tree_sitter_javascript::LANGUAGE.into(),
+            },
+            definitions: &[
+                "function_declaration",
+                "generator_function_declaration",
+                "class_declaration",
+                "abstract_class_declaration",
+                "interface_declaration",
+                "enum_declaration",
+                "type_alias_declaration",
+                "method_definition",
+                "module",
+                "internal_module",
+            ],
+            containers: &[
+                "class_declaration",
+                "abstract_class_declaration",
+                "interface_declaration",
+                "module",
+                "internal_module",
+            ],
+            wrappers: &[("export_statement", "declaration")],
+            attached: &["comment"],
+            separator: ".",
+        },
+        "go" => LanguageSpec {
+            language: || tree_sitter_go::LANGUAGE.into(),
+            definitions: &["function_declaration", "method_declaration", "type_declaration"],
+            containers: &[],
+            wrappers: &[],
+            attached: &["comment"],
+            separator: ".",
+        },
+        "java" => LanguageSpec {
+            language: || tree_sitter_java::LANGUAGE.into(),
+            definitions: &[
+                "class_declaration",
+                "interface_declaration",
+                "enum_declaration",
+                "record_declaration",
+                "annotation_type_declaration",
+                "method_declaration",
+                "constructor_declaration",
+            ],
+            containers: &[
+                "class_declaration",
+                "interface_declaration",
+                "enum_declaration",
+                "record_declaration",
+            ],
+            wrappers: &[],
+            attached: &["line_comment", "block_comment"],
+            separator: ".",
+        },
+        _ => return None,
+    };
+    Some(spec)
+}
+
+/// Byte and row range of consecutive sibling nodes
+#[derive(Clone, Copy)]
+struct Span {
+    start_byte: usize,
+    end_byte: usize,
+    start_row: usize,
+    end_row: usize,
+}
+
+impl Span {
+    fn of(node: &Node) -> Self {
+        Self {
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+            start_row: node.start_position().row,
+            end_row: node.end_position().row,
+        }
+    }
+
+    fn join(span: Option<Span>, other: Span) -> Span {
+        match span {
+            Some(span) => Span {
+                start_byte: span.start_byte,
+                end_byte: other.end_byte,
+                start_row: span.start_row,
+                end_row: other.end_row,
+            },
+            None => other,
+        }
+    }
+}
+
+/// Splits source files along syntactic boundaries (functions, impls, classes,
+/// modules) using tree-sitter, keeping each chunk under a token cap
+pub struct CodeChunker {
+    max_tokens: usize,
+    overlap_tokens: usize,
+}
+
+impl CodeChunker {
+    pub fn new(max_tokens: usize, overlap_tokens: usize) -> Self {
+        Self {
+            max_tokens: max_tokens.max(1),
+            overlap_tokens,
+        }
+    }
+
+    pub fn supports(language: &str) -> bool {
+        language_spec(language).is_some()
+    }
+
+    /// Chunk `source`, or `None` if the language has no grammar
+    pub fn chunk(&self, language: &str, source: &str) -> Option<Vec<Chunk>> {
+        let spec = language_spec(language)?;
+        let mut parser = Parser::new();
+        parser.set_language(&(spec.language)()).ok()?;
+        let tree = parser.parse(source, None)?;
+
+        let mut chunks = Vec::new();
+        self.chunk_children(&spec, tree.root_node(), source, None, &mut chunks);
+        Some(chunks)
+    }
+
+    fn chunk_children(
+        &self,
+        spec: &LanguageSpec,
+        parent: Node,
+        source: &str,
+        scope: Option<&str>,
+        chunks: &mut Vec<Chunk>,
+    ) {
+        let mut cursor = parent.walk();
+        let children: Vec<Node> = parent.named_children(&mut cursor).collect();
+
+        // Statements between definitions are grouped until they reach the cap
+        let mut gap: Option<Span> = None;
+        // Comments and attributes waiting to see whether a definition follows
+        let mut leading: Option<Span> = None;
+
+        for child in children {
+            let span = Span::of(&child);
+
+            if spec.attached.contains(&child.kind()) {
+                if let Some(run) = leading.filter(|run| span.start_row > run.end_row + 1) {
+                    gap = Some(Span::join(gap, run));
+                    leading = None;
+                }
+                leading = Some(Span::join(leading, span));
+                continue;
+            }
+
+            if let Some(definition) = self.definition(spec, child) {
+                if let Some(run) = leading.take() {
+                    if span.start_row > run.end_row + 1 {
+                        gap = Some(Span::join(gap, run));
+                    } else {
+                        leading = Some(run);
+                    }
+                }
+                if let Some(gap) = gap.take() {
+                    self.emit_span(gap, source, scope, chunks);
+                }
+
+                let start = leading.take().map(|run| run.start_byte).unwrap_or(span.start_byte);
+                self.emit_definition(spec, child, definition, start, source, scope, chunks);
+                continue;
+            }
+
+            if let Some(run) = leading.take() {
+                gap = Some(Span::join(gap, run));
+            }
+            let joined = Span::join(gap, span);
+            if gap.is_some() && estimate_tokens(&source[joined.start_byte..joined.end_byte]) > self.max_tokens {
+                self.emit_span(gap.take().unwrap(), source, scope, chunks);
+                gap = Some(span);
+            } else {
+                gap = Some(joined);
+            }
+        }
+
+        if let Some(run) = leading {
+            gap = Some(Span::join(gap, run));
+        }
+        if let Some(gap) = gap {
+            self.emit_span(gap, source, scope, chunks);
+        }
+    }
+
+    /// The definition node behind `node`, looking through wrappers such as
+    /// decorators and `export`
+    fn definition<'tree>(&self, spec: &LanguageSpec, node: Node<'tree>) -> Option<Node<'tree>> {
+        let node = match spec.wrappers.iter().find(|(kind, _)| *kind == node.kind()) {
+            Some((_, field)) => node.child_by_field_name(field)?,
+            None => node,
+        };
+        if !spec.definitions.contains(&node.kind()) {
+            return None;
+        }
+        // A container without a body (`mod tests;`) is just a declaration
+        if spec.containers.contains(&node.kind()) && node.child_by_field_name("body").is_none() {
+            return None;
+        }
+        Some(node)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn emit_definition(
+        &self,
+        spec: &LanguageSpec,
+        node: Node,
+        definition: Node,
+        start_byte: usize,
+        source: &str,
+        scope: Option<&str>,
+        chunks: &mut Vec<Chunk>,
+    ) {
+        let symbol = match (scope, symbol_name(definition, source)) {
+            (Some(scope), Some(name)) => Some(format!("{}{}{}", scope, spec.separator, name)),
+            (None, Some(name)) => Some(name),
+            (scope, None) => scope.map(str::to_string),
+        };
+        let kind = definition.kind().to_string();
+
+        let start_byte = line_start(source, start_byte);
+        let text = &source[start_byte..node.end_byte()];
+        let start_row = source[..start_byte].matches('\n').count();
+
+        if estimate_tokens(text) <= self.max_tokens {
+            chunks.push(Chunk {
+                content: text.to_string(),
+                start_line: start_row + 1,
+                end_line: node.end_position().row + 1,
+                symbol,
+                kind: Some(kind),
+                breadcrumb: None,
+            });
+            return;
+        }
+
+        if spec.containers.contains(&definition.kind()) {
+            if let Some(body) = definition.child_by_field_name("body") {
+                self.chunk_children(spec, body, source, symbol.as_deref(), chunks);
+                return;
+            }
+        }
+
+        for mut chunk in split_lines(text, start_row + 1, self.max_tokens, self.overlap_tokens) {
+            chunk.symbol = symbol.clone();
+            chunk.kind = Some(kind.clone());
+            chunks.push(chunk);
+        }
+    }
+
+    fn emit_span(&self, span: Span, source: &str, scope: Option<&str>, chunks: &mut Vec<Chunk>) {
+        let start_byte = line_start(source, span.start_byte);
+        let text = &source[start_byte..span.end_byte];
+        for mut chunk in split_lines(text, span.start_row + 1, self.max_tokens, self.overlap_tokens) {
+            chunk.symbol = scope.map(str::to_string);
+            chunks.push(chunk);
+        }
+    }
+}
+
+/// Byte offset of the start of the line containing `byte`, so chunks keep their indentation
+fn line_start(source: &str, byte: usize) -> usize {
+    source[..byte].rfind('\n').map(|i| i + 1).unwrap_or(0)
+}
+
+fn node_text<'a>(node: Node, source: &'a str) -> &'a str {
+    &source[node.start_byte()..node.end_byte()]
+}
+
+/// Name of a definition as it would be written in a qualified path
+fn symbol_name(node: Node, source: &str) -> Option<String> {
+    match node.kind() {
+        // `impl Trait for Type` members are addressed through the type
+        "impl_item" => node.child_by_field_name("type").map(|ty| node_text(ty, source).to_string()),
+        // Go `type ( A struct{}; B int )` declares through type specs
+        "type_declaration" => {
+            let mut cursor = node.walk();
+            let names: Vec<&str> = node
+                .named_children(&mut cursor)
+                .filter_map(|spec| spec.child_by_field_name("name"))
+                .map(|name| node_text(name, source))
+                .collect();
+            (!names.is_empty()).then(|| names.join(", "))
+        }
+        _ => {
+            let name = node_text(node.child_by_field_name("name")?, source);
+            // Go methods are qualified by their receiver type
+            match node.child_by_field_name("receiver").and_then(|receiver| receiver_type(receiver, source)) {
+                Some(receiver) => Some(format!("{}.{}", receiver, name)),
+                None => Some(name.to_string()),
+            }
+        }
+    }
+}
+
+fn receiver_type(node: Node, source: &str) -> Option<String> {
+    if node.kind() == "type_identifier" {
+        return Some(node_text(node, source).to_string());
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    children.into_iter().find_map(|child| receiver_type(child, source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST_SOURCE: &str = r#"use std::fmt;
+
+/// A point in space
+#[derive(Debug)]
+pub struct Point {
+    x: f64,
+    y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    /// Distance from the origin
+    pub fn norm(&self) -> f64 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+}
+"#;
+
+    #[test]
+    fn test_rust_definitions_with_doc_comments() {
+        let chunks = CodeChunker::new(512, 0).chunk("rust", RUST_SOURCE).unwrap();
+        let symbols: Vec<Option<&str>> = chunks.iter().map(|chunk| chunk.symbol.as_deref()).collect();
+        assert_eq!(symbols, vec![None, Some("Point"), Some("Point")]);
+
+        // Doc comment and attribute travel with the struct
+        assert_eq!(chunks[1].start_line, 3);
+        assert_eq!(chunks[1].end_line, 8);
+        assert!(chunks[1].content.starts_with("/// A point in space"));
+        assert_eq!(chunks[2].kind.as_deref(), Some("impl_item"));
+    }
+
+    #[test]
+    fn test_oversized_impl_splits_into_methods() {
+        let chunks = CodeChunker::new(40, 0).chunk("rust", RUST_SOURCE).unwrap();
+        let methods: Vec<(&str, usize, usize)> = chunks
+            .iter()
+            .filter(|chunk| chunk.kind.as_deref() == Some("function_item"))
+            .map(|chunk| (chunk.symbol.as_deref().unwrap(), chunk.start_line, chunk.end_line))
+            .collect();
+        assert_eq!(methods, vec![("Point::new", 11, 13), ("Point::norm", 15, 18)]);
+    }
+
+    #[test]
+    fn test_python_decorated_class_methods() {
+        let source = "import os\n\n@dataclass\nclass Config:\n    path: str\n\n    def load(self):\n        return open(self.path).read()\n";
+        let chunks = CodeChunker::new(16, 0).chunk("python", source).unwrap();
+
+        let load = chunks.iter().find(|chunk| chunk.symbol.as_deref() == Some("Config.load")).unwrap();
+        assert_eq!((load.start_line, load.end_line), (7, 8));
+        assert_eq!(load.kind.as_deref(), Some("function_definition"));
+    }
+}
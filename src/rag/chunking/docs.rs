@@ -0,0 +1,387 @@
+// src/rag/chunking/docs.rs
+use super::{estimate_tokens, split_lines, Chunk};
+
+/// Markup understood by the documentation chunker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocFormat {
+    Markdown,
+    ReStructuredText,
+    AsciiDoc,
+    PlainText,
+}
+
+impl DocFormat {
+    pub fn from_language(language: &str) -> Option<Self> {
+        match language {
+            "markdown" => Some(Self::Markdown),
+            "restructuredtext" => Some(Self::ReStructuredText),
+            "asciidoc" => Some(Self::AsciiDoc),
+            "text" => Some(Self::PlainText),
+            _ => None,
+        }
+    }
+}
+
+/// Lines under one heading, with the titles of every enclosing heading
+struct Section<'a> {
+    breadcrumb: Vec<String>,
+    /// 0-based index of the section's first line in the document
+    first_line: usize,
+    lines: Vec<&'a str>,
+    /// Number of leading lines that make up the heading itself
+    heading_lines: usize,
+}
+
+/// Splits documentation along its heading hierarchy, keeping fenced and
+/// delimited blocks whole and recording each chunk's heading breadcrumb
+pub struct DocsChunker {
+    max_tokens: usize,
+    overlap_tokens: usize,
+}
+
+impl DocsChunker {
+    pub fn new(max_tokens: usize, overlap_tokens: usize) -> Self {
+        Self {
+            max_tokens: max_tokens.max(1),
+            overlap_tokens,
+        }
+    }
+
+    pub fn chunk(&self, format: DocFormat, text: &str) -> Vec<Chunk> {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut chunks = Vec::new();
+
+        for section in sections(format, &lines) {
+            let body = &section.lines[section.heading_lines..];
+            if body.iter().all(|line| line.trim().is_empty()) {
+                // The title lives on in the breadcrumbs of its subsections
+                continue;
+            }
+
+            let breadcrumb = (!section.breadcrumb.is_empty()).then(|| section.breadcrumb.join(" > "));
+            for mut chunk in self.chunk_section(format, &section) {
+                chunk.breadcrumb = breadcrumb.clone();
+                chunks.push(chunk);
+            }
+        }
+
+        chunks
+    }
+
+    fn chunk_section(&self, format: DocFormat, section: &Section) -> Vec<Chunk> {
+        // Blank lines at either end belong to no chunk
+        let lines = &section.lines;
+        let start = lines.iter().position(|line| !line.trim().is_empty()).unwrap_or(0);
+        let end = lines.iter().rposition(|line| !line.trim().is_empty()).map_or(start, |end| end + 1);
+
+        let text = lines[start..end].join("\n");
+        if estimate_tokens(&text) <= self.max_tokens {
+            return vec![chunk_from_lines(&lines[start..end], section.first_line + start)];
+        }
+
+        // Pack whole blocks until the next one would overflow the cap
+        let mut chunks = Vec::new();
+        let mut current: Option<(usize, usize)> = None;
+        for (start, end) in blocks(format, &section.lines) {
+            if let Some((current_start, _)) = current {
+                let joined = section.lines[current_start..=end].join("\n");
+                if estimate_tokens(&joined) <= self.max_tokens {
+                    current = Some((current_start, end));
+                    continue;
+                }
+            }
+
+            if let Some((current_start, current_end)) = current.take() {
+                chunks.extend(self.emit(section, current_start, current_end));
+            }
+            current = Some((start, end));
+        }
+        if let Some((start, end)) = current {
+            chunks.extend(self.emit(section, start, end));
+        }
+
+        chunks
+    }
+
+    /// Lines `start..=end` of a section as one chunk, or as overlapping
+    /// line windows when a single block is over the cap
+    fn emit(&self, section: &Section, start: usize, end: usize) -> Vec<Chunk> {
+        let lines = &section.lines[start..=end];
+        let text = lines.join("\n");
+        if estimate_tokens(&text) <= self.max_tokens {
+            vec![chunk_from_lines(lines, section.first_line + start)]
+        } else {
+            split_lines(&text, section.first_line + start + 1, self.max_tokens, self.overlap_tokens)
+        }
+    }
+}
+
+fn chunk_from_lines(lines: &[&str], first_line: usize) -> Chunk {
+    Chunk {
+        content: lines.join("\n"),
+        start_line: first_line + 1,
+        end_line: first_line + lines.len(),
+        symbol: None,
+        kind: None,
+        breadcrumb: None,
+    }
+}
+
+/// Split a document into sections at each heading
+fn sections<'a>(format: DocFormat, lines: &[&'a str]) -> Vec<Section<'a>> {
+    let mut sections = vec![Section {
+        breadcrumb: Vec::new(),
+        first_line: 0,
+        lines: Vec::new(),
+        heading_lines: 0,
+    }];
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    let mut rst_styles: Vec<(char, bool)> = Vec::new();
+    let mut fence: Option<String> = None;
+
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some(open) = &fence {
+            if closes_fence(format, open, lines[i]) {
+                fence = None;
+            }
+            sections.last_mut().unwrap().lines.push(lines[i]);
+            i += 1;
+            continue;
+        }
+
+        if let Some(marker) = opens_fence(format, lines[i]) {
+            fence = Some(marker);
+            sections.last_mut().unwrap().lines.push(lines[i]);
+            i += 1;
+            continue;
+        }
+
+        if let Some((level, title, span)) = heading(format, lines, i, &mut rst_styles) {
+            while stack.last().is_some_and(|(top, _)| *top >= level) {
+                stack.pop();
+            }
+            stack.push((level, title));
+
+            sections.push(Section {
+                breadcrumb: stack.iter().map(|(_, title)| title.clone()).collect(),
+                first_line: i,
+                lines: lines[i..i + span].to_vec(),
+                heading_lines: span,
+            });
+            i += span;
+            continue;
+        }
+
+        sections.last_mut().unwrap().lines.push(lines[i]);
+        i += 1;
+    }
+
+    sections
+}
+
+/// Heading starting at line `i`: its level, title and the number of lines it spans
+fn heading(
+    format: DocFormat,
+    lines: &[&str],
+    i: usize,
+    rst_styles: &mut Vec<(char, bool)>,
+) -> Option<(usize, String, usize)> {
+    let line = lines[i];
+    match format {
+        DocFormat::Markdown => {
+            let trimmed = line.trim_start();
+            if line.len() - trimmed.len() <= 3 {
+                let level = trimmed.chars().take_while(|c| *c == '#').count();
+                let rest = &trimmed[level..];
+                if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')) {
+                    let title = rest.trim().trim_end_matches('#').trim();
+                    return Some((level, title.to_string(), 1));
+                }
+            }
+
+            // Setext: a paragraph line underlined with === or ---
+            let underline = lines.get(i + 1)?.trim();
+            let is_text = !line.trim().is_empty() && !line.starts_with(' ') && !is_adornment(line.trim());
+            if is_text && underline.len() >= 2 {
+                if underline.chars().all(|c| c == '=') {
+                    return Some((1, line.trim().to_string(), 2));
+                }
+                if underline.chars().all(|c| c == '-') {
+                    return Some((2, line.trim().to_string(), 2));
+                }
+            }
+            None
+        }
+        DocFormat::ReStructuredText => {
+            // Title underlined, optionally overlined, with a run of one punctuation character
+            let (title, overline, span) = if is_adornment(line) {
+                let title = lines.get(i + 1)?;
+                let underline = lines.get(i + 2)?;
+                if underline.trim() != line.trim() || title.trim().is_empty() {
+                    return None;
+                }
+                (title.trim(), true, 3)
+            } else {
+                let underline = lines.get(i + 1)?;
+                if line.trim().is_empty() || line.starts_with(' ') || !is_adornment(underline) {
+                    return None;
+                }
+                if underline.trim().chars().count() < line.trim().chars().count() {
+                    return None;
+                }
+                (line.trim(), false, 2)
+            };
+
+            // Levels follow the order in which adornment styles first appear
+            let marker = lines[i + span - 1].trim().chars().next()?;
+            let style = (marker, overline);
+            let level = match rst_styles.iter().position(|known| *known == style) {
+                Some(position) => position + 1,
+                None => {
+                    rst_styles.push(style);
+                    rst_styles.len()
+                }
+            };
+            Some((level, title.to_string(), span))
+        }
+        DocFormat::AsciiDoc => {
+            let level = line.chars().take_while(|c| *c == '=').count();
+            let rest = &line[level..];
+            if (1..=6).contains(&level) && rest.starts_with(' ') && !rest.trim().is_empty() {
+                return Some((level, rest.trim().to_string(), 1));
+            }
+            None
+        }
+        DocFormat::PlainText => None,
+    }
+}
+
+/// A line made only of one repeated punctuation character, e.g. `=====`
+fn is_adornment(line: &str) -> bool {
+    let line = line.trim_end();
+    let mut chars = line.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_punctuation() => line.len() >= 3 && chars.all(|c| c == first),
+        _ => false,
+    }
+}
+
+fn opens_fence(format: DocFormat, line: &str) -> Option<String> {
+    match format {
+        DocFormat::Markdown => {
+            let trimmed = line.trim_start();
+            ["```", "~~~"].iter().find(|marker| trimmed.starts_with(**marker)).map(|marker| {
+                let fence_char = marker.chars().next().unwrap();
+                trimmed.chars().take_while(|c| *c == fence_char).collect()
+            })
+        }
+        // Listing, literal, example, sidebar, quote and passthrough blocks
+        DocFormat::AsciiDoc => {
+            let trimmed = line.trim_end();
+            let delimiter = trimmed.chars().next()?;
+            let is_delimiter = "-.=*_+/".contains(delimiter)
+                && trimmed.len() >= 4
+                && trimmed.chars().all(|c| c == delimiter);
+            is_delimiter.then(|| trimmed.to_string())
+        }
+        DocFormat::ReStructuredText | DocFormat::PlainText => None,
+    }
+}
+
+fn closes_fence(format: DocFormat, open: &str, line: &str) -> bool {
+    match format {
+        DocFormat::Markdown => {
+            let trimmed = line.trim();
+            let fence_char = open.chars().next().unwrap_or('`');
+            trimmed.len() >= open.len() && trimmed.chars().all(|c| c == fence_char)
+        }
+        _ => line.trim_end() == open,
+    }
+}
+
+/// Blank-line separated blocks as inclusive line ranges. Fenced blocks stay
+/// whole, and in reStructuredText indented blocks stay with the paragraph
+/// that introduces them (literal blocks, directive bodies).
+fn blocks(format: DocFormat, lines: &[&str]) -> Vec<(usize, usize)> {
+    let mut blocks: Vec<(usize, usize)> = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut fence: Option<String> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(open) = &fence {
+            if closes_fence(format, open, line) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = opens_fence(format, line) {
+            fence = Some(marker);
+            start.get_or_insert(i);
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            if let Some(block_start) = start.take() {
+                blocks.push((block_start, i - 1));
+            }
+            continue;
+        }
+
+        if start.is_none() {
+            let continues_previous = format == DocFormat::ReStructuredText && line.starts_with([' ', '\t']);
+            match blocks.pop() {
+                Some((previous_start, _)) if continues_previous => start = Some(previous_start),
+                Some(previous) => {
+                    blocks.push(previous);
+                    start = Some(i);
+                }
+                None => start = Some(i),
+            }
+        }
+    }
+
+    if let Some(block_start) = start {
+        blocks.push((block_start, lines.len().saturating_sub(1)));
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_breadcrumbs_and_fences() {
+        let text = "# Guide\n\nIntro text.\n\n## Installation\n\n### Docker\n\n```bash\n# not a heading\ndocker compose up\n```\n\n## Usage\n\nRun it.\n";
+        let chunks = DocsChunker::new(512, 0).chunk(DocFormat::Markdown, text);
+
+        let breadcrumbs: Vec<&str> = chunks.iter().map(|chunk| chunk.breadcrumb.as_deref().unwrap()).collect();
+        assert_eq!(breadcrumbs, vec!["Guide", "Guide > Installation > Docker", "Guide > Usage"]);
+
+        let docker = &chunks[1];
+        assert_eq!((docker.start_line, docker.end_line), (7, 12));
+        assert!(docker.content.contains("# not a heading\ndocker compose up\n```"));
+    }
+
+    #[test]
+    fn test_rst_heading_levels_follow_adornment_order() {
+        let text = "=====\nTitle\n=====\n\nSection\n-------\n\nBody::\n\n    indented literal\n\nOther\n-----\n\nMore.\n";
+        let chunks = DocsChunker::new(512, 0).chunk(DocFormat::ReStructuredText, text);
+
+        let breadcrumbs: Vec<&str> = chunks.iter().map(|chunk| chunk.breadcrumb.as_deref().unwrap()).collect();
+        assert_eq!(breadcrumbs, vec!["Title > Section", "Title > Other"]);
+        assert!(chunks[0].content.contains("indented literal"));
+    }
+
+    #[test]
+    fn test_plain_text_packs_paragraphs() {
+        let text = "first paragraph line\n\nsecond paragraph line\n\nthird paragraph line\n";
+        // Each paragraph is 5 tokens; two fit together with the blank line between them
+        let chunks = DocsChunker::new(11, 0).chunk(DocFormat::PlainText, text);
+
+        let ranges: Vec<(usize, usize)> = chunks.iter().map(|chunk| (chunk.start_line, chunk.end_line)).collect();
+        assert_eq!(ranges, vec![(1, 3), (5, 5)]);
+        assert!(chunks.iter().all(|chunk| chunk.breadcrumb.is_none()));
+    }
+}
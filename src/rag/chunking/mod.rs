@@ -0,0 +1,185 @@
+// src/rag/chunking/mod.rs
+pub mod code;
+pub mod docs;
+
+pub use code::CodeChunker;
+pub use docs::{DocFormat, DocsChunker};
+
+use crate::utils::config::ChunkingConfig;
+use std::path::Path;
+
+/// A span of a file that is embedded and stored as its own point
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub content: String,
+    /// First line of the chunk, 1-based
+    pub start_line: usize,
+    /// Last line of the chunk, inclusive
+    pub end_line: usize,
+    /// Name of the definition the chunk covers, e.g. `RagClient::search`
+    pub symbol: Option<String>,
+    /// Syntax node kind the chunk was cut from, e.g. `function_item`
+    pub kind: Option<String>,
+    /// Enclosing document headings, e.g. `Installation > Docker`
+    pub breadcrumb: Option<String>,
+}
+
+impl Chunk {
+    /// Text sent to the embedder: the content prefixed with its heading
+    /// breadcrumb, so a section is found by the topic it sits under
+    pub fn embedding_text(&self) -> String {
+        match &self.breadcrumb {
+            Some(breadcrumb) => format!("{}\n\n{}", breadcrumb, self.content),
+            None => self.content.clone(),
+        }
+    }
+}
+
+/// Splits files into chunks small enough to embed
+pub struct Chunker {
+    code: CodeChunker,
+    docs: DocsChunker,
+}
+
+impl Chunker {
+    pub fn new(config: ChunkingConfig) -> Self {
+        Self {
+            code: CodeChunker::new(config.max_tokens, config.overlap_tokens),
+            docs: DocsChunker::new(config.max_tokens, config.overlap_tokens),
+        }
+    }
+
+    /// Chunk a file: source code along its syntax tree, documentation along
+    /// its headings, and anything else by paragraphs
+    pub fn chunk(&self, path: &str, language: Option<&str>, content: &str) -> Vec<Chunk> {
+        let language = language.or_else(|| language_from_path(path));
+
+        if let Some(language) = language {
+            if let Some(chunks) = self.code.chunk(language, content) {
+                return chunks;
+            }
+        }
+
+        let format = language
+            .and_then(DocFormat::from_language)
+            .unwrap_or(DocFormat::PlainText);
+        self.docs.chunk(format, content)
+    }
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Self::new(ChunkingConfig::default())
+    }
+}
+
+/// Language name for the source and documentation extensions the chunkers understand
+pub fn language_from_path(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    match extension {
+        "rs" => Some("rust"),
+        "py" | "pyi" => Some("python"),
+        "ts" | "mts" | "cts" => Some("typescript"),
+        "tsx" => Some("tsx"),
+        "js" | "mjs" | "cjs" | "jsx" => Some("javascript"),
+        "go" => Some("go"),
+        "java" => Some("java"),
+        "md" | "markdown" | "mdx" => Some("markdown"),
+        "rst" => Some("restructuredtext"),
+        "adoc" | "asciidoc" | "asc" => Some("asciidoc"),
+        "txt" | "text" => Some("text"),
+        _ => None,
+    }
+}
+
+/// Rough token count used for size caps; about four bytes per token for code and prose
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Split text into windows of whole lines under `max_tokens`, repeating
+/// roughly `overlap_tokens` of trailing lines at the start of the next window.
+/// `first_line` is the 1-based line number of the first line of `text`.
+pub fn split_lines(text: &str, first_line: usize, max_tokens: usize, overlap_tokens: usize) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.is_empty() || text.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        // Always take at least one line so an oversized line still makes progress
+        let mut end = start + 1;
+        let mut tokens = estimate_tokens(lines[start]) + 1;
+        while end < lines.len() {
+            let line_tokens = estimate_tokens(lines[end]) + 1;
+            if tokens + line_tokens > max_tokens {
+                break;
+            }
+            tokens += line_tokens;
+            end += 1;
+        }
+
+        chunks.push(Chunk {
+            content: lines[start..end].join("\n"),
+            start_line: first_line + start,
+            end_line: first_line + end - 1,
+            symbol: None,
+            kind: None,
+            breadcrumb: None,
+        });
+
+        if end == lines.len() {
+            break;
+        }
+
+        // Step back over the overlap, but never to or before the previous start
+        let mut next = end;
+        let mut overlap = 0;
+        while next > start + 1 {
+            let line_tokens = estimate_tokens(lines[next - 1]) + 1;
+            if overlap + line_tokens > overlap_tokens {
+                break;
+            }
+            overlap += line_tokens;
+            next -= 1;
+        }
+        start = next;
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_lines_respects_cap_and_overlap() {
+        let text = (1..=10).map(|i| format!("line {:02}", i)).collect::<Vec<_>>().join("\n");
+
+        // Each line is 2 + 1 tokens, so 4 lines fit in 12 tokens and one line overlaps
+        let chunks = split_lines(&text, 5, 12, 3);
+        assert_eq!(chunks[0].start_line, 5);
+        assert_eq!(chunks[0].end_line, 8);
+        assert_eq!(chunks[1].start_line, 8);
+        assert_eq!(chunks.last().unwrap().end_line, 14);
+    }
+
+    #[test]
+    fn test_unknown_files_fall_back_to_paragraphs() {
+        let chunker = Chunker::default();
+        let chunks = chunker.chunk("NOTICE", None, "just some text\nover two lines");
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].end_line, 2);
+        assert_eq!(chunks[0].symbol, None);
+    }
+
+    #[test]
+    fn test_breadcrumb_prefixes_embedding_text() {
+        let chunks = Chunker::default().chunk("README.md", None, "# Install\n\n## Docker\n\nRun it.\n");
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].embedding_text(), "Install > Docker\n\n## Docker\n\nRun it.");
+    }
+}
@@ -0,0 +1,299 @@
+// src/rag/classify.rs
+use crate::rag::chunking::language_from_path;
+use crate::rag::walker;
+use crate::utils::config::RouteConfig;
+use globset::GlobMatcher;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClassifyError {
+    #[error("Invalid route glob {0}: {1}")]
+    Glob(String, globset::Error),
+    #[error("Route {0} sets neither a class nor a language")]
+    EmptyRoute(String),
+}
+
+/// Kind of file, deciding which collection it is indexed into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileClass {
+    Documentation,
+    Code,
+    Config,
+}
+
+impl FileClass {
+    /// Collection holding files of this class
+    pub fn collection(self) -> &'static str {
+        match self {
+            FileClass::Documentation => "documentation",
+            FileClass::Code => "code",
+            FileClass::Config => "config",
+        }
+    }
+
+    /// Class of files written in a prose or data language, if it is not code
+    pub fn from_language(language: &str) -> Option<Self> {
+        match language {
+            "markdown" | "restructuredtext" | "asciidoc" | "text" => Some(FileClass::Documentation),
+            "toml" | "yaml" | "json" | "xml" | "ini" | "dockerfile" | "make" | "cmake" => Some(FileClass::Config),
+            _ => None,
+        }
+    }
+}
+
+/// Classify a file by its name: prose and well-known project documents are
+/// documentation, data and build files are configuration, anything else is code
+pub fn classify(path: &str) -> FileClass {
+    let path = Path::new(path);
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+
+    match name {
+        "Dockerfile" | "Containerfile" | "Makefile" | "Procfile" | "CMakeLists.txt" | ".gitignore"
+        | ".gitattributes" | ".dockerignore" | ".editorconfig" | ".env" => return FileClass::Config,
+        _ => {}
+    }
+
+    match extension.as_str() {
+        "md" | "markdown" | "mdx" | "rst" | "adoc" | "asciidoc" | "asc" | "txt" | "text" => {
+            return FileClass::Documentation
+        }
+        "toml" | "yaml" | "yml" | "json" | "jsonc" | "ini" | "cfg" | "conf" | "properties" | "env" | "xml"
+        | "lock" => return FileClass::Config,
+        _ => {}
+    }
+
+    match stem.to_uppercase().as_str() {
+        "README" | "CHANGELOG" | "CHANGES" | "CONTRIBUTING" | "LICENSE" | "LICENCE" | "COPYING" | "AUTHORS"
+        | "NOTICE" => FileClass::Documentation,
+        _ => FileClass::Code,
+    }
+}
+
+/// Language of a file from, in order, well-known file names, its extension,
+/// a `#!` interpreter line and the start of its content
+pub fn detect_language(path: &str, content: &str) -> Option<&'static str> {
+    let path = Path::new(path);
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    language_from_name(name)
+        .or_else(|| path.to_str().and_then(language_from_path))
+        .or_else(|| language_from_extension(path))
+        .or_else(|| language_from_shebang(content))
+        .or_else(|| language_from_content(content))
+}
+
+fn language_from_name(name: &str) -> Option<&'static str> {
+    match name {
+        "Dockerfile" | "Containerfile" => Some("dockerfile"),
+        "Makefile" | "makefile" | "GNUmakefile" => Some("make"),
+        "CMakeLists.txt" => Some("cmake"),
+        "Gemfile" | "Rakefile" => Some("ruby"),
+        "Jenkinsfile" => Some("groovy"),
+        "BUILD" | "BUILD.bazel" | "WORKSPACE" => Some("starlark"),
+        "Cargo.lock" | "Pipfile" => Some("toml"),
+        _ if name.starts_with("Dockerfile.") => Some("dockerfile"),
+        _ => None,
+    }
+}
+
+/// Languages the chunkers have no special handling for
+fn language_from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "toml" => Some("toml"),
+        "yaml" | "yml" => Some("yaml"),
+        "json" | "jsonc" => Some("json"),
+        "xml" => Some("xml"),
+        "ini" | "cfg" | "conf" => Some("ini"),
+        "sh" | "bash" | "zsh" => Some("shell"),
+        "c" | "h" => Some("c"),
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" => Some("cpp"),
+        "cs" => Some("csharp"),
+        "kt" | "kts" => Some("kotlin"),
+        "rb" => Some("ruby"),
+        "php" => Some("php"),
+        "swift" => Some("swift"),
+        "scala" => Some("scala"),
+        "lua" => Some("lua"),
+        "pl" | "pm" => Some("perl"),
+        "sql" => Some("sql"),
+        "html" | "htm" => Some("html"),
+        "css" | "scss" => Some("css"),
+        "bzl" => Some("starlark"),
+        _ => None,
+    }
+}
+
+/// Language named by a `#!` line, through `env` if used
+fn language_from_shebang(content: &str) -> Option<&'static str> {
+    let line = content.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.find(|word| !word.starts_with('-'))?;
+    }
+    // python3.12 and the like name the same language as python
+    match interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+        "python" => Some("python"),
+        "node" | "deno" | "bun" => Some("javascript"),
+        "sh" | "bash" | "zsh" | "dash" | "ksh" => Some("shell"),
+        "ruby" => Some("ruby"),
+        "perl" => Some("perl"),
+        "php" => Some("php"),
+        "lua" => Some("lua"),
+        _ => None,
+    }
+}
+
+/// Language recognised from the opening of the content
+fn language_from_content(content: &str) -> Option<&'static str> {
+    let start = content.trim_start();
+    let lowercase = start.chars().take(16).collect::<String>().to_ascii_lowercase();
+    if start.starts_with("<?xml") {
+        Some("xml")
+    } else if start.starts_with("<?php") {
+        Some("php")
+    } else if lowercase.starts_with("<!doctype html") || lowercase.starts_with("<html") {
+        Some("html")
+    } else if (start.starts_with('{') || start.starts_with('['))
+        && serde_json::from_str::<serde::de::IgnoredAny>(start).is_ok()
+    {
+        Some("json")
+    } else {
+        None
+    }
+}
+
+/// A configured override for files matching a glob
+#[derive(Debug, Clone)]
+struct Route {
+    matcher: GlobMatcher,
+    class: Option<FileClass>,
+    language: Option<String>,
+}
+
+/// Classifies files by configured routes, falling back to name and content.
+/// The first route matching a path wins.
+#[derive(Debug, Clone, Default)]
+pub struct Classifier {
+    routes: Vec<Route>,
+}
+
+impl Classifier {
+    pub fn new(routes: &[RouteConfig]) -> Result<Self, ClassifyError> {
+        let routes = routes
+            .iter()
+            .map(|route| {
+                if route.class.is_none() && route.language.is_none() {
+                    return Err(ClassifyError::EmptyRoute(route.glob.clone()));
+                }
+                let glob = walker::glob(&route.glob).map_err(|e| ClassifyError::Glob(route.glob.clone(), e))?;
+                Ok(Route {
+                    matcher: glob.compile_matcher(),
+                    class: route.class,
+                    language: route.language.clone(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { routes })
+    }
+
+    fn route(&self, path: &str) -> Option<&Route> {
+        self.routes.iter().find(|route| route.matcher.is_match(path))
+    }
+
+    /// Class of a file known only by its path
+    pub fn class(&self, path: &str) -> FileClass {
+        self.route(path).and_then(|route| route.class).unwrap_or_else(|| classify(path))
+    }
+
+    /// Class of a file whose language is known. A file without an extension
+    /// that would otherwise count as code takes the class of its language.
+    pub fn file_class(&self, path: &str, language: Option<&str>) -> FileClass {
+        if let Some(class) = self.route(path).and_then(|route| route.class) {
+            return class;
+        }
+        match classify(path) {
+            FileClass::Code if Path::new(path).extension().is_none() => {
+                language.and_then(FileClass::from_language).unwrap_or(FileClass::Code)
+            }
+            class => class,
+        }
+    }
+
+    /// Language of a file, as routed or detected
+    pub fn language(&self, path: &str, content: &str) -> Option<String> {
+        match self.route(path).and_then(|route| route.language.clone()) {
+            Some(language) => Some(language),
+            None => detect_language(path, content).map(str::to_string),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("README.md"), FileClass::Documentation);
+        assert_eq!(classify("docs/guide.rst"), FileClass::Documentation);
+        assert_eq!(classify("LICENSE"), FileClass::Documentation);
+        assert_eq!(classify("Cargo.toml"), FileClass::Config);
+        assert_eq!(classify("deploy/Dockerfile"), FileClass::Config);
+        assert_eq!(classify("CMakeLists.txt"), FileClass::Config);
+        assert_eq!(classify(".github/workflows/ci.YML"), FileClass::Config);
+        assert_eq!(classify("src/main.rs"), FileClass::Code);
+        assert_eq!(classify("scripts/release"), FileClass::Code);
+        assert_eq!(FileClass::Documentation.collection(), "documentation");
+    }
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("src/main.rs", ""), Some("rust"));
+        assert_eq!(detect_language("deploy/Dockerfile.dev", ""), Some("dockerfile"));
+        assert_eq!(detect_language("CMakeLists.txt", ""), Some("cmake"));
+        assert_eq!(detect_language("Cargo.toml", ""), Some("toml"));
+        assert_eq!(detect_language("scripts/release", "#!/usr/bin/env -S python3.12 -u\n"), Some("python"));
+        assert_eq!(detect_language("bin/setup", "#!/bin/bash\nset -e\n"), Some("shell"));
+        assert_eq!(detect_language("data/feed", "  <?xml version=\"1.0\"?>\n<feed/>"), Some("xml"));
+        assert_eq!(detect_language("data/fixture", "{\"a\": [1, 2]}"), Some("json"));
+        assert_eq!(detect_language("notes", "{ not json"), None);
+    }
+
+    #[test]
+    fn test_classifier_routes_and_content() {
+        let routes = vec![
+            RouteConfig {
+                glob: "examples/**".to_string(),
+                class: Some(FileClass::Documentation),
+                language: None,
+            },
+            RouteConfig {
+                glob: "*.tpl".to_string(),
+                class: None,
+                language: Some("html".to_string()),
+            },
+        ];
+        let classifier = Classifier::new(&routes).unwrap();
+        assert_eq!(classifier.class("examples/basic/main.rs"), FileClass::Documentation);
+        assert_eq!(classifier.class("src/main.rs"), FileClass::Code);
+        assert_eq!(classifier.language("web/page.tpl", "").as_deref(), Some("html"));
+        assert_eq!(classifier.file_class("data/fixture", Some("json")), FileClass::Config);
+        assert_eq!(classifier.file_class("src/lib.rs", Some("json")), FileClass::Code);
+
+        let empty = RouteConfig {
+            glob: "*.x".to_string(),
+            class: None,
+            language: None,
+        };
+        assert!(matches!(Classifier::new(&[empty]), Err(ClassifyError::EmptyRoute(_))));
+    }
+}
@@ -1,122 +1,388 @@
 // src/rag/client.rs
-use crate::mcp::types::{SearchRequest, SearchResult};
-use qdrant_client::{
-    Qdrant,
-    qdrant::{CreateCollection, VectorParams, HnswConfigDiff, Distance},
-};
+use crate::mcp::types::{FileFailure, IndexedFile, RepoFile, SearchMode, SearchRequest, SearchResult};
+use crate::rag::chunking::Chunk;
+use crate::rag::classify::FileClass;
+use crate::rag::embedding::Embedder;
+use crate::rag::filter::{self, CompiledFilter, EXTENSION_FIELD, MODIFIED_AT_FIELD, PATH_PREFIXES_FIELD};
+use crate::rag::git;
+use crate::rag::hybrid::{self, RRF_K};
+use crate::rag::manifest::{self, IndexReport, Manifest, ManifestEntry, ManifestStore};
+use crate::rag::migration::{self, Migration, WriteTargets};
+use crate::rag::registry::{RegistryError, RepoRecord, RepoRegistry};
+use crate::rag::repo_config::{EffectiveConfig, FileExplanation, RepoConfigError, RepoProfile};
+use crate::rag::rerank::RerankStages;
+use crate::rag::store::{CollectionSpec, Point, QdrantStore, QueryVector, SparseVector, VectorStore};
+use crate::rag::walker::{self, FileSelector, WalkSummary, WalkedFile};
+use crate::utils::config::{ChunkingConfig, CollectionConfig, IndexingConfig, RagConfig};
+use chrono::Utc;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Vector size used when no embedder is configured (all-MiniLM-L6-v2)
+pub const DEFAULT_VECTOR_SIZE: u64 = 384;
+
+/// Number of results returned when a search request has no limit
+pub const DEFAULT_SEARCH_LIMIT: u32 = 10;
+
+/// Candidates fetched from each retriever per requested result in hybrid mode
+pub const HYBRID_CANDIDATE_FACTOR: u32 = 4;
+
+/// Number of points embedded and sent to the vector store per upsert request
+pub const UPSERT_BATCH_SIZE: usize = 64;
+
+/// Documents indexed between saves of a repository's manifest during a run
+pub const COMMIT_BATCH_SIZE: usize = 512;
+
+/// Namespace of the UUIDv5 point ids derived from document and chunk identities
+pub const POINT_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a4e_93b7_4d05_8e1a_c3f2_7b90_d41e);
+
+/// A file to re-index: its path, its new manifest entry and its chunks
+type ChangedFile = (String, ManifestEntry, Vec<Document>);
+
+/// A piece of text to embed and store, with the metadata kept as its payload
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub id: String,
+    pub content: String,
+    pub metadata: HashMap<String, Value>,
+    /// Text to embed when it differs from the stored content
+    pub embedding_text: Option<String>,
+}
+
+impl Document {
+    pub fn new(id: String, content: String, metadata: HashMap<String, Value>) -> Self {
+        Self {
+            id,
+            content,
+            metadata,
+            embedding_text: None,
+        }
+    }
+
+    pub fn with_embedding_text(mut self, text: String) -> Self {
+        self.embedding_text = Some(text);
+        self
+    }
+
+    /// Point payload: the metadata plus the text itself and the document id
+    fn payload(&self) -> HashMap<String, Value> {
+        let mut payload = self.metadata.clone();
+        payload.insert("content".to_string(), Value::String(self.content.clone()));
+        payload.insert("document_id".to_string(), Value::String(self.id.clone()));
+        payload
+    }
+
+    /// Vector stores only accept unsigned integers and UUIDs as point ids. Other
+    /// ids are hashed into a UUIDv5, so indexing the same id again replaces its point.
+    fn point_id(&self) -> String {
+        if self.id.parse::<u64>().is_ok() || Uuid::parse_str(&self.id).is_ok() {
+            self.id.clone()
+        } else {
+            Uuid::new_v5(&POINT_ID_NAMESPACE, self.id.as_bytes()).to_string()
+        }
+    }
+}
 
 /// RAG (Retrieval-Augmented Generation) client for connecting to vector databases
 pub struct RagClient {
-    pub qdrant_client: Option<Qdrant>,
     pub endpoint: String,
     pub api_key: Option<String>,
-    pub collections: Vec<String>,
+    pub collections: Vec<CollectionConfig>,
+    store: Option<Arc<dyn VectorStore>>,
+    embedder: Option<Arc<dyn Embedder>>,
+    chunking: ChunkingConfig,
+    reranker: Option<RerankStages>,
+    write_targets: WriteTargets,
+    outdated: Vec<String>,
+    legacy: Vec<String>,
+    manifests: Arc<ManifestStore>,
+    indexing: IndexingConfig,
+    registry: Arc<RepoRegistry>,
+    /// How repositories without a `.cerebus.toml` are indexed
+    profile: Arc<RepoProfile>,
 }
 
 impl RagClient {
     pub fn new(endpoint: String, api_key: Option<String>) -> Self {
         Self {
-            qdrant_client: None, // Will be initialized later
             endpoint,
             api_key,
-            collections: vec![
-                "documentation".to_string(),
-                "code".to_string(),
-                "config".to_string(),
-            ],
+            collections: RagConfig::default().collections,
+            store: None, // Connected to Qdrant in initialize unless another store is set
+            embedder: None,
+            chunking: ChunkingConfig::default(),
+            reranker: None,
+            write_targets: WriteTargets::default(),
+            outdated: Vec::new(),
+            legacy: Vec::new(),
+            manifests: Arc::new(ManifestStore::in_memory()),
+            indexing: IndexingConfig::default(),
+            registry: Arc::new(RepoRegistry::in_memory()),
+            profile: Arc::new(RepoProfile::default()),
         }
     }
 
-    /// Initialize the Qdrant client connection
+    /// Store vectors in the given backend instead of the Qdrant server at `endpoint`
+    pub fn with_store(mut self, store: Arc<dyn VectorStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Create and validate these collections instead of the defaults
+    pub fn with_collections(mut self, collections: Vec<CollectionConfig>) -> Self {
+        self.collections = collections;
+        self
+    }
+
+    /// Track indexed files in the given manifests instead of in memory
+    pub fn with_manifests(mut self, manifests: ManifestStore) -> Self {
+        self.manifests = Arc::new(manifests);
+        self
+    }
+
+    /// Record repositories in the given registry instead of in memory
+    pub fn with_registry(mut self, registry: RepoRegistry) -> Self {
+        self.registry = Arc::new(registry);
+        self
+    }
+
+    /// Walk repositories with these size limits and exclusions
+    pub fn with_indexing(mut self, indexing: IndexingConfig) -> Self {
+        self.indexing = indexing;
+        self
+    }
+
+    /// Split files into chunks of these sizes before embedding
+    pub fn with_chunking(mut self, chunking: ChunkingConfig) -> Self {
+        self.chunking = chunking;
+        self
+    }
+
+    /// Use the given embedder for documents and queries
+    pub fn with_embedder(mut self, embedder: Arc<dyn Embedder>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// Re-score the top search candidates with the given per-collection rerank stages
+    pub fn with_reranker(mut self, reranker: RerankStages) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
+    pub fn embedder(&self) -> Option<&Arc<dyn Embedder>> {
+        self.embedder.as_ref()
+    }
+
+    /// Vector size of the collections, taken from the embedding model
+    pub fn vector_size(&self) -> u64 {
+        self.embedder
+            .as_ref()
+            .map(|embedder| embedder.dimension() as u64)
+            .unwrap_or(DEFAULT_VECTOR_SIZE)
+    }
+
+    /// Embed documents for indexing, one vector per input
+    pub async fn embed_documents(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+        let embedder = self.embedder.as_ref().ok_or("No embedding provider configured")?;
+        Ok(embedder.embed(texts).await?)
+    }
+
+    /// Embed a search query
+    pub async fn embed_query(&self, query: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        let embedder = self.embedder.as_ref().ok_or("No embedding provider configured")?;
+        Ok(embedder.embed_query(query).await?)
+    }
+
+    fn store(&self) -> Result<&Arc<dyn VectorStore>, Box<dyn std::error::Error>> {
+        Ok(self.store.as_ref().ok_or("Vector store not initialized")?)
+    }
+
+    /// Connect to Qdrant, unless another store was set, and create the collections
     pub async fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut client_builder = Qdrant::from_url(&self.endpoint);
-        // Skip server version check to avoid REST/GRPC mismatch noise
-        client_builder.check_compatibility = false;
-        if let Some(api_key) = &self.api_key {
-            client_builder = client_builder.api_key(api_key.clone());
+        // Files are routed to a collection by class, so each of those must exist
+        for class in [FileClass::Documentation, FileClass::Code, FileClass::Config] {
+            let name = class.collection();
+            if !self.collections.iter().any(|config| config.name == name) {
+                return Err(format!(
+                    "Collection \"{}\" is not configured; [[rag.collections]] must include \"documentation\", \"code\" and \"config\", which indexed files are routed to",
+                    name
+                )
+                .into());
+            }
+        }
+        let config = EffectiveConfig::server(&self.indexing, &self.chunking);
+        self.profile = Arc::new(RepoProfile::new(config)?);
+        if self.store.is_none() {
+            let store = QdrantStore::connect(&self.endpoint, self.api_key.clone())?;
+            self.store = Some(Arc::new(store));
         }
-        let client = client_builder.build()?;
-
-        // Set the client
-        self.qdrant_client = Some(client);
 
         // Create collections if they don't exist
-        self.create_collections().await?;
+        (self.outdated, self.legacy) = self.create_collections().await?;
 
         Ok(())
     }
 
-    /// Create necessary collections in Qdrant
-    async fn create_collections(&self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(client) = &self.qdrant_client {
-            for collection_name in &self.collections {
-                // Check if collection exists
-                match client.collection_exists(collection_name).await {
-                    Ok(exists) => {
-                        if !exists {
-                            // Create collection
-                            let collection_config = CreateCollection {
-                                collection_name: collection_name.clone(),
-                                vectors_config: Some(
-                                    VectorParams {
-                                        size: 384, // Use 384 for sentence transformers
-                                        distance: Distance::Cosine as i32,
-                                        ..Default::default()
-                                    }.into()
-                                ),
-                                hnsw_config: Some(HnswConfigDiff {
-                                    m: Some(16),
-                                    ef_construct: Some(100),
-                                    ..Default::default()
-                                }),
-                                quantization_config: None, // Simplified for now
-                                ..Default::default()
-                            };
-
-                            client.create_collection(collection_config).await?;
-                        }
-                    }
-                    Err(e) => {
-                        return Err(format!("Failed to check collection existence: {}", e).into());
-                    }
+    /// Create missing collections and report existing ones whose layout
+    /// differs from the configuration. Returns the collections whose vectors
+    /// come from another embedding model and those with the legacy layout.
+    async fn create_collections(&self) -> Result<(Vec<String>, Vec<String>), Box<dyn std::error::Error>> {
+        let store = self.store()?;
+        let model = self.embedder.as_ref().map(|embedder| embedder.model_id().to_string());
+        let mut outdated = Vec::new();
+        let mut legacy = Vec::new();
+        for config in &self.collections {
+            if let Some(target) = migration::resume_swap(store.as_ref(), &config.name).await? {
+                tracing::warn!("Collection {}: finished an interrupted migration to {}", config.name, target);
+            }
+            // A migrated collection is served through an alias of the configured name
+            let alias_target = store.alias_target(&config.name).await?;
+            let collection = alias_target.unwrap_or_else(|| config.name.clone());
+            let spec = CollectionSpec::from_config(config, self.vector_size()).with_embedding_model(model.clone());
+            let existing = store.ensure_collection(&collection, &spec).await?;
+            for mismatch in collection_mismatches(config, &spec, &existing, self.embedder.as_deref()) {
+                tracing::warn!("Collection {}: {}", config.name, mismatch);
+            }
+            if existing.legacy {
+                tracing::warn!(
+                    "Collection {} has the single-vector layout of an older version; it must be rebuilt before new points can be stored",
+                    config.name
+                );
+                legacy.push(config.name.clone());
+            } else if let Some(embedder) = self.embedder.as_deref() {
+                if needs_reembedding(&existing, embedder) {
+                    outdated.push(config.name.clone());
                 }
             }
         }
+        Ok((outdated, legacy))
+    }
 
-        Ok(())
+    /// Collections found at startup whose vectors were produced by another
+    /// embedding model; dense search in them is unreliable until migrated
+    pub fn outdated_collections(&self) -> &[String] {
+        &self.outdated
+    }
+
+    /// Collections found at startup with a single unnamed vector, as created
+    /// before hybrid search; searching them fails until they are migrated
+    pub fn legacy_collections(&self) -> &[String] {
+        &self.legacy
+    }
+
+    /// Prepare re-embedding a collection with the current model. The returned
+    /// migration runs independently of the client, typically in a spawned task.
+    pub async fn migration(&self, collection_name: &str) -> Result<Migration, Box<dyn std::error::Error>> {
+        let store = self.store()?;
+        let embedder = self.embedder.as_ref().ok_or("No embedding provider configured")?;
+        let config = self
+            .collections
+            .iter()
+            .find(|config| config.name == collection_name)
+            .ok_or_else(|| format!("Unknown collection: {}", collection_name))?;
+
+        let alias_target = store.alias_target(collection_name).await?;
+        let source = alias_target.unwrap_or_else(|| collection_name.to_string());
+        let spec = CollectionSpec::from_config(config, self.vector_size());
+        Ok(Migration::new(
+            collection_name,
+            &source,
+            spec,
+            store.clone(),
+            embedder.clone(),
+            self.write_targets.clone(),
+        )?)
     }
 
-    /// Perform a semantic search in the RAG system
+    /// Perform a semantic, lexical or hybrid search in the RAG system
     pub async fn search(&self, request: &SearchRequest) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
-        if let Some(client) = &self.qdrant_client {
-            let collection_name = request.context.as_ref().unwrap_or(&"documentation".to_string());
-            
-            // In a real implementation, we would convert the query to an embedding
-            // and perform the semantic search in Qdrant
-            // For now, return mock results
-            let results = vec![
-                SearchResult {
-                    id: "1".to_string(),
-                    content: format!("Search result for query: {}", request.query),
-                    source: "mock.md".to_string(),
-                    score: 0.95,
-                    metadata: Some(HashMap::new()),
-                },
-                SearchResult {
-                    id: "2".to_string(),
-                    content: "Another relevant result".to_string(),
-                    source: "project-structure.md".to_string(),
-                    score: 0.85,
-                    metadata: Some(HashMap::new()),
-                },
-            ];
+        let collection_name = request.context.as_deref().unwrap_or("documentation");
+        let limit = request.limit.unwrap_or(DEFAULT_SEARCH_LIMIT) as u64;
+        let filter = match &request.filter {
+            Some(filter) => CompiledFilter::new(filter)?,
+            None => CompiledFilter::default(),
+        };
 
-            Ok(results)
-        } else {
-            Err("Qdrant client not initialized".into())
+        // A reranker needs a deeper candidate pool than the caller asked for
+        let reranker = self.reranker.as_ref().and_then(|stages| stages.for_collection(collection_name));
+        let candidates = match reranker {
+            Some(stage) => limit.max(stage.top_n() as u64),
+            None => limit,
+        };
+
+        let mut results = match request.mode.unwrap_or_default() {
+            SearchMode::Dense => {
+                self.dense_search(collection_name, &request.query, candidates, request.score_threshold, &filter)
+                    .await?
+            }
+            SearchMode::Sparse => {
+                self.sparse_search(collection_name, &request.query, candidates, request.score_threshold, &filter)
+                    .await?
+            }
+            SearchMode::Hybrid => {
+                let pool = candidates * HYBRID_CANDIDATE_FACTOR as u64;
+                let dense = self
+                    .dense_search(collection_name, &request.query, pool, request.score_threshold, &filter)
+                    .await?;
+                let sparse = self
+                    .sparse_search(collection_name, &request.query, pool, None, &filter)
+                    .await?;
+
+                hybrid::reciprocal_rank_fusion(vec![("dense", dense), ("sparse", sparse)], RRF_K)
+            }
+        };
+
+        if let Some(stage) = reranker {
+            results = stage.rerank(&request.query, results).await?;
+        }
+        results.truncate(limit as usize);
+        Ok(results)
+    }
+
+    async fn dense_search(
+        &self,
+        collection_name: &str,
+        query: &str,
+        limit: u64,
+        score_threshold: Option<f32>,
+        filter: &CompiledFilter,
+    ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        let vector = self.embed_query(query).await?;
+        self.query_points(collection_name, QueryVector::Dense(vector), limit, score_threshold, filter)
+            .await
+    }
+
+    async fn sparse_search(
+        &self,
+        collection_name: &str,
+        query: &str,
+        limit: u64,
+        score_threshold: Option<f32>,
+        filter: &CompiledFilter,
+    ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        let (indices, values) = hybrid::query_vector(query);
+        if indices.is_empty() {
+            return Ok(Vec::new());
         }
+        let vector = QueryVector::Sparse(SparseVector { indices, values });
+        self.query_points(collection_name, vector, limit, score_threshold, filter)
+            .await
+    }
+
+    async fn query_points(
+        &self,
+        collection_name: &str,
+        vector: QueryVector,
+        limit: u64,
+        score_threshold: Option<f32>,
+        filter: &CompiledFilter,
+    ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        let store = self.store()?;
+        Ok(store.query(collection_name, vector, limit, score_threshold, filter).await?)
     }
 
     /// Index a document in the RAG system
@@ -127,47 +393,667 @@ impl RagClient {
         content: &str,
         metadata: Option<HashMap<String, Value>>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(client) = &self.qdrant_client {
-            // In a real implementation, we would:
-            // 1. Embed the content using a model like sentence transformers
-            // 2. Store the embedding in Qdrant with the content as payload
-            // For now, we just return Ok to simulate the operation
-            
-            println!("Would index document {} in collection {}", id, collection_name);
-            Ok(())
-        } else {
-            Err("Qdrant client not initialized".into())
+        let document = Document::new(id.to_string(), content.to_string(), metadata.unwrap_or_default());
+        self.index_documents(collection_name, vec![document]).await?;
+        Ok(())
+    }
+
+    /// Embed documents and upsert them into a collection in batches,
+    /// returning the number of points written
+    pub async fn index_documents(
+        &self,
+        collection_name: &str,
+        documents: Vec<Document>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let store = self.store()?;
+        // Documents indexed during a migration already belong to the new collection
+        let collection_name = &self.write_targets.resolve(collection_name);
+
+        let mut indexed = 0;
+        for batch in documents.chunks(UPSERT_BATCH_SIZE) {
+            let texts: Vec<String> = batch
+                .iter()
+                .map(|document| document.embedding_text.clone().unwrap_or_else(|| document.content.clone()))
+                .collect();
+            let vectors = self.embed_documents(&texts).await?;
+
+            let points: Vec<Point> = batch
+                .iter()
+                .zip(vectors)
+                .zip(&texts)
+                .map(|((document, vector), text)| {
+                    let (indices, values) = hybrid::document_vector(text);
+                    Point {
+                        id: document.point_id(),
+                        dense: vector,
+                        sparse: SparseVector { indices, values },
+                        payload: document.payload(),
+                    }
+                })
+                .collect();
+
+            store.upsert(collection_name, points).await?;
+
+            indexed += batch.len();
+            tracing::debug!("Indexed {}/{} documents in {}", indexed, documents.len(), collection_name);
         }
+        store.flush().await?;
+
+        Ok(indexed)
     }
 
-    /// Index repository files for quick access
+    /// Index repository files for quick access, re-embedding only files whose
+    /// content changed since they were last indexed
     pub async fn index_repo_files(
         &self,
         repo_id: &str,
         files: Vec<(String, String)>, // (file_path, content)
+    ) -> Result<IndexReport, Box<dyn std::error::Error>> {
+        let profile = self.repo_profile(self.repo_root(repo_id).as_deref())?;
+        self.update_repo(repo_id, content_files(files), None, &profile).await
+    }
+
+    /// Bring a repository's index in line with its complete file list: changed
+    /// files are re-embedded, and indexed files the selector covers but the
+    /// list lacks are removed
+    pub async fn sync_repo_files(
+        &self,
+        repo_id: &str,
+        files: Vec<(String, String)>, // (file_path, content)
+        selector: &FileSelector,
+    ) -> Result<IndexReport, Box<dyn std::error::Error>> {
+        let profile = self.repo_profile(self.repo_root(repo_id).as_deref())?;
+        self.update_repo(repo_id, content_files(files), Some(selector), &profile).await
+    }
+
+    /// Walk a repository checkout and sync the index with the files of the
+    /// given classes, narrowed by include and exclude globs
+    pub async fn index_directory(
+        &self,
+        repo_id: &str,
+        root: &Path,
+        include: &[String],
+        exclude: &[String],
+        classes: Vec<FileClass>,
+    ) -> Result<(IndexReport, WalkSummary), Box<dyn std::error::Error>> {
+        self.registry.check_root(repo_id, root)?;
+        let profile = self.repo_profile(Some(root))?;
+        let selector = profile.selector(include, exclude, classes)?;
+        let walk_root = root.to_path_buf();
+        let walk_selector = selector.clone();
+        let max_file_size = profile.config.max_file_size;
+        let walked =
+            tokio::task::spawn_blocking(move || walker::walk(&walk_root, &walk_selector, max_file_size)).await?;
+        let (files, summary) = walked?;
+
+        let report = self.update_repo(repo_id, walked_files(files), Some(&selector), &profile).await?;
+        self.registry.record_index(repo_id, root, report, summary, None).await?;
+        Ok((report, summary))
+    }
+
+    /// Index a repository as of a git revision, reading blobs from the object
+    /// database instead of the working tree. When the index is at a base
+    /// revision, only files changed since are read and re-embedded, and files
+    /// deleted since are removed; otherwise the index is synced with the whole
+    /// tree. The base defaults to the revision last indexed, and a base the
+    /// index is not at is ignored. Re-embedded chunks record their commit.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn index_revision(
+        &self,
+        repo_id: &str,
+        root: &Path,
+        rev: &str,
+        base_rev: Option<&str>,
+        include: &[String],
+        exclude: &[String],
+        classes: Vec<FileClass>,
+    ) -> Result<(IndexReport, git::Revision), Box<dyn std::error::Error>> {
+        self.registry.check_root(repo_id, root)?;
+        let profile = self.repo_profile(Some(root))?;
+        let selector = profile.selector(include, exclude, classes)?;
+        let git_root = root.to_path_buf();
+        let git_selector = selector.clone();
+        let (rev, base_rev) = (rev.to_string(), base_rev.map(str::to_string));
+        let indexed = self.registry.get(repo_id).and_then(|repo| repo.last_revision);
+        let max_file_size = profile.config.max_file_size;
+        let read = tokio::task::spawn_blocking(move || {
+            // Diffing is only right from the commit the index currently holds
+            let base = match (base_rev, indexed) {
+                (Some(base_rev), Some(indexed)) => match git::resolve_commit(&git_root, &base_rev) {
+                    Ok(base) if base == indexed => Some(base),
+                    _ => {
+                        tracing::info!("Index is at {}, not {}; reading all of {}", indexed, base_rev, rev);
+                        None
+                    }
+                },
+                (Some(base_rev), None) => {
+                    tracing::info!("Index is not at a revision, not {}; reading all of {}", base_rev, rev);
+                    None
+                }
+                // The last indexed commit may be gone, e.g. after a force push
+                (None, indexed) => indexed.filter(|indexed| git::resolve_commit(&git_root, indexed).is_ok()),
+            };
+            match base {
+                Some(base) => git::diff_revisions(&git_root, &base, &rev, &git_selector, max_file_size).map(|r| (r, true)),
+                None => git::read_revision(&git_root, &rev, &git_selector, max_file_size).map(|r| (r, false)),
+            }
+        })
+        .await?;
+        let (mut revision, diffing) = read?;
+
+        let files = std::mem::take(&mut revision.files)
+            .into_iter()
+            .map(|file| IndexedFile {
+                hash: manifest::content_hash(&file.content),
+                path: file.path,
+                content: file.content,
+                language: None,
+                modified_at: Some(revision.committed_at),
+                commit: Some(revision.commit.clone()),
+            })
+            .collect();
+        let report = if diffing {
+            let mut report = self.update_repo(repo_id, files, None, &profile).await?;
+            if !revision.deleted.is_empty() {
+                report.removed += self.remove_repo_paths(repo_id, &revision.deleted).await?.removed;
+            }
+            report
+        } else {
+            self.update_repo(repo_id, files, Some(&selector), &profile).await?
+        };
+        let commit = Some(revision.commit.clone());
+        self.registry.record_index(repo_id, root, report, revision.summary, commit).await?;
+        Ok((report, revision))
+    }
+
+    /// How the repository checked out at `root` is indexed: its
+    /// `.cerebus.toml` merged over the server's settings, or the server's
+    /// settings alone without a root or that file
+    pub fn repo_profile(&self, root: Option<&Path>) -> Result<Arc<RepoProfile>, RepoConfigError> {
+        let Some(root) = root else {
+            return Ok(self.profile.clone());
+        };
+        let config = EffectiveConfig::load(root, &self.indexing, &self.chunking)?;
+        if config.source.is_none() {
+            return Ok(self.profile.clone());
+        }
+        Ok(Arc::new(RepoProfile::new(config)?))
+    }
+
+    /// Explain whether and how a file of the repository checked out at
+    /// `root` is indexed when selecting the given classes and globs
+    pub async fn explain_path(
+        &self,
+        repo_id: &str,
+        root: &Path,
+        path: &str,
+        include: &[String],
+        exclude: &[String],
+        classes: Vec<FileClass>,
+    ) -> Result<FileExplanation, Box<dyn std::error::Error>> {
+        let profile = self.repo_profile(Some(root))?;
+        let selector = profile.selector(include, exclude, classes)?;
+        let relative = walker::normalize_repo_path(Some(root), path);
+        let indexed = self.manifests.lock(repo_id).await?.files.contains_key(&relative);
+
+        let (root, path) = (root.to_path_buf(), path.to_string());
+        let explained = tokio::task::spawn_blocking(move || profile.explain(&root, &path, &selector, indexed));
+        Ok(explained.await?)
+    }
+
+    /// Drop indexed files from a repository's index. A path also covers
+    /// every file below it, so a deleted directory removes its contents.
+    pub async fn remove_repo_paths(&self, repo_id: &str, paths: &[String]) -> Result<IndexReport, Box<dyn std::error::Error>> {
+        let mut manifest = self.manifests.lock(repo_id).await?;
+        let removed: Vec<String> = manifest
+            .files
+            .keys()
+            .filter(|file| {
+                paths.iter().any(|path| {
+                    file.as_str() == path || file.strip_prefix(path.as_str()).is_some_and(|rest| rest.starts_with('/'))
+                })
+            })
+            .cloned()
+            .collect();
+
+        let mut report = IndexReport::default();
+        let mut stale: HashMap<String, Vec<String>> = HashMap::new();
+        for path in removed {
+            if let Some(entry) = manifest.files.remove(&path) {
+                stale.entry(entry.collection).or_default().extend(entry.chunk_ids);
+                report.removed += 1;
+            }
+        }
+        for (collection, ids) in stale {
+            self.delete_points(&collection, &ids).await?;
+        }
+
+        self.manifests.save(&manifest).await?;
+        Ok(report)
+    }
+
+    /// Record the checkout directory that a repository's relative paths refer to
+    pub async fn register_root(&self, repo_id: &str, root: &Path) -> Result<RepoRecord, RegistryError> {
+        self.registry.register(Some(repo_id), root, None).await
+    }
+
+    /// Checkout directory of a repository, if one was registered
+    pub fn repo_root(&self, repo_id: &str) -> Option<PathBuf> {
+        self.registry.get(repo_id).map(|repo| repo.root)
+    }
+
+    pub fn registry(&self) -> &Arc<RepoRegistry> {
+        &self.registry
+    }
+
+    /// Delete every indexed chunk of a repository, its manifest and its
+    /// registration. Returns the number of files removed and the record,
+    /// if the repository was registered.
+    pub async fn remove_repo(
+        &self,
+        repo_id: &str,
+    ) -> Result<(IndexReport, Option<RepoRecord>), Box<dyn std::error::Error>> {
+        let mut manifest = self.manifests.lock(repo_id).await?;
+        let mut stale: HashMap<String, Vec<String>> = HashMap::new();
+        for entry in manifest.files.values() {
+            stale.entry(entry.collection.clone()).or_default().extend(entry.chunk_ids.iter().cloned());
+        }
+        for (collection, ids) in stale {
+            self.delete_points(&collection, &ids).await?;
+        }
+        let report = IndexReport {
+            removed: std::mem::take(&mut manifest.files).len(),
+            ..Default::default()
+        };
+        self.manifests.remove(repo_id).await?;
+        let record = self.registry.remove(repo_id).await?;
+        Ok((report, record))
+    }
+
+    /// Index the named files of a repository. Files without inline content
+    /// are read from the repository's registered root; those that cannot be
+    /// read, lie outside the root or are binary are reported, not indexed.
+    pub async fn index_repo_paths(
+        &self,
+        repo_id: &str,
+        files: Vec<RepoFile>,
+    ) -> Result<(IndexReport, Vec<FileFailure>), Box<dyn std::error::Error>> {
+        let root = self.repo_root(repo_id);
+        let profile = self.repo_profile(root.as_deref())?;
+        let max_file_size = profile.config.max_file_size;
+        let repo = repo_id.to_string();
+        let read = tokio::task::spawn_blocking(move || read_repo_files(&repo, root.as_deref(), files, max_file_size));
+        let (files, failures) = read.await?;
+
+        let report = self.update_repo(repo_id, files, None, &profile).await?;
+        Ok((report, failures))
+    }
+
+    /// Index files that already carry their content, language and hash
+    pub async fn index_files(
+        &self,
+        repo_id: &str,
+        files: Vec<IndexedFile>,
+    ) -> Result<IndexReport, Box<dyn std::error::Error>> {
+        let profile = self.repo_profile(self.repo_root(repo_id).as_deref())?;
+        self.update_repo(repo_id, files, None, &profile).await
+    }
+
+    /// Compare files against the repository's manifest, embed the changed
+    /// ones into their collections, delete chunks that no longer exist and
+    /// record the result. With a scope, indexed files it covers that are
+    /// missing from `files` are removed. The manifest is saved after every
+    /// batch, so an interrupted run only redoes the batch it was in.
+    async fn update_repo(
+        &self,
+        repo_id: &str,
+        files: Vec<IndexedFile>,
+        scope: Option<&FileSelector>,
+        profile: &RepoProfile,
+    ) -> Result<IndexReport, Box<dyn std::error::Error>> {
+        let mut manifest = self.manifests.lock(repo_id).await?;
+        let mut report = IndexReport::default();
+        let listed: HashSet<String> = files.iter().map(|file| file.path.clone()).collect();
+        // Files indexed with other chunking or routes are indexed again even if unchanged
+        let fingerprint = profile.config.fingerprint();
+
+        // In collection order, so an interrupted run is repeated the same way
+        let mut changed: BTreeMap<&'static str, Vec<ChangedFile>> = BTreeMap::new();
+        for mut file in files {
+            match manifest.files.get(&file.path) {
+                Some(entry) if entry.hash == file.hash && entry.config == fingerprint => {
+                    report.unchanged += 1;
+                    continue;
+                }
+                Some(_) => report.updated += 1,
+                None => report.added += 1,
+            }
+
+            file.language = file.language.or_else(|| profile.classifier.language(&file.path, &file.content));
+            let collection = profile.classifier.file_class(&file.path, file.language.as_deref()).collection();
+            let file_documents = self.file_documents(repo_id, &file, profile);
+            let entry = ManifestEntry {
+                hash: file.hash,
+                collection: collection.to_string(),
+                chunk_ids: file_documents.iter().map(Document::point_id).collect(),
+                indexed_at: Utc::now(),
+                config: fingerprint.clone(),
+            };
+            changed.entry(collection).or_default().push((file.path, entry, file_documents));
+        }
+        for (collection, files) in changed {
+            let mut batch = Vec::new();
+            let mut documents = Vec::new();
+            for (path, entry, file_documents) in files {
+                documents.extend(file_documents);
+                batch.push((path, entry));
+                if documents.len() >= COMMIT_BATCH_SIZE {
+                    let (documents, batch) = (std::mem::take(&mut documents), std::mem::take(&mut batch));
+                    self.commit_files(&mut manifest, collection, documents, batch).await?;
+                }
+            }
+            if !batch.is_empty() {
+                self.commit_files(&mut manifest, collection, documents, batch).await?;
+            }
+        }
+
+        if let Some(scope) = scope {
+            let removed: Vec<String> = manifest
+                .files
+                .keys()
+                .filter(|path| !listed.contains(*path) && scope.selects(path))
+                .cloned()
+                .collect();
+            let mut stale: HashMap<String, Vec<String>> = HashMap::new();
+            for path in removed {
+                if let Some(entry) = manifest.files.remove(&path) {
+                    stale.entry(entry.collection).or_default().extend(entry.chunk_ids);
+                    report.removed += 1;
+                }
+            }
+            for (collection, ids) in stale {
+                self.delete_points(&collection, &ids).await?;
+            }
+            if report.removed > 0 {
+                self.manifests.save(&manifest).await?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Embed a batch of changed files into their collection, record them in
+    /// the manifest, delete chunks they no longer have and save the manifest
+    async fn commit_files(
+        &self,
+        manifest: &mut Manifest,
+        collection: &str,
+        documents: Vec<Document>,
+        files: Vec<(String, ManifestEntry)>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        for (file_path, content) in files {
-            let doc_id = format!("{}-{}", repo_id, file_path.replace("/", "_"));
-            let mut metadata = HashMap::new();
-            metadata.insert("repo_id".to_string(), serde_json::Value::String(repo_id.to_string()));
-            metadata.insert("file_path".to_string(), serde_json::Value::String(file_path));
-            
-            self.index_document("code", &doc_id, &content, Some(metadata)).await?;
-        }
-        
+        self.index_documents(collection, documents).await?;
+
+        let mut stale: HashMap<String, Vec<String>> = HashMap::new();
+        for (path, entry) in files {
+            if let Some(previous) = manifest.files.insert(path, entry.clone()) {
+                let moved = previous.collection != entry.collection;
+                let ids = previous.chunk_ids.into_iter().filter(|id| moved || !entry.chunk_ids.contains(id));
+                stale.entry(previous.collection).or_default().extend(ids);
+            }
+        }
+        for (collection, ids) in stale {
+            self.delete_points(&collection, &ids).await?;
+        }
+
+        self.manifests.save(manifest).await?;
+        Ok(())
+    }
+
+    /// Delete points from a collection and, while it is being migrated, from
+    /// the collection replacing it
+    async fn delete_points(&self, collection_name: &str, ids: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let store = self.store()?;
+        store.delete(collection_name, ids).await?;
+        let write_target = self.write_targets.resolve(collection_name);
+        if write_target != collection_name {
+            store.delete(&write_target, ids).await?;
+        }
+        store.flush().await?;
         Ok(())
     }
 
+    /// Split a file into chunks, each becoming a document carrying the file's
+    /// metadata plus its line range and symbol
+    fn file_documents(&self, repo_id: &str, file: &IndexedFile, profile: &RepoProfile) -> Vec<Document> {
+        let language = file
+            .language
+            .clone()
+            .or_else(|| profile.classifier.language(&file.path, &file.content));
+        let metadata = file_metadata(repo_id, file, language.as_deref());
+
+        profile
+            .chunker
+            .chunk(&file.path, language.as_deref(), &file.content)
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut metadata = metadata.clone();
+                add_chunk_metadata(&mut metadata, &chunk, index);
+                let embedding_text = chunk.embedding_text();
+                let id = chunk_point_id(repo_id, &file.path, index);
+                let document = Document::new(id, chunk.content, metadata);
+                if chunk.breadcrumb.is_some() {
+                    document.with_embedding_text(embedding_text)
+                } else {
+                    document
+                }
+            })
+            .collect()
+    }
+
+    /// Files indexed into a collection as (repository id, path), for every registered repository
+    pub async fn indexed_paths(&self, collection_name: &str) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+        let mut paths = Vec::new();
+        for repo in self.registry.repos() {
+            let manifest = self.manifests.lock(&repo.id).await?;
+            paths.extend(
+                manifest
+                    .files
+                    .iter()
+                    .filter(|(_, entry)| entry.collection == collection_name)
+                    .map(|(path, _)| (repo.id.clone(), path.clone())),
+            );
+        }
+        Ok(paths)
+    }
+
+    /// Repository paths of the files indexed for a repository
+    pub async fn repo_paths(&self, repo_id: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let manifest = self.manifests.lock(repo_id).await?;
+        Ok(manifest.files.keys().cloned().collect())
+    }
+
+    /// Stored chunks of an indexed file in chunk order, with the collection
+    /// holding them; `None` if the file is not indexed
+    pub async fn indexed_chunks(
+        &self,
+        repo_id: &str,
+        path: &str,
+    ) -> Result<Option<(String, Vec<String>)>, Box<dyn std::error::Error>> {
+        let entry = self.manifests.lock(repo_id).await?.files.get(path).cloned();
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+        let mut chunks = Vec::new();
+        for id in &entry.chunk_ids {
+            if let Some(content) = self.get_document(&entry.collection, id).await? {
+                chunks.push(content);
+            }
+        }
+        Ok(Some((entry.collection, chunks)))
+    }
+
     /// Get a document by ID
     pub async fn get_document(&self, collection_name: &str, id: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
-        if let Some(client) = &self.qdrant_client {
-            // In a real implementation, we would retrieve the document from Qdrant
-            // For now, return a mock result
-            Ok(Some(format!("Content of document {}", id)))
-        } else {
-            Err("Qdrant client not initialized".into())
+        let store = self.store()?;
+        let payload = store.get(collection_name, id).await?;
+        Ok(payload.and_then(|payload| match payload.get("content") {
+            Some(Value::String(content)) => Some(content.clone()),
+            _ => None,
+        }))
+    }
+}
+
+/// Files given as (path, content), hashed for change detection
+fn content_files(files: Vec<(String, String)>) -> Vec<IndexedFile> {
+    files
+        .into_iter()
+        .map(|(path, content)| IndexedFile {
+            hash: manifest::content_hash(&content),
+            path,
+            content,
+            language: None,
+            modified_at: None,
+            commit: None,
+        })
+        .collect()
+}
+
+/// Files read from a checkout, hashed for change detection
+fn walked_files(files: Vec<WalkedFile>) -> Vec<IndexedFile> {
+    files
+        .into_iter()
+        .map(|file| IndexedFile {
+            hash: manifest::content_hash(&file.content),
+            path: file.path,
+            content: file.content,
+            language: None,
+            modified_at: file.modified_at,
+            commit: None,
+        })
+        .collect()
+}
+
+/// Content, language and hash of the named files, with the reasons the
+/// others could not be read
+fn read_repo_files(
+    repo_id: &str,
+    root: Option<&Path>,
+    files: Vec<RepoFile>,
+    max_file_size: u64,
+) -> (Vec<IndexedFile>, Vec<FileFailure>) {
+    let mut indexed = Vec::new();
+    let mut failures = Vec::new();
+    for file in files {
+        let path = walker::normalize_repo_path(root, &file.path);
+        let read = match (file.content, root) {
+            (Some(content), _) => Ok((content, file.modified_at)),
+            (None, Some(root)) => walker::read_file(root, &path, max_file_size)
+                .map(|read| (read.content, read.modified_at))
+                .map_err(|e| e.to_string()),
+            (None, None) => Err(format!("no root directory is registered for repository {}", repo_id)),
+        };
+        match read {
+            Ok((content, modified_at)) => indexed.push(IndexedFile {
+                hash: manifest::content_hash(&content),
+                language: None,
+                path,
+                content,
+                modified_at,
+                commit: None,
+            }),
+            Err(error) => failures.push(FileFailure { path, error }),
+        }
+    }
+    (indexed, failures)
+}
+
+/// Stable id of a file's chunk: a UUIDv5 of the repository, path and chunk
+/// position, so re-indexing a file overwrites its own points. The position is
+/// used rather than the symbol, which can repeat within a file.
+pub fn chunk_point_id(repo_id: &str, file_path: &str, chunk_index: usize) -> String {
+    // NUL cannot occur in either name, so distinct triples never share a key
+    let key = format!("{}\0{}\0{}", repo_id, file_path, chunk_index);
+    Uuid::new_v5(&POINT_ID_NAMESPACE, key.as_bytes()).to_string()
+}
+
+/// Differences between a configured collection and the one in the store,
+/// including an embedding model other than the one the collection is meant for
+fn collection_mismatches(
+    config: &CollectionConfig,
+    spec: &CollectionSpec,
+    existing: &CollectionSpec,
+    embedder: Option<&dyn Embedder>,
+) -> Vec<String> {
+    let mut mismatches = spec.mismatches(existing);
+    let Some(embedder) = embedder else {
+        return mismatches;
+    };
+    if let Some(expected) = &config.embedding_model {
+        if embedder.model_id() != expected {
+            mismatches.push(format!(
+                "configured for embedding model {} but {} is loaded",
+                expected,
+                embedder.model_id()
+            ));
         }
     }
+    if spec.vector_size != embedder.dimension() as u64 {
+        mismatches.push(format!(
+            "vector size {} is configured but {} produces {}-dimensional vectors",
+            spec.vector_size,
+            embedder.model_id(),
+            embedder.dimension()
+        ));
+    }
+    mismatches
+}
+
+/// Whether a collection's vectors come from another model than `embedder`.
+/// Collections that predate recording the model are judged by vector size.
+fn needs_reembedding(existing: &CollectionSpec, embedder: &dyn Embedder) -> bool {
+    match &existing.embedding_model {
+        Some(model) => model != embedder.model_id(),
+        None => existing.vector_size != embedder.dimension() as u64,
+    }
+}
+
+/// Payload metadata shared by every chunk of a file
+fn file_metadata(repo_id: &str, file: &IndexedFile, language: Option<&str>) -> HashMap<String, Value> {
+    let modified_at = file.modified_at.unwrap_or_else(Utc::now);
+    let mut metadata = HashMap::new();
+    metadata.insert("repo_id".to_string(), Value::String(repo_id.to_string()));
+    metadata.insert("file_path".to_string(), Value::String(file.path.clone()));
+    metadata.insert(PATH_PREFIXES_FIELD.to_string(), serde_json::json!(filter::path_prefixes(&file.path)));
+    if let Some(extension) = filter::extension(&file.path) {
+        metadata.insert(EXTENSION_FIELD.to_string(), Value::String(extension));
+    }
+    metadata.insert(MODIFIED_AT_FIELD.to_string(), serde_json::json!(modified_at.timestamp()));
+    if let Some(language) = language {
+        metadata.insert("language".to_string(), Value::String(language.to_string()));
+    }
+    metadata.insert("hash".to_string(), Value::String(file.hash.clone()));
+    if let Some(commit) = &file.commit {
+        metadata.insert("commit".to_string(), Value::String(commit.clone()));
+    }
+    metadata
+}
+
+fn add_chunk_metadata(metadata: &mut HashMap<String, Value>, chunk: &Chunk, index: usize) {
+    metadata.insert("chunk_index".to_string(), Value::from(index));
+    metadata.insert("start_line".to_string(), Value::from(chunk.start_line));
+    metadata.insert("end_line".to_string(), Value::from(chunk.end_line));
+    if let Some(symbol) = &chunk.symbol {
+        metadata.insert("symbol".to_string(), Value::String(symbol.clone()));
+    }
+    if let Some(kind) = &chunk.kind {
+        metadata.insert("kind".to_string(), Value::String(kind.clone()));
+    }
+    if let Some(breadcrumb) = &chunk.breadcrumb {
+        metadata.insert("breadcrumb".to_string(), Value::String(breadcrumb.clone()));
+    }
 }
 
 impl Default for RagClient {
@@ -179,6 +1065,8 @@ impl Default for RagClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rag::store::{EmbeddedStore, ScrollPage, StoreError};
+    use crate::rag::test_support::{temp_dir, test_client, test_client_with, FixedEmbedder};
 
     #[tokio::test]
     async fn test_rag_client_creation() {
@@ -187,6 +1075,544 @@ mod tests {
         assert_eq!(rag_client.collections.len(), 3);
     }
 
+    #[tokio::test]
+    async fn test_vector_size_follows_embedder() {
+        let rag_client = RagClient::default();
+        assert_eq!(rag_client.vector_size(), DEFAULT_VECTOR_SIZE);
+
+        let rag_client = rag_client.with_embedder(Arc::new(FixedEmbedder::default()));
+        assert_eq!(rag_client.vector_size(), 768);
+        assert_eq!(rag_client.embed_query("query").await.unwrap().len(), 768);
+    }
+
+    #[tokio::test]
+    async fn test_index_and_search_with_embedded_store() {
+        let directory = temp_dir("client");
+        let (rag_client, _) = test_client(&directory).await;
+
+        // Indexing twice replaces the points instead of duplicating them
+        for _ in 0..2 {
+            rag_client
+                .index_repo_files(
+                    "repo",
+                    vec![
+                        ("src/config.rs".to_string(), "fn load_settings() {}\n".to_string()),
+                        ("src/server.rs".to_string(), "fn start_server() {}\n".to_string()),
+                    ],
+                )
+                .await
+                .unwrap();
+        }
+
+        let request = SearchRequest {
+            query: "load settings".to_string(),
+            context: Some("code".to_string()),
+            limit: Some(5),
+            score_threshold: None,
+            mode: Some(SearchMode::Sparse),
+            filter: None,
+        };
+        let results = rag_client.search(&request).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].source, "src/config.rs");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_incremental_indexing_with_manifest() {
+        let directory = temp_dir("client");
+        let (rag_client, store) = test_client(&directory).await;
+
+        let files = |lib: &str| {
+            vec![
+                ("src/lib.rs".to_string(), lib.to_string()),
+                ("src/config.rs".to_string(), "fn load_settings() {}\n".to_string()),
+            ]
+        };
+        let all = FileSelector::default();
+        let report = rag_client.sync_repo_files("repo", files("fn a() {}\n\nfn b() {}\n"), &all).await.unwrap();
+        assert_eq!((report.added, report.unchanged), (2, 0));
+        let report = rag_client.index_repo_files("repo", files("fn a() {}\n\nfn b() {}\n")).await.unwrap();
+        assert_eq!((report.added, report.updated, report.unchanged), (0, 0, 2));
+
+        // lib.rs loses its second chunk and config.rs is deleted
+        let report = rag_client
+            .sync_repo_files("repo", vec![("src/lib.rs".to_string(), "fn a() {}\n".to_string())], &all)
+            .await
+            .unwrap();
+        assert_eq!((report.updated, report.removed, report.unchanged), (1, 1, 0));
+        assert!(store.get("code", &chunk_point_id("repo", "src/lib.rs", 0)).await.unwrap().is_some());
+        assert!(store.get("code", &chunk_point_id("repo", "src/lib.rs", 1)).await.unwrap().is_none());
+        assert!(store.get("code", &chunk_point_id("repo", "src/config.rs", 0)).await.unwrap().is_none());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_run_keeps_committed_files() {
+        let directory = temp_dir("client");
+        // The documentation collection cannot hold the embedder's vectors, so indexing into it fails
+        let mut collections = RagConfig::default().collections;
+        for collection in collections.iter_mut().filter(|collection| collection.name == "documentation") {
+            collection.vector_size = Some(3);
+        }
+        let (rag_client, _) = test_client_with(&directory, |client| client.with_collections(collections)).await;
+
+        let code = ("src/lib.rs".to_string(), "fn a() {}\n".to_string());
+        let docs = ("README.md".to_string(), "# Project\n\nHow to build it.\n".to_string());
+        assert!(rag_client.index_repo_files("repo", vec![code.clone(), docs]).await.is_err());
+
+        // The code collection comes first and was recorded before the failure
+        let report = rag_client.index_repo_files("repo", vec![code]).await.unwrap();
+        assert_eq!((report.added, report.unchanged), (0, 1));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_initialize_requires_routed_collections() {
+        let directory = temp_dir("client");
+        let store: Arc<dyn VectorStore> = Arc::new(EmbeddedStore::open(directory.join(".vectors")).await.unwrap());
+        let mut collections = RagConfig::default().collections;
+        collections.retain(|collection| collection.name != "config");
+        let mut rag_client = RagClient::default()
+            .with_embedder(Arc::new(FixedEmbedder::default()))
+            .with_store(store.clone())
+            .with_collections(collections);
+
+        let error = rag_client.initialize().await.unwrap_err().to_string();
+        assert!(error.contains("\"config\" is not configured"), "{}", error);
+        assert!(store.collections().await.unwrap().is_empty());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_index_directory_routes_files_by_class() {
+        let root = temp_dir("repo");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("README.md"), "# Project\n\nHow to build it.\n").unwrap();
+        std::fs::write(root.join("Cargo.toml"), "[package]\nname = \"project\"\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("src/legacy.rs"), "fn old() {}\n").unwrap();
+        let a_month_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(30 * 24 * 3600);
+        std::fs::File::options()
+            .write(true)
+            .open(root.join("src/legacy.rs"))
+            .unwrap()
+            .set_modified(a_month_ago)
+            .unwrap();
+
+        let (rag_client, store) = test_client(&root).await;
+
+        let classes = vec![FileClass::Documentation, FileClass::Code];
+        let (report, summary) = rag_client.index_directory("repo", &root, &[], &[], classes).await.unwrap();
+        assert_eq!((report.added, summary.files), (3, 3));
+        assert!(store.get("documentation", &chunk_point_id("repo", "README.md", 0)).await.unwrap().is_some());
+        assert!(store.get("code", &chunk_point_id("repo", "src/main.rs", 0)).await.unwrap().is_some());
+        assert!(store.get("config", &chunk_point_id("repo", "Cargo.toml", 0)).await.unwrap().is_none());
+
+        // Files keep their modification time, so older ones can be filtered out
+        let request = SearchRequest {
+            query: "fn".to_string(),
+            context: Some("code".to_string()),
+            limit: Some(5),
+            score_threshold: None,
+            mode: Some(SearchMode::Dense),
+            filter: Some(crate::mcp::types::SearchFilter {
+                modified_after: Some(Utc::now() - chrono::Duration::days(1)),
+                ..Default::default()
+            }),
+        };
+        let sources: Vec<String> = rag_client.search(&request).await.unwrap().into_iter().map(|result| result.source).collect();
+        assert_eq!(sources, vec!["src/main.rs"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_remove_repo_purges_points_and_registration() {
+        let root = temp_dir("repo");
+        let other = temp_dir("repo");
+        for checkout in [&root, &other] {
+            std::fs::create_dir_all(checkout.join("src")).unwrap();
+            std::fs::write(checkout.join("README.md"), "# Project\n").unwrap();
+            std::fs::write(checkout.join("src/main.rs"), "fn main() {}\n").unwrap();
+        }
+
+        let (rag_client, store) = test_client(&root).await;
+
+        let classes = vec![FileClass::Documentation, FileClass::Code];
+        rag_client.index_directory("repo", &root, &[], &[], classes.clone()).await.unwrap();
+        // A second id for the same checkout is refused rather than indexed twice
+        assert!(rag_client.index_directory("other", &root, &[], &[], classes.clone()).await.is_err());
+        rag_client.index_directory("other", &other, &[], &[], classes).await.unwrap();
+        let repo = rag_client.registry().get("repo").unwrap();
+        assert_eq!(repo.root, root.canonicalize().unwrap());
+        assert_eq!(repo.last_report.unwrap().added, 2);
+
+        let (report, removed) = rag_client.remove_repo("repo").await.unwrap();
+        assert_eq!(report.removed, 2);
+        assert!(removed.is_some());
+        assert!(rag_client.repo_root("repo").is_none());
+        assert!(store.get("code", &chunk_point_id("repo", "src/main.rs", 0)).await.unwrap().is_none());
+        assert!(store.get("code", &chunk_point_id("other", "src/main.rs", 0)).await.unwrap().is_some());
+
+        std::fs::remove_dir_all(&root).unwrap();
+        std::fs::remove_dir_all(&other).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_repo_config_file_applies_and_explains() {
+        let root = temp_dir("repo");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("guide")).unwrap();
+        std::fs::write(root.join(".cerebus.toml"), "docs = [\"guide\"]\nexclude = [\"*.gen.rs\"]\n").unwrap();
+        std::fs::write(root.join("guide/example.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("src/schema.gen.rs"), "struct Schema;\n").unwrap();
+
+        let (rag_client, store) = test_client(&root).await;
+
+        let classes = vec![FileClass::Documentation, FileClass::Code];
+        let (report, _) = rag_client.index_directory("repo", &root, &[], &[], classes.clone()).await.unwrap();
+        assert_eq!(report.added, 2);
+        assert!(store.get("documentation", &chunk_point_id("repo", "guide/example.rs", 0)).await.unwrap().is_some());
+
+        let explained = rag_client
+            .explain_path("repo", &root, "src/schema.gen.rs", &[], &[], classes.clone())
+            .await
+            .unwrap();
+        assert!(!explained.indexed && !explained.selected);
+        assert_eq!(explained.reason.as_deref(), Some("it matches the exclude glob *.gen.rs"));
+        let explained = rag_client
+            .explain_path("repo", &root, "guide/example.rs", &[], &[], classes.clone())
+            .await
+            .unwrap();
+        assert!(explained.indexed && explained.selected);
+        assert_eq!((explained.collection, explained.language.as_deref()), ("documentation", Some("rust")));
+
+        // Dropping the docs route moves the example to the code collection
+        std::fs::write(root.join(".cerebus.toml"), "exclude = [\"*.gen.rs\"]\n").unwrap();
+        let (report, _) = rag_client.index_directory("repo", &root, &[], &[], classes.clone()).await.unwrap();
+        assert_eq!((report.updated, report.unchanged), (2, 0));
+        assert!(store.get("documentation", &chunk_point_id("repo", "guide/example.rs", 0)).await.unwrap().is_none());
+        assert!(store.get("code", &chunk_point_id("repo", "guide/example.rs", 0)).await.unwrap().is_some());
+        let (report, _) = rag_client.index_directory("repo", &root, &[], &[], classes).await.unwrap();
+        assert_eq!(report.unchanged, 2);
+
+        std::fs::write(root.join(".cerebus.toml"), "[chunking]\noverlap_tokens = 4096\n").unwrap();
+        assert!(rag_client.index_directory("repo", &root, &[], &[], vec![FileClass::Code]).await.is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_index_repo_files_detects_language_and_routes() {
+        let directory = temp_dir("vectors");
+        let indexing = IndexingConfig {
+            routes: vec![crate::utils::config::RouteConfig {
+                glob: "examples/**".to_string(),
+                class: Some(FileClass::Documentation),
+                language: None,
+            }],
+            ..Default::default()
+        };
+        let (rag_client, store) = test_client_with(&directory, |client| client.with_indexing(indexing)).await;
+
+        let files = vec![
+            ("bin/deploy".to_string(), "#!/usr/bin/env bash\necho deploy\n".to_string()),
+            ("fixtures/users".to_string(), "[{\"name\": \"ada\"}]".to_string()),
+            ("examples/hello.rs".to_string(), "fn main() {}\n".to_string()),
+        ];
+        let report = rag_client.index_repo_files("repo", files).await.unwrap();
+        assert_eq!(report.added, 3);
+
+        let script = store.get("code", &chunk_point_id("repo", "bin/deploy", 0)).await.unwrap().unwrap();
+        assert_eq!(script["language"], "shell");
+        let fixture = store.get("config", &chunk_point_id("repo", "fixtures/users", 0)).await.unwrap().unwrap();
+        assert_eq!(fixture["language"], "json");
+        let example = store.get("documentation", &chunk_point_id("repo", "examples/hello.rs", 0)).await.unwrap().unwrap();
+        assert_eq!(example["language"], "rust");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_index_repo_paths_reads_files_and_reports_failures() {
+        let root = temp_dir("repo");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn on_disk() {}\n").unwrap();
+        std::fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0]).unwrap();
+
+        let (rag_client, store) = test_client(&root).await;
+
+        let file = |path: &str, content: Option<&str>| RepoFile {
+            path: path.to_string(),
+            content: content.map(str::to_string),
+            modified_at: None,
+        };
+        let (_, failures) = rag_client.index_repo_paths("repo", vec![file("src/lib.rs", None)]).await.unwrap();
+        assert_eq!(failures.len(), 1);
+
+        rag_client.register_root("repo", &root).await.unwrap();
+        let files = vec![
+            file("./src/lib.rs", None),
+            file("src/draft.rs", Some("fn unsaved() {}\n")),
+            file("logo.png", None),
+            file("../outside.rs", None),
+        ];
+        let (report, failures) = rag_client.index_repo_paths("repo", files).await.unwrap();
+        assert_eq!(report.added, 2);
+        let failed: Vec<&str> = failures.iter().map(|failure| failure.path.as_str()).collect();
+        assert_eq!(failed, vec!["logo.png", "../outside.rs"]);
+
+        let stored = store.get("code", &chunk_point_id("repo", "src/lib.rs", 0)).await.unwrap().unwrap();
+        assert_eq!(stored["content"], "fn on_disk() {}");
+        assert_eq!(stored["language"], "rust");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_index_revision_records_commit_and_diffs() {
+        use crate::rag::git::tests::commit;
+
+        let root = temp_dir("repo");
+        let repository = git2::Repository::init(&root).unwrap();
+        let first = commit(
+            &repository,
+            &[
+                ("src/lib.rs", Some("fn a() {}\n")),
+                ("src/old.rs", Some("fn old() {}\n")),
+                ("src/keep.rs", Some("fn keep() {}\n")),
+            ],
+            "first",
+        );
+        let second = commit(&repository, &[("src/lib.rs", Some("fn b() {}\n")), ("src/old.rs", None)], "second");
+        commit(&repository, &[("src/lib.rs", Some("\0\0binary"))], "third");
+
+        let (rag_client, store) = test_client(&root).await;
+
+        let classes = vec![FileClass::Code];
+        let (report, revision) =
+            rag_client.index_revision("repo", &root, &first, None, &[], &[], classes.clone()).await.unwrap();
+        assert_eq!((report.added, revision.commit.as_str()), (3, first.as_str()));
+        let lib = chunk_point_id("repo", "src/lib.rs", 0);
+        assert_eq!(store.get("code", &lib).await.unwrap().unwrap()["commit"], first.as_str());
+
+        // The base defaults to the commit last indexed, so only the diff is read
+        let (report, _) = rag_client
+            .index_revision("repo", &root, &second, None, &[], &[], classes.clone())
+            .await
+            .unwrap();
+        assert_eq!((report.updated, report.removed, report.unchanged), (1, 1, 0));
+        let payload = store.get("code", &lib).await.unwrap().unwrap();
+        assert_eq!((payload["commit"].as_str(), payload["content"].as_str()), (Some(second.as_str()), Some("fn b() {}")));
+        assert!(store.get("code", &chunk_point_id("repo", "src/old.rs", 0)).await.unwrap().is_none());
+
+        // A file that became binary is removed rather than left at its old content
+        let (report, _) = rag_client
+            .index_revision("repo", &root, "HEAD", Some(&second), &[], &[], classes.clone())
+            .await
+            .unwrap();
+        assert_eq!((report.removed, report.unchanged), (1, 0));
+        assert!(store.get("code", &lib).await.unwrap().is_none());
+
+        // A base the index is not at falls back to reading the whole tree
+        let (report, _) = rag_client
+            .index_revision("repo", &root, "HEAD", Some(&first), &[], &[], classes)
+            .await
+            .unwrap();
+        assert_eq!((report.added, report.updated, report.unchanged), (0, 0, 1));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_model_change_migrates_collection() {
+        let directory = temp_dir("client");
+        let (rag_client, store) = test_client(&directory).await;
+        assert!(rag_client.outdated_collections().is_empty());
+        rag_client
+            .index_repo_files("repo", vec![("src/config.rs".to_string(), "fn load_settings() {}\n".to_string())])
+            .await
+            .unwrap();
+
+        let mut rag_client = RagClient::default()
+            .with_embedder(Arc::new(FixedEmbedder::new("small", 4)))
+            .with_store(store.clone());
+        rag_client.initialize().await.unwrap();
+        assert_eq!(rag_client.outdated_collections().len(), 3);
+
+        let migration = rag_client.migration("code").await.unwrap();
+        assert_eq!(migration.target, "code_v2");
+        let report = migration.run().await.unwrap();
+        assert_eq!(report.migrated, 1);
+        assert_eq!(store.alias_target("code").await.unwrap().as_deref(), Some("code_v2"));
+
+        // Searches follow the alias and the new collection records the model
+        let request = SearchRequest {
+            query: "settings".to_string(),
+            context: Some("code".to_string()),
+            limit: Some(5),
+            score_threshold: None,
+            mode: Some(SearchMode::Dense),
+            filter: None,
+        };
+        assert_eq!(rag_client.search(&request).await.unwrap()[0].source, "src/config.rs");
+        let mut rag_client = RagClient::default()
+            .with_embedder(Arc::new(FixedEmbedder::new("small", 4)))
+            .with_store(store);
+        rag_client.initialize().await.unwrap();
+        assert!(!rag_client.outdated_collections().contains(&"code".to_string()));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// Embedded store whose aliases cannot be set
+    struct NoAliasStore(Arc<dyn VectorStore>);
+
+    #[async_trait::async_trait]
+    impl VectorStore for NoAliasStore {
+        fn name(&self) -> &str {
+            "no-alias"
+        }
+
+        async fn ensure_collection(&self, collection: &str, spec: &CollectionSpec) -> Result<CollectionSpec, StoreError> {
+            self.0.ensure_collection(collection, spec).await
+        }
+
+        async fn upsert(&self, collection: &str, points: Vec<Point>) -> Result<(), StoreError> {
+            self.0.upsert(collection, points).await
+        }
+
+        async fn query(
+            &self,
+            collection: &str,
+            vector: QueryVector,
+            limit: u64,
+            score_threshold: Option<f32>,
+            filter: &CompiledFilter,
+        ) -> Result<Vec<SearchResult>, StoreError> {
+            self.0.query(collection, vector, limit, score_threshold, filter).await
+        }
+
+        async fn delete(&self, collection: &str, ids: &[String]) -> Result<(), StoreError> {
+            self.0.delete(collection, ids).await
+        }
+
+        async fn get(&self, collection: &str, id: &str) -> Result<Option<HashMap<String, Value>>, StoreError> {
+            self.0.get(collection, id).await
+        }
+
+        async fn existing(&self, collection: &str, ids: &[String]) -> Result<HashSet<String>, StoreError> {
+            self.0.existing(collection, ids).await
+        }
+
+        async fn scroll(&self, collection: &str, offset: Option<String>, limit: u32) -> Result<ScrollPage, StoreError> {
+            self.0.scroll(collection, offset, limit).await
+        }
+
+        async fn collections(&self) -> Result<Vec<String>, StoreError> {
+            self.0.collections().await
+        }
+
+        async fn alias_target(&self, alias: &str) -> Result<Option<String>, StoreError> {
+            self.0.alias_target(alias).await
+        }
+
+        async fn set_alias(&self, alias: &str, _collection: &str) -> Result<(), StoreError> {
+            Err(StoreError::Backend(format!("cannot create alias {}", alias)))
+        }
+
+        async fn delete_collection(&self, collection: &str) -> Result<(), StoreError> {
+            self.0.delete_collection(collection).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_first_migration_resumes_on_start() {
+        let directory = temp_dir("client");
+        let (rag_client, store) = test_client(&directory).await;
+        rag_client
+            .index_repo_files("repo", vec![("src/config.rs".to_string(), "fn load_settings() {}\n".to_string())])
+            .await
+            .unwrap();
+
+        // The original collection is dropped, then creating the alias fails
+        let small = || Arc::new(FixedEmbedder::new("small", 4));
+        let failing: Arc<dyn VectorStore> = Arc::new(NoAliasStore(store.clone()));
+        let mut rag_client = RagClient::default().with_embedder(small()).with_store(failing);
+        rag_client.initialize().await.unwrap();
+        assert!(rag_client.migration("code").await.unwrap().run().await.is_err());
+        assert!(store.alias_target("code").await.unwrap().is_none());
+        assert!(!store.collections().await.unwrap().contains(&"code".to_string()));
+
+        // The next start points the alias at the migrated collection instead of recreating an empty one
+        let mut rag_client = RagClient::default().with_embedder(small()).with_store(store.clone());
+        rag_client.initialize().await.unwrap();
+        assert_eq!(store.alias_target("code").await.unwrap().as_deref(), Some("code_v2"));
+        assert!(!rag_client.outdated_collections().contains(&"code".to_string()));
+        let request = SearchRequest {
+            query: "settings".to_string(),
+            context: Some("code".to_string()),
+            limit: Some(5),
+            score_threshold: None,
+            mode: Some(SearchMode::Dense),
+            filter: None,
+        };
+        assert_eq!(rag_client.search(&request).await.unwrap()[0].source, "src/config.rs");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_collection_mismatches_include_embedder() {
+        let mut config = CollectionConfig::new("code");
+        config.vector_size = Some(384);
+        config.embedding_model = Some("all-MiniLM-L6-v2".to_string());
+        let spec = CollectionSpec::from_config(&config, 768);
+
+        assert!(collection_mismatches(&config, &spec, &spec, None).is_empty());
+        let mismatches = collection_mismatches(&config, &spec, &spec, Some(&FixedEmbedder::default()));
+        assert_eq!(mismatches.len(), 2);
+        assert!(mismatches[0].contains("all-MiniLM-L6-v2 but fixed is loaded"));
+    }
+
+    #[test]
+    fn test_document_payload_and_point_id() {
+        let rag_client = RagClient::default();
+        let file = content_files(vec![("src/lib.rs".to_string(), "fn a() {}\n\nfn b() {}\n".to_string())]).remove(0);
+        let documents = rag_client.file_documents("repo", &file, &RepoProfile::default());
+        assert_eq!(documents.len(), 2);
+
+        let payload = documents[1].payload();
+        assert_eq!(payload["file_path"], "src/lib.rs");
+        assert_eq!(payload["language"], "rust");
+        assert_eq!(payload["symbol"], "b");
+        assert_eq!(payload["start_line"], 3);
+        assert_eq!(payload["end_line"], 3);
+        assert_eq!(payload["document_id"], chunk_point_id("repo", "src/lib.rs", 1));
+        assert_eq!(documents[1].point_id(), chunk_point_id("repo", "src/lib.rs", 1));
+        assert_eq!(payload["content"], "fn b() {}");
+
+        let numeric = Document::new("42".to_string(), String::new(), HashMap::new());
+        assert_eq!(numeric.point_id(), "42");
+        let named = Document::new("guide".to_string(), String::new(), HashMap::new());
+        assert_eq!(named.point_id(), named.clone().point_id());
+        assert!(Uuid::parse_str(&named.point_id()).is_ok());
+    }
+
+    #[test]
+    fn test_chunk_point_ids_are_stable_and_distinct() {
+        assert_eq!(chunk_point_id("repo", "a/b_c", 0), chunk_point_id("repo", "a/b_c", 0));
+        assert_ne!(chunk_point_id("repo", "a/b_c", 0), chunk_point_id("repo", "a_b/c", 0));
+        assert_ne!(chunk_point_id("repo", "a/b_c", 0), chunk_point_id("repo", "a/b_c", 1));
+        assert_ne!(chunk_point_id("repo", "src", 0), chunk_point_id("other", "src", 0));
+    }
+
     #[tokio::test]
     #[ignore] // Ignore until Qdrant is available
     async fn test_rag_client_initialization() {
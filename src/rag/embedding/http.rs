@@ -0,0 +1,201 @@
+// src/rag/embedding/http.rs
+use super::{Embedder, EmbeddingError};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Wire format spoken by the embedding service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpApi {
+    /// OpenAI-compatible `POST /v1/embeddings`
+    OpenAi,
+    /// Ollama `POST /api/embed`
+    Ollama,
+}
+
+impl HttpApi {
+    pub fn from_provider(provider: &str) -> Option<Self> {
+        match provider {
+            "openai" => Some(Self::OpenAi),
+            "ollama" => Some(Self::Ollama),
+            _ => None,
+        }
+    }
+
+    fn default_endpoint(&self) -> &'static str {
+        match self {
+            Self::OpenAi => "https://api.openai.com",
+            Self::Ollama => "http://localhost:11434",
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        match self {
+            Self::OpenAi => "/v1/embeddings",
+            Self::Ollama => "/api/embed",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAiResponse {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct OllamaResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// Embedder backed by a remote inference server
+pub struct HttpEmbedder {
+    client: reqwest::Client,
+    api: HttpApi,
+    url: String,
+    model: String,
+    dimension: usize,
+    batch_size: usize,
+    auth: Option<(String, String)>,
+}
+
+impl HttpEmbedder {
+    pub fn new(api: HttpApi, endpoint: Option<&str>, model: &str, dimension: usize) -> Self {
+        let endpoint = endpoint.unwrap_or(api.default_endpoint()).trim_end_matches('/');
+        Self {
+            client: reqwest::Client::new(),
+            api,
+            url: format!("{}{}", endpoint, api.path()),
+            model: model.to_string(),
+            dimension,
+            batch_size: 32,
+            auth: None,
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Send `name: value` with every request, e.g. `Authorization: Bearer <key>`
+    pub fn with_auth_header(mut self, name: &str, value: &str) -> Self {
+        self.auth = Some((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self, EmbeddingError> {
+        self.client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(self)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "input": texts,
+        });
+
+        let mut request = self.client.post(&self.url).json(&body);
+        if let Some((name, value)) = &self.auth {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(EmbeddingError::Api(format!("{} returned {}: {}", self.url, status, message)));
+        }
+
+        let body = response.bytes().await?;
+        let vectors = parse_response(self.api, &body)?;
+        if vectors.len() != texts.len() {
+            return Err(EmbeddingError::Api(format!(
+                "expected {} embeddings, got {}",
+                texts.len(),
+                vectors.len()
+            )));
+        }
+        if let Some(vector) = vectors.iter().find(|vector| vector.len() != self.dimension) {
+            return Err(EmbeddingError::Config(format!(
+                "model {} returned {}-dimensional vectors, but {} are configured",
+                self.model,
+                vector.len(),
+                self.dimension
+            )));
+        }
+
+        Ok(vectors)
+    }
+}
+
+#[async_trait]
+impl Embedder for HttpEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            vectors.extend(self.embed_batch(batch).await?);
+        }
+        Ok(vectors)
+    }
+}
+
+/// Extract vectors in input order from a provider response body
+fn parse_response(api: HttpApi, body: &[u8]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+    let invalid = |e: serde_json::Error| EmbeddingError::Api(format!("invalid embedding response: {}", e));
+    match api {
+        HttpApi::OpenAi => {
+            let mut response: OpenAiResponse = serde_json::from_slice(body).map_err(invalid)?;
+            // The API does not promise to keep input order
+            response.data.sort_by_key(|item| item.index);
+            Ok(response.data.into_iter().map(|item| item.embedding).collect())
+        }
+        HttpApi::Ollama => {
+            let response: OllamaResponse = serde_json::from_slice(body).map_err(invalid)?;
+            Ok(response.embeddings)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_openai_response_in_input_order() {
+        let body = br#"{"object":"list","data":[
+            {"object":"embedding","index":1,"embedding":[0.3,0.4]},
+            {"object":"embedding","index":0,"embedding":[0.1,0.2]}
+        ],"model":"text-embedding-3-small"}"#;
+
+        let vectors = parse_response(HttpApi::OpenAi, body).unwrap();
+        assert_eq!(vectors, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    }
+
+    #[test]
+    fn test_parse_ollama_response() {
+        let body = br#"{"model":"nomic-embed-text","embeddings":[[0.5,0.6]]}"#;
+
+        let vectors = parse_response(HttpApi::Ollama, body).unwrap();
+        assert_eq!(vectors, vec![vec![0.5, 0.6]]);
+    }
+
+    #[test]
+    fn test_endpoint_path() {
+        let embedder = HttpEmbedder::new(HttpApi::Ollama, Some("http://gpu-box:11434/"), "nomic-embed-text", 768);
+        assert_eq!(embedder.url, "http://gpu-box:11434/api/embed");
+    }
+}
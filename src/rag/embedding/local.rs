@@ -0,0 +1,194 @@
+// src/rag/embedding/local.rs
+use super::{Embedder, EmbeddingError};
+use async_trait::async_trait;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use std::path::Path;
+use std::sync::Arc;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+/// CPU sentence embedder for BERT-style models such as all-MiniLM-L6-v2.
+///
+/// Loads `config.json`, `tokenizer.json` and `model.safetensors` (or
+/// `pytorch_model.bin`) from a local directory, so no network access is
+/// needed at runtime. Vectors are mean-pooled and L2-normalized, matching
+/// sentence-transformers output.
+pub struct LocalEmbedder {
+    model_id: String,
+    model: Arc<BertModel>,
+    tokenizer: Arc<Tokenizer>,
+    device: Device,
+    dimension: usize,
+    batch_size: usize,
+}
+
+impl LocalEmbedder {
+    pub fn load(model_dir: impl AsRef<Path>, max_tokens: usize, batch_size: usize) -> Result<Self, EmbeddingError> {
+        let model_dir = model_dir.as_ref();
+        let device = Device::Cpu;
+        let files = BertFiles::load(model_dir, max_tokens, &device)?;
+        let model = BertModel::load(files.weights, &files.config).map_err(model_error)?;
+
+        Ok(Self {
+            model_id: model_id(model_dir),
+            model: Arc::new(model),
+            tokenizer: Arc::new(files.tokenizer),
+            device,
+            dimension: files.config.hidden_size,
+            batch_size: batch_size.max(1),
+        })
+    }
+
+    fn embed_batch(
+        model: &BertModel,
+        tokenizer: &Tokenizer,
+        device: &Device,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let encodings = tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| EmbeddingError::Tokenizer(e.to_string()))?;
+
+        let ids = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_ids(), device))
+            .collect::<candle_core::Result<Vec<_>>>()
+            .map_err(model_error)?;
+        let masks = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_attention_mask(), device))
+            .collect::<candle_core::Result<Vec<_>>>()
+            .map_err(model_error)?;
+
+        let embeddings = (|| {
+            let input_ids = Tensor::stack(&ids, 0)?;
+            let attention_mask = Tensor::stack(&masks, 0)?;
+            let token_type_ids = input_ids.zeros_like()?;
+            let hidden = model.forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+            normalize(&mean_pool(&hidden, &attention_mask)?)?.to_vec2::<f32>()
+        })();
+        embeddings.map_err(model_error)
+    }
+}
+
+#[async_trait]
+impl Embedder for LocalEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let model = self.model.clone();
+        let tokenizer = self.tokenizer.clone();
+        let device = self.device.clone();
+        let batch_size = self.batch_size;
+        let texts = texts.to_vec();
+
+        // Inference is CPU-bound; keep it off the async workers
+        tokio::task::spawn_blocking(move || {
+            let mut vectors = Vec::with_capacity(texts.len());
+            for batch in texts.chunks(batch_size) {
+                vectors.extend(Self::embed_batch(&model, &tokenizer, &device, batch)?);
+            }
+            Ok(vectors)
+        })
+        .await
+        .map_err(|e| EmbeddingError::Model(e.to_string()))?
+    }
+}
+
+/// Config, tokenizer and weights of a BERT-style model directory, shared with
+/// the cross-encoder reranker
+pub(crate) struct BertFiles {
+    pub config: Config,
+    pub tokenizer: Tokenizer,
+    pub weights: VarBuilder<'static>,
+}
+
+impl BertFiles {
+    /// Read `config.json`, `tokenizer.json` and `model.safetensors` (or
+    /// `pytorch_model.bin`), padding batches and truncating to `max_tokens`
+    pub fn load(model_dir: &Path, max_tokens: usize, device: &Device) -> Result<Self, EmbeddingError> {
+        let config: Config = serde_json::from_str(&std::fs::read_to_string(model_dir.join("config.json"))?)
+            .map_err(|e| EmbeddingError::Model(format!("invalid config.json: {}", e)))?;
+
+        let mut tokenizer = Tokenizer::from_file(model_dir.join("tokenizer.json"))
+            .map_err(|e| EmbeddingError::Tokenizer(e.to_string()))?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: max_tokens,
+                ..Default::default()
+            }))
+            .map_err(|e| EmbeddingError::Tokenizer(e.to_string()))?;
+
+        let safetensors = model_dir.join("model.safetensors");
+        let weights = if safetensors.exists() {
+            // Safety: the weights file is not modified while the model is loaded
+            unsafe { VarBuilder::from_mmaped_safetensors(&[safetensors], DTYPE, device) }
+        } else {
+            VarBuilder::from_pth(model_dir.join("pytorch_model.bin"), DTYPE, device)
+        }
+        .map_err(model_error)?;
+
+        Ok(Self { config, tokenizer, weights })
+    }
+}
+
+/// Model id reported for a local model: the name of its directory
+pub(crate) fn model_id(model_dir: &Path) -> String {
+    model_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| model_dir.display().to_string())
+}
+
+/// Average token embeddings, ignoring padding positions
+fn mean_pool(hidden: &Tensor, attention_mask: &Tensor) -> candle_core::Result<Tensor> {
+    let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+    let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+    let counts = mask.sum(1)?.maximum(1e-9f32)?;
+    summed.broadcast_div(&counts)
+}
+
+/// Scale each row to unit length so cosine similarity is a dot product
+fn normalize(embeddings: &Tensor) -> candle_core::Result<Tensor> {
+    let norms = embeddings.sqr()?.sum_keepdim(1)?.sqrt()?.maximum(1e-12f32)?;
+    embeddings.broadcast_div(&norms)
+}
+
+fn model_error(e: candle_core::Error) -> EmbeddingError {
+    EmbeddingError::Model(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mean_pool_ignores_padding() {
+        let hidden = Tensor::new(&[[[1f32, 2.0], [3.0, 4.0], [100.0, 100.0]]], &Device::Cpu).unwrap();
+        let mask = Tensor::new(&[[1u32, 1, 0]], &Device::Cpu).unwrap();
+
+        let pooled = mean_pool(&hidden, &mask).unwrap().to_vec2::<f32>().unwrap();
+        assert_eq!(pooled, vec![vec![2.0, 3.0]]);
+    }
+
+    #[test]
+    fn test_normalize_unit_length() {
+        let embeddings = Tensor::new(&[[3f32, 4.0], [0.0, 0.0]], &Device::Cpu).unwrap();
+
+        let normalized = normalize(&embeddings).unwrap().to_vec2::<f32>().unwrap();
+        assert_eq!(normalized[0], vec![0.6, 0.8]);
+        assert_eq!(normalized[1], vec![0.0, 0.0]);
+    }
+}
@@ -0,0 +1,85 @@
+// src/rag/embedding/mod.rs
+pub mod http;
+#[cfg(feature = "local-embeddings")]
+pub mod local;
+
+#[cfg(feature = "local-embeddings")]
+pub use local::LocalEmbedder;
+pub use http::{HttpApi, HttpEmbedder};
+
+use crate::utils::config::EmbeddingConfig;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmbeddingError {
+    #[error("Embedding model error: {0}")]
+    Model(String),
+    #[error("Tokenizer error: {0}")]
+    Tokenizer(String),
+    #[error("Invalid embedding configuration: {0}")]
+    Config(String),
+    #[error("Embedding service error: {0}")]
+    Api(String),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Turns text into dense vectors for storage and retrieval
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Identifier of the model producing the vectors
+    fn model_id(&self) -> &str;
+
+    /// Length of every vector this embedder returns
+    fn dimension(&self) -> usize;
+
+    /// Embed a batch of documents, returning one vector per input in order
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError>;
+
+    /// Embed a single search query
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>, EmbeddingError> {
+        self.embed(&[query.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| EmbeddingError::Model("No embedding returned for query".to_string()))
+    }
+}
+
+/// Build the embedder selected by the `[embedding]` settings
+pub fn from_config(config: &EmbeddingConfig) -> Result<Arc<dyn Embedder>, EmbeddingError> {
+    match config.provider.as_str() {
+        #[cfg(feature = "local-embeddings")]
+        "local" => {
+            let embedder = LocalEmbedder::load(&config.model_path, config.max_tokens, config.batch_size)?;
+            if embedder.dimension() != config.dimension {
+                return Err(EmbeddingError::Config(format!(
+                    "model at {} produces {}-dimensional vectors, but {} are configured",
+                    config.model_path,
+                    embedder.dimension(),
+                    config.dimension
+                )));
+            }
+            Ok(Arc::new(embedder))
+        }
+        #[cfg(not(feature = "local-embeddings"))]
+        "local" => Err(EmbeddingError::Config(
+            "built without the local-embeddings feature".to_string(),
+        )),
+        provider => match HttpApi::from_provider(provider) {
+            Some(api) => {
+                let mut embedder = HttpEmbedder::new(api, config.endpoint.as_deref(), &config.model, config.dimension)
+                    .with_batch_size(config.batch_size)
+                    .with_timeout(Duration::from_secs(config.timeout_seconds))?;
+                if let Some(value) = &config.auth_header {
+                    embedder = embedder.with_auth_header(&config.auth_header_name, value);
+                }
+                Ok(Arc::new(embedder))
+            }
+            None => Err(EmbeddingError::Config(format!("unknown embedding provider: {}", provider))),
+        },
+    }
+}
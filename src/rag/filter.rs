@@ -0,0 +1,245 @@
+// src/rag/filter.rs
+use crate::mcp::types::{SearchFilter, SearchResult};
+use globset::{GlobBuilder, GlobMatcher};
+use qdrant_client::qdrant::{Condition, FieldType, Filter, Range};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Payload field holding the file path and each of its parent directories,
+/// so a path prefix becomes a keyword match
+pub const PATH_PREFIXES_FIELD: &str = "path_prefixes";
+/// Payload field holding the lowercase file extension without the dot
+pub const EXTENSION_FIELD: &str = "extension";
+/// Payload field holding the file modification time in Unix seconds
+pub const MODIFIED_AT_FIELD: &str = "modified_at";
+
+/// Payload fields indexed in every collection so filtered searches stay fast
+pub const PAYLOAD_INDEXES: &[(&str, FieldType)] = &[
+    ("repo_id", FieldType::Keyword),
+    ("language", FieldType::Keyword),
+    (EXTENSION_FIELD, FieldType::Keyword),
+    (PATH_PREFIXES_FIELD, FieldType::Keyword),
+    (MODIFIED_AT_FIELD, FieldType::Integer),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum FilterError {
+    #[error("Invalid path glob: {0}")]
+    Glob(#[from] globset::Error),
+    #[error("Cannot filter on payload field {0}: only strings, integers and booleans can be matched")]
+    Payload(String),
+}
+
+/// A search filter split into the conditions Qdrant evaluates and the path
+/// glob, which Qdrant cannot express and is checked on the returned results
+#[derive(Debug, Clone, Default)]
+pub struct CompiledFilter {
+    pub conditions: Option<Filter>,
+    pub glob: Option<GlobMatcher>,
+    filter: SearchFilter,
+}
+
+impl CompiledFilter {
+    pub fn new(filter: &SearchFilter) -> Result<Self, FilterError> {
+        let mut conditions = Vec::new();
+
+        if let Some(repo_id) = &filter.repo_id {
+            conditions.push(Condition::matches("repo_id", repo_id.clone()));
+        }
+        if let Some(language) = &filter.language {
+            conditions.push(Condition::matches("language", language.clone()));
+        }
+        if let Some(extension) = &filter.extension {
+            let extension = extension.trim_start_matches('.').to_lowercase();
+            conditions.push(Condition::matches(EXTENSION_FIELD, extension));
+        }
+        if let Some(prefix) = filter.path_prefix.as_deref().map(normalize_path).filter(|p| !p.is_empty()) {
+            conditions.push(Condition::matches(PATH_PREFIXES_FIELD, prefix));
+        }
+        if let Some(modified_after) = filter.modified_after {
+            conditions.push(Condition::range(
+                MODIFIED_AT_FIELD,
+                Range {
+                    gt: Some(modified_after.timestamp() as f64),
+                    ..Default::default()
+                },
+            ));
+        }
+        for (key, value) in &filter.payload {
+            conditions.push(match value {
+                Value::String(value) => Condition::matches(key.clone(), value.clone()),
+                Value::Bool(value) => Condition::matches(key.clone(), *value),
+                Value::Number(number) => match number.as_i64() {
+                    Some(value) => Condition::matches(key.clone(), value),
+                    None => return Err(FilterError::Payload(key.clone())),
+                },
+                _ => return Err(FilterError::Payload(key.clone())),
+            });
+        }
+
+        let glob = match &filter.path_glob {
+            Some(pattern) => {
+                let pattern = normalize_path(pattern);
+                // Narrow the candidates in Qdrant by the glob's literal directories
+                if let Some(prefix) = glob_prefix(&pattern) {
+                    conditions.push(Condition::matches(PATH_PREFIXES_FIELD, prefix));
+                }
+                Some(GlobBuilder::new(&pattern).literal_separator(true).build()?.compile_matcher())
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            conditions: (!conditions.is_empty()).then(|| Filter::must(conditions)),
+            glob,
+            filter: filter.clone(),
+        })
+    }
+
+    /// Whether results must be checked after retrieval, so more candidates are needed
+    pub fn needs_post_filter(&self) -> bool {
+        self.glob.is_some()
+    }
+
+    /// Check a retrieved result against the parts Qdrant could not evaluate
+    pub fn matches(&self, result: &SearchResult) -> bool {
+        let Some(glob) = &self.glob else {
+            return true;
+        };
+        result
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("file_path"))
+            .and_then(Value::as_str)
+            .is_some_and(|path| glob.is_match(path))
+    }
+
+    /// Evaluate the whole filter against a stored payload, for backends
+    /// without their own filtering
+    pub fn matches_payload(&self, payload: &HashMap<String, Value>) -> bool {
+        let field = |key: &str, expected: &Value| payload.get(key).is_some_and(|value| value_matches(value, expected));
+        let filter = &self.filter;
+
+        filter.repo_id.as_ref().is_none_or(|repo_id| field("repo_id", &Value::from(repo_id.as_str())))
+            && filter.language.as_ref().is_none_or(|language| field("language", &Value::from(language.as_str())))
+            && filter.extension.as_ref().is_none_or(|extension| {
+                field(EXTENSION_FIELD, &Value::from(extension.trim_start_matches('.').to_lowercase()))
+            })
+            && filter
+                .path_prefix
+                .as_deref()
+                .map(normalize_path)
+                .filter(|prefix| !prefix.is_empty())
+                .is_none_or(|prefix| field(PATH_PREFIXES_FIELD, &Value::from(prefix)))
+            && filter.modified_after.is_none_or(|after| {
+                payload
+                    .get(MODIFIED_AT_FIELD)
+                    .and_then(Value::as_i64)
+                    .is_some_and(|modified_at| modified_at > after.timestamp())
+            })
+            && filter.payload.iter().all(|(key, expected)| field(key, expected))
+            && self.glob.as_ref().is_none_or(|glob| {
+                payload
+                    .get("file_path")
+                    .and_then(Value::as_str)
+                    .is_some_and(|path| glob.is_match(normalize_path(path)))
+            })
+    }
+}
+
+/// Keyword semantics: an array field matches when any element equals the value
+fn value_matches(value: &Value, expected: &Value) -> bool {
+    match value {
+        Value::Array(values) => values.contains(expected),
+        value => value == expected,
+    }
+}
+
+/// The path and every parent directory: `src/rag/client.rs` ->
+/// `src`, `src/rag`, `src/rag/client.rs`
+pub fn path_prefixes(path: &str) -> Vec<String> {
+    let path = normalize_path(path);
+    path.match_indices('/')
+        .map(|(index, _)| path[..index].to_string())
+        .chain(std::iter::once(path.clone()))
+        .filter(|prefix| !prefix.is_empty())
+        .collect()
+}
+
+/// Lowercase extension of a path, without the dot
+pub fn extension(path: &str) -> Option<String> {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+}
+
+/// Leading directories of a glob that contain no wildcards: `src/rag/**/*.rs` -> `src/rag`
+fn glob_prefix(pattern: &str) -> Option<String> {
+    let directories: Vec<&str> = pattern.split('/').collect();
+    let literal: Vec<&str> = directories[..directories.len() - 1]
+        .iter()
+        .take_while(|part| !part.contains(['*', '?', '[', '{', '\\']))
+        .copied()
+        .collect();
+    (!literal.is_empty()).then(|| literal.join("/"))
+}
+
+fn normalize_path(path: &str) -> String {
+    path.trim_start_matches("./").trim_end_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_prefixes_and_glob_prefix() {
+        assert_eq!(path_prefixes("./src/rag/client.rs"), vec!["src", "src/rag", "src/rag/client.rs"]);
+        assert_eq!(glob_prefix("src/rag/**/*.rs"), Some("src/rag".to_string()));
+        assert_eq!(glob_prefix("**/*.rs"), None);
+        assert_eq!(glob_prefix("README.md"), None);
+    }
+
+    #[test]
+    fn test_compiled_filter_conditions() {
+        let filter = SearchFilter {
+            repo_id: Some("cerebus".to_string()),
+            extension: Some(".RS".to_string()),
+            path_glob: Some("src/**/*.rs".to_string()),
+            payload: HashMap::from([("kind".to_string(), serde_json::json!("function_item"))]),
+            ..Default::default()
+        };
+
+        let compiled = CompiledFilter::new(&filter).unwrap();
+        // repo, extension, payload and the glob's literal directory
+        assert_eq!(compiled.conditions.as_ref().unwrap().must.len(), 4);
+        assert!(compiled.needs_post_filter());
+        assert!(CompiledFilter::new(&SearchFilter::default()).unwrap().conditions.is_none());
+    }
+
+    #[test]
+    fn test_glob_post_filter_and_invalid_payload() {
+        let compiled = CompiledFilter::new(&SearchFilter {
+            path_glob: Some("src/*.rs".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let result = |path: &str| SearchResult {
+            id: path.to_string(),
+            content: String::new(),
+            source: path.to_string(),
+            score: 1.0,
+            metadata: Some(HashMap::from([("file_path".to_string(), serde_json::json!(path))])),
+        };
+        assert!(compiled.matches(&result("src/main.rs")));
+        assert!(!compiled.matches(&result("src/rag/client.rs")));
+
+        let invalid = SearchFilter {
+            payload: HashMap::from([("score".to_string(), serde_json::json!(0.5))]),
+            ..Default::default()
+        };
+        assert!(matches!(CompiledFilter::new(&invalid), Err(FilterError::Payload(_))));
+    }
+}
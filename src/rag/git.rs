@@ -0,0 +1,241 @@
+// src/rag/git.rs
+use crate::rag::walker::{self, FileSelector, WalkSummary, WalkedFile};
+use chrono::{DateTime, TimeZone, Utc};
+use git2::{Delta, ObjectType, Oid, Repository, Tree, TreeWalkMode, TreeWalkResult};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GitError {
+    #[error(transparent)]
+    Git(#[from] git2::Error),
+    #[error("{0} is not valid UTF-8")]
+    NonUtf8Path(String),
+}
+
+/// Files of a commit, read from the object database rather than a checkout
+#[derive(Debug, Clone)]
+pub struct Revision {
+    /// Full id of the commit the revision resolved to
+    pub commit: String,
+    pub committed_at: DateTime<Utc>,
+    /// Selected text files added or changed
+    pub files: Vec<WalkedFile>,
+    /// Paths deleted since the base revision, when diffing
+    pub deleted: Vec<String>,
+    pub summary: WalkSummary,
+}
+
+/// Read the selected files of `rev` (a branch, tag, commit id or any other
+/// revision git understands) from the repository at `root`. Hidden files,
+/// binaries and blobs over `max_file_size` bytes are skipped as in a walk.
+pub fn read_revision(root: &Path, rev: &str, selector: &FileSelector, max_file_size: u64) -> Result<Revision, GitError> {
+    let repository = Repository::open(root)?;
+    let commit = repository.revparse_single(rev)?.peel_to_commit()?;
+    let mut revision = new_revision(&commit);
+
+    let tree = commit.tree()?;
+    let mut paths = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |directory, entry| {
+        // Submodules are commits and symlinks hold only their target
+        if entry.kind() != Some(ObjectType::Blob) || entry.filemode() == 0o120000 {
+            return TreeWalkResult::Ok;
+        }
+        match entry.name() {
+            Ok(name) => paths.push((format!("{}{}", directory, name), entry.id())),
+            Err(_) => revision.summary.unreadable += 1,
+        }
+        TreeWalkResult::Ok
+    })?;
+
+    for (path, id) in paths {
+        read_blob(&repository, &path, id, selector, max_file_size, &mut revision)?;
+    }
+    revision.summary.files = revision.files.len();
+    Ok(revision)
+}
+
+/// Read only the selected files that differ between `base` and `rev`, and
+/// list the paths `rev` no longer has. A rename is a deletion plus an addition.
+/// Changed files that are no longer indexable (deselected, too large or
+/// binary) are listed as deleted, so their old content is not left behind.
+pub fn diff_revisions(
+    root: &Path,
+    base: &str,
+    rev: &str,
+    selector: &FileSelector,
+    max_file_size: u64,
+) -> Result<Revision, GitError> {
+    let repository = Repository::open(root)?;
+    let base_tree = repository.revparse_single(base)?.peel_to_tree()?;
+    let commit = repository.revparse_single(rev)?.peel_to_commit()?;
+    let mut revision = new_revision(&commit);
+
+    let tree = commit.tree()?;
+    let diff = repository.diff_tree_to_tree(Some(&base_tree), Some(&tree), None)?;
+    for delta in diff.deltas() {
+        if matches!(delta.status(), Delta::Deleted | Delta::Renamed) {
+            if let Some(path) = delta.old_file().path() {
+                revision.deleted.push(utf8(path)?);
+            }
+        }
+        if matches!(delta.status(), Delta::Added | Delta::Modified | Delta::Renamed | Delta::Copied | Delta::Typechange) {
+            let Some(path) = delta.new_file().path() else {
+                continue;
+            };
+            let path = utf8(path)?;
+            // Symlinks and submodules have no blob to read
+            let read = match blob_id(&tree, &path) {
+                Some(id) => read_blob(&repository, &path, id, selector, max_file_size, &mut revision)?,
+                None => false,
+            };
+            if !read {
+                revision.deleted.push(path);
+            }
+        }
+    }
+    revision.summary.files = revision.files.len();
+    Ok(revision)
+}
+
+/// Full id of the commit `rev` resolves to in the repository at `root`
+pub fn resolve_commit(root: &Path, rev: &str) -> Result<String, GitError> {
+    let repository = Repository::open(root)?;
+    let commit = repository.revparse_single(rev)?.peel_to_commit()?;
+    Ok(commit.id().to_string())
+}
+
+/// URL of the `origin` remote of the repository at `root`, if it has one
+pub fn origin_url(root: &Path) -> Option<String> {
+    let repository = Repository::open(root).ok()?;
+    let remote = repository.find_remote("origin").ok()?;
+    remote.url().ok().map(str::to_string)
+}
+
+fn new_revision(commit: &git2::Commit) -> Revision {
+    Revision {
+        commit: commit.id().to_string(),
+        committed_at: Utc.timestamp_opt(commit.time().seconds(), 0).single().unwrap_or_else(Utc::now),
+        files: Vec::new(),
+        deleted: Vec::new(),
+        summary: WalkSummary::default(),
+    }
+}
+
+fn blob_id(tree: &Tree, path: &str) -> Option<Oid> {
+    let entry = tree.get_path(Path::new(path)).ok()?;
+    (entry.kind() == Some(ObjectType::Blob) && entry.filemode() != 0o120000).then(|| entry.id())
+}
+
+fn read_blob(
+    repository: &Repository,
+    path: &str,
+    id: Oid,
+    selector: &FileSelector,
+    max_file_size: u64,
+    revision: &mut Revision,
+) -> Result<bool, GitError> {
+    if path.split('/').any(|part| part.starts_with('.')) || !selector.selects(path) {
+        return Ok(false);
+    }
+    let blob = repository.find_blob(id)?;
+    if blob.size() as u64 > max_file_size {
+        revision.summary.skipped_large += 1;
+        return Ok(false);
+    }
+    match walker::decode_text(blob.content().to_vec()) {
+        Some(content) => revision.files.push(WalkedFile {
+            path: path.to_string(),
+            content,
+            // Revisions are dated by their commit instead
+            modified_at: None,
+        }),
+        None => {
+            revision.summary.skipped_binary += 1;
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn utf8(path: &Path) -> Result<String, GitError> {
+    path.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| GitError::NonUtf8Path(path.display().to_string()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use git2::Signature;
+
+    /// Write, or with `None` delete, files in the work tree and commit them
+    pub(crate) fn commit(repository: &Repository, files: &[(&str, Option<&str>)], message: &str) -> String {
+        let root = repository.workdir().unwrap();
+        let mut index = repository.index().unwrap();
+        for (path, content) in files {
+            match content {
+                Some(content) => {
+                    let file = root.join(path);
+                    std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+                    std::fs::write(&file, content).unwrap();
+                    index.add_path(Path::new(path)).unwrap();
+                }
+                None => {
+                    std::fs::remove_file(root.join(path)).unwrap();
+                    index.remove_path(Path::new(path)).unwrap();
+                }
+            }
+        }
+        index.write().unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repository.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repository
+            .commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_read_and_diff_revisions() {
+        let root = std::env::temp_dir().join(format!("cerebus-git-{}", uuid::Uuid::new_v4()));
+        let repository = Repository::init(&root).unwrap();
+        let first = commit(
+            &repository,
+            &[
+                ("README.md", Some("# Project\n")),
+                ("src/lib.rs", Some("fn a() {}\n")),
+                ("src/old.rs", Some("fn old() {}\n")),
+                ("logo.png", Some("\u{0}PNG")),
+            ],
+            "first",
+        );
+        let second = commit(
+            &repository,
+            &[("src/lib.rs", Some("fn a() {}\nfn b() {}\n")), ("src/old.rs", None)],
+            "second",
+        );
+        // The working tree differs from both commits
+        std::fs::write(root.join("src/lib.rs"), "uncommitted\n").unwrap();
+
+        let selector = FileSelector::default();
+        let revision = read_revision(&root, &first[..8], &selector, 1024).unwrap();
+        assert_eq!(revision.commit, first);
+        let paths: Vec<&str> = revision.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["README.md", "src/lib.rs", "src/old.rs"]);
+        assert_eq!(revision.summary.skipped_binary, 1);
+
+        let head = read_revision(&root, "HEAD", &selector, 1024).unwrap();
+        assert_eq!(head.commit, second);
+        assert!(head.files.iter().any(|file| file.content == "fn a() {}\nfn b() {}\n"));
+
+        let diff = diff_revisions(&root, &first, "HEAD", &selector, 1024).unwrap();
+        let changed: Vec<&str> = diff.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(changed, vec!["src/lib.rs"]);
+        assert_eq!(diff.deleted, vec!["src/old.rs"]);
+
+        assert!(read_revision(&root, "no-such-branch", &selector, 1024).is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
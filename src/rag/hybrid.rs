@@ -0,0 +1,194 @@
+// src/rag/hybrid.rs
+use crate::mcp::types::SearchResult;
+use std::collections::HashMap;
+
+/// Name of the dense embedding vector in every collection
+pub const DENSE_VECTOR: &str = "dense";
+/// Name of the sparse lexical vector in every collection
+pub const SPARSE_VECTOR: &str = "bm25";
+
+/// BM25 term frequency saturation
+const BM25_K1: f32 = 1.2;
+/// BM25 document length normalization
+const BM25_B: f32 = 0.75;
+/// Assumed average chunk length in terms. Chunks are size-capped, so a fixed
+/// value stands in for corpus statistics.
+const AVERAGE_DOCUMENT_TERMS: f32 = 256.0;
+
+/// Constant in reciprocal rank fusion; damps the weight of the very top ranks
+pub const RRF_K: f32 = 60.0;
+
+/// Split text into lowercase lexical terms. Identifiers are kept whole and
+/// also broken into their snake_case and camelCase parts, so
+/// `index_repo_files` matches both exactly and on `repo`.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in text.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        let word = word.trim_matches('_');
+        if word.chars().count() < 2 {
+            continue;
+        }
+        terms.push(word.to_lowercase());
+
+        let parts = identifier_parts(word);
+        if parts.len() > 1 {
+            terms.extend(
+                parts
+                    .into_iter()
+                    .filter(|part| part.chars().count() >= 2)
+                    .map(|part| part.to_lowercase()),
+            );
+        }
+    }
+    terms
+}
+
+/// Break an identifier at underscores, lower-to-upper case changes and
+/// letter/digit boundaries: `parseHTTPRequest2` -> `parse`, `HTTP`, `Request`, `2`
+fn identifier_parts(word: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    for segment in word.split('_').filter(|segment| !segment.is_empty()) {
+        let chars: Vec<(usize, char)> = segment.char_indices().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (offset, current) = chars[i];
+            let previous = chars[i - 1].1;
+            let next = chars.get(i + 1).map(|(_, c)| *c);
+            let boundary = (previous.is_lowercase() && current.is_uppercase())
+                || (previous.is_uppercase() && current.is_uppercase() && next.is_some_and(char::is_lowercase))
+                || (previous.is_alphabetic() != current.is_alphabetic());
+            if boundary {
+                parts.push(&segment[start..offset]);
+                start = offset;
+            }
+        }
+        parts.push(&segment[start..]);
+    }
+    parts
+}
+
+/// Stable 32-bit FNV-1a hash used as the sparse dimension of a term
+fn term_index(term: &str) -> u32 {
+    term.bytes()
+        .fold(0x811c9dc5u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x01000193))
+}
+
+fn sparse_from_counts(counts: HashMap<u32, f32>) -> (Vec<u32>, Vec<f32>) {
+    let mut entries: Vec<(u32, f32)> = counts.into_iter().collect();
+    entries.sort_by_key(|(index, _)| *index);
+    entries.into_iter().unzip()
+}
+
+/// Sparse document vector holding the BM25 term-frequency component.
+/// The collection applies IDF, so the dot product with a query vector is BM25.
+pub fn document_vector(text: &str) -> (Vec<u32>, Vec<f32>) {
+    let terms = tokenize(text);
+    let length = terms.len() as f32;
+
+    let mut frequencies: HashMap<u32, f32> = HashMap::new();
+    for term in &terms {
+        *frequencies.entry(term_index(term)).or_default() += 1.0;
+    }
+
+    let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * length / AVERAGE_DOCUMENT_TERMS);
+    for tf in frequencies.values_mut() {
+        *tf = *tf * (BM25_K1 + 1.0) / (*tf + norm);
+    }
+    sparse_from_counts(frequencies)
+}
+
+/// Sparse query vector: every distinct term weighted once
+pub fn query_vector(text: &str) -> (Vec<u32>, Vec<f32>) {
+    let counts = tokenize(text)
+        .iter()
+        .map(|term| (term_index(term), 1.0))
+        .collect();
+    sparse_from_counts(counts)
+}
+
+/// Merge ranked lists by reciprocal rank fusion. Each result's score becomes
+/// the sum of `1 / (k + rank)` over the lists it appears in, and its score in
+/// each list is kept in metadata as `<label>_score`.
+pub fn reciprocal_rank_fusion(lists: Vec<(&str, Vec<SearchResult>)>, k: f32) -> Vec<SearchResult> {
+    let mut fused: Vec<SearchResult> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut scores: Vec<f32> = Vec::new();
+
+    for (label, results) in lists {
+        for (rank, result) in results.into_iter().enumerate() {
+            let score = result.score;
+            let position = match positions.get(&result.id) {
+                Some(&position) => position,
+                None => {
+                    positions.insert(result.id.clone(), fused.len());
+                    fused.push(result);
+                    scores.push(0.0);
+                    fused.len() - 1
+                }
+            };
+
+            scores[position] += 1.0 / (k + rank as f32 + 1.0);
+            fused[position]
+                .metadata
+                .get_or_insert_with(HashMap::new)
+                .insert(format!("{}_score", label), serde_json::json!(score));
+        }
+    }
+
+    for (result, score) in fused.iter_mut().zip(scores) {
+        result.score = score;
+    }
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(id: &str, score: f32) -> SearchResult {
+        SearchResult {
+            id: id.to_string(),
+            content: String::new(),
+            source: String::new(),
+            score,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_tokenize_splits_identifiers() {
+        assert_eq!(
+            tokenize("fn index_repo_files(parseHTTPRequest)"),
+            vec!["fn", "index_repo_files", "index", "repo", "files", "parsehttprequest", "parse", "http", "request"]
+        );
+    }
+
+    #[test]
+    fn test_document_and_query_share_dimensions() {
+        let (document_indices, document_values) = document_vector("search search query");
+        let (query_indices, _) = query_vector("Search");
+
+        assert_eq!(document_indices.len(), 2);
+        let position = document_indices.iter().position(|i| *i == query_indices[0]).unwrap();
+        let other = 1 - position;
+        assert!(document_values[position] > document_values[other]);
+    }
+
+    #[test]
+    fn test_rrf_rewards_agreement() {
+        let fused = reciprocal_rank_fusion(
+            vec![
+                ("dense", vec![result("a", 0.9), result("b", 0.8)]),
+                ("sparse", vec![result("b", 7.0), result("c", 3.0)]),
+            ],
+            RRF_K,
+        );
+
+        let ids: Vec<&str> = fused.iter().map(|result| result.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "c"]);
+        let metadata = fused[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata["dense_score"], serde_json::json!(0.8f32));
+        assert_eq!(metadata["sparse_score"], serde_json::json!(7.0f32));
+    }
+}
@@ -0,0 +1,495 @@
+// src/rag/jobs.rs
+use crate::rag::classify::FileClass;
+use crate::rag::client::RagClient;
+use crate::rag::manifest::IndexReport;
+use crate::rag::walker::WalkSummary;
+use crate::rag::watcher::RepoWatchers;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, RwLock, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+/// Finished jobs kept for status queries; older ones are forgotten
+pub const MAX_FINISHED_JOBS: usize = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum JobError {
+    #[error("No indexing job {0}")]
+    NotFound(String),
+    #[error("Indexing job {0} has already finished")]
+    Finished(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Corrupt job state: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// What an indexing job indexes, as given to initialize_context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexJobRequest {
+    pub repo_id: String,
+    pub repo_path: String,
+    pub classes: Vec<FileClass>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Git revision to index instead of the working tree
+    #[serde(default)]
+    pub rev: Option<String>,
+    /// With `rev`, only index what changed since this revision
+    #[serde(default)]
+    pub base_rev: Option<String>,
+    /// Watch the repository once indexed
+    #[serde(default)]
+    pub watch: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobState::Completed | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+/// An indexing job and, once finished, its outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexJob {
+    pub id: String,
+    pub request: IndexJobRequest,
+    pub state: JobState,
+    pub submitted_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub report: Option<IndexReport>,
+    pub summary: Option<WalkSummary>,
+    /// Commit indexed, for jobs indexing a git revision
+    pub commit: Option<String>,
+    #[serde(default)]
+    pub watching: bool,
+    pub error: Option<String>,
+}
+
+/// Queue of indexing jobs run in the background by a bounded number of
+/// workers.
+///
+/// Job state is saved on every change when the queue has a file, and jobs
+/// that were queued or running when the server stopped are started again on
+/// the next open. Indexing is incremental, so a resumed or cancelled job
+/// leaves nothing behind that the next run does not pick up.
+pub struct JobQueue {
+    rag_client: Arc<RwLock<RagClient>>,
+    watchers: Arc<RepoWatchers>,
+    workers: Arc<Semaphore>,
+    file: Option<PathBuf>,
+    jobs: Mutex<HashMap<String, IndexJob>>,
+    cancellations: Mutex<HashMap<String, CancellationToken>>,
+    // Saves write the whole file, so they take turns
+    saving: tokio::sync::Mutex<()>,
+    finished: broadcast::Sender<IndexJob>,
+}
+
+impl JobQueue {
+    /// A queue whose jobs last as long as the process
+    pub fn in_memory(rag_client: Arc<RwLock<RagClient>>, watchers: Arc<RepoWatchers>, workers: usize) -> Arc<Self> {
+        Arc::new(Self::new(rag_client, watchers, workers, None))
+    }
+
+    /// A queue persisted to `file`, resuming the unfinished jobs recorded there
+    pub async fn open(
+        rag_client: Arc<RwLock<RagClient>>,
+        watchers: Arc<RepoWatchers>,
+        workers: usize,
+        file: impl AsRef<Path>,
+    ) -> Result<Arc<Self>, JobError> {
+        let file = file.as_ref().to_path_buf();
+        let saved: Vec<IndexJob> = if file.exists() {
+            serde_json::from_slice(&tokio::fs::read(&file).await?)?
+        } else {
+            Vec::new()
+        };
+
+        let queue = Arc::new(Self::new(rag_client, watchers, workers, Some(file)));
+        let mut unfinished = Vec::new();
+        {
+            let mut jobs = queue.lock_jobs();
+            for mut job in saved {
+                if !job.state.is_finished() {
+                    job.state = JobState::Queued;
+                    job.started_at = None;
+                    unfinished.push((job.submitted_at, job.id.clone()));
+                }
+                jobs.insert(job.id.clone(), job);
+            }
+            forget_old_jobs(&mut jobs);
+        }
+        // Resume in submission order
+        unfinished.sort();
+        if !unfinished.is_empty() {
+            tracing::info!("Resuming {} interrupted indexing jobs", unfinished.len());
+        }
+        for (_, id) in unfinished {
+            queue.start(id);
+        }
+        Ok(queue)
+    }
+
+    fn new(
+        rag_client: Arc<RwLock<RagClient>>,
+        watchers: Arc<RepoWatchers>,
+        workers: usize,
+        file: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            rag_client,
+            watchers,
+            workers: Arc::new(Semaphore::new(workers.max(1))),
+            file,
+            jobs: Mutex::default(),
+            cancellations: Mutex::default(),
+            saving: tokio::sync::Mutex::new(()),
+            finished: broadcast::channel(16).0,
+        }
+    }
+
+    /// Queue a job, returning it as submitted
+    pub async fn submit(self: &Arc<Self>, request: IndexJobRequest) -> Result<IndexJob, JobError> {
+        let job = IndexJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            request,
+            state: JobState::Queued,
+            submitted_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            report: None,
+            summary: None,
+            commit: None,
+            watching: false,
+            error: None,
+        };
+        self.lock_jobs().insert(job.id.clone(), job.clone());
+        self.save().await?;
+        self.start(job.id.clone());
+        Ok(job)
+    }
+
+    pub fn status(&self, id: &str) -> Option<IndexJob> {
+        self.lock_jobs().get(id).cloned()
+    }
+
+    /// Every known job, oldest first
+    pub fn jobs(&self) -> Vec<IndexJob> {
+        let mut jobs: Vec<IndexJob> = self.lock_jobs().values().cloned().collect();
+        jobs.sort_by_key(|job| job.submitted_at);
+        jobs
+    }
+
+    /// Cancel a queued or running job
+    pub async fn cancel(&self, id: &str) -> Result<IndexJob, JobError> {
+        let job = {
+            let mut jobs = self.lock_jobs();
+            let job = jobs.get_mut(id).ok_or_else(|| JobError::NotFound(id.to_string()))?;
+            if job.state.is_finished() {
+                return Err(JobError::Finished(id.to_string()));
+            }
+            job.state = JobState::Cancelled;
+            job.finished_at = Some(Utc::now());
+            let job = job.clone();
+            forget_old_jobs(&mut jobs);
+            job
+        };
+        if let Some(token) = self.lock_cancellations().remove(id) {
+            token.cancel();
+        }
+        self.save().await?;
+        let _ = self.finished.send(job.clone());
+        Ok(job)
+    }
+
+    /// Jobs as they finish, whether completed, failed or cancelled
+    pub fn subscribe(&self) -> broadcast::Receiver<IndexJob> {
+        self.finished.subscribe()
+    }
+
+    fn start(self: &Arc<Self>, id: String) {
+        let token = CancellationToken::new();
+        self.lock_cancellations().insert(id.clone(), token.clone());
+        tokio::spawn(self.clone().run(id, token));
+    }
+
+    async fn run(self: Arc<Self>, id: String, token: CancellationToken) {
+        let permit = tokio::select! {
+            _ = token.cancelled() => return,
+            permit = self.workers.clone().acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => return,
+            },
+        };
+
+        let request = {
+            let mut jobs = self.lock_jobs();
+            match jobs.get_mut(&id) {
+                Some(job) if job.state == JobState::Queued => {
+                    job.state = JobState::Running;
+                    job.started_at = Some(Utc::now());
+                    job.request.clone()
+                }
+                _ => return,
+            }
+        };
+        self.save_logged().await;
+
+        let outcome = tokio::select! {
+            _ = token.cancelled() => return,
+            outcome = self.index(&request) => outcome,
+        };
+        // A job cancelled once indexing is done must not leave a watcher behind
+        let watching = match &outcome {
+            Ok(_) if request.watch && !token.is_cancelled() => {
+                let watched = self
+                    .watchers
+                    .watch(&request.repo_id, &request.include, &request.exclude, request.classes.clone())
+                    .await;
+                watched.map_err(|e| tracing::warn!("Failed to watch {}: {}", request.repo_id, e)).is_ok()
+            }
+            _ => false,
+        };
+        drop(permit);
+        self.lock_cancellations().remove(&id);
+
+        let finished = {
+            let mut jobs = self.lock_jobs();
+            let Some(job) = jobs.get_mut(&id).filter(|job| job.state == JobState::Running) else {
+                return;
+            };
+            job.finished_at = Some(Utc::now());
+            job.watching = watching;
+            match outcome {
+                Ok((report, summary, commit)) => {
+                    job.state = JobState::Completed;
+                    job.report = Some(report);
+                    job.summary = Some(summary);
+                    job.commit = commit;
+                }
+                Err(e) => {
+                    tracing::warn!("Indexing job {} failed: {}", id, e);
+                    job.state = JobState::Failed;
+                    job.error = Some(e);
+                }
+            }
+            let finished = job.clone();
+            forget_old_jobs(&mut jobs);
+            finished
+        };
+        self.save_logged().await;
+        let _ = self.finished.send(finished);
+    }
+
+    async fn index(&self, request: &IndexJobRequest) -> Result<(IndexReport, WalkSummary, Option<String>), String> {
+        let client = self.rag_client.read().await;
+        let root = Path::new(&request.repo_path);
+        let classes = request.classes.clone();
+        match &request.rev {
+            Some(rev) => client
+                .index_revision(&request.repo_id, root, rev, request.base_rev.as_deref(), &request.include, &request.exclude, classes)
+                .await
+                .map(|(report, revision)| (report, revision.summary, Some(revision.commit)))
+                .map_err(|e| e.to_string()),
+            None => client
+                .index_directory(&request.repo_id, root, &request.include, &request.exclude, classes)
+                .await
+                .map(|(report, summary)| (report, summary, None))
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    async fn save(&self) -> Result<(), JobError> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let _saving = self.saving.lock().await;
+        let contents = serde_json::to_vec_pretty(&self.jobs())?;
+        if let Some(directory) = file.parent() {
+            tokio::fs::create_dir_all(directory).await?;
+        }
+        let temporary = file.with_extension("json.tmp");
+        tokio::fs::write(&temporary, contents).await?;
+        tokio::fs::rename(&temporary, file).await?;
+        Ok(())
+    }
+
+    async fn save_logged(&self) {
+        if let Err(e) = self.save().await {
+            tracing::warn!("Failed to save indexing jobs: {}", e);
+        }
+    }
+
+    fn lock_jobs(&self) -> std::sync::MutexGuard<'_, HashMap<String, IndexJob>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_cancellations(&self) -> std::sync::MutexGuard<'_, HashMap<String, CancellationToken>> {
+        self.cancellations.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Drop the oldest finished jobs beyond `MAX_FINISHED_JOBS`
+fn forget_old_jobs(jobs: &mut HashMap<String, IndexJob>) {
+    let mut finished: Vec<(DateTime<Utc>, String)> = jobs
+        .values()
+        .filter(|job| job.state.is_finished())
+        .map(|job| (job.finished_at.unwrap_or(job.submitted_at), job.id.clone()))
+        .collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort();
+    for (_, id) in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+        jobs.remove(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::test_support::{temp_dir, test_client};
+    use crate::utils::config::IndexingConfig;
+    use std::time::Duration;
+
+    async fn client(root: &Path) -> Arc<RwLock<RagClient>> {
+        Arc::new(RwLock::new(test_client(root).await.0))
+    }
+
+    fn request(root: &Path) -> IndexJobRequest {
+        IndexJobRequest {
+            repo_id: "repo".to_string(),
+            repo_path: root.display().to_string(),
+            classes: vec![FileClass::Code],
+            include: Vec::new(),
+            exclude: Vec::new(),
+            rev: None,
+            base_rev: None,
+            watch: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jobs_run_in_background_and_report() {
+        let root = temp_dir("jobs");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\n").unwrap();
+
+        let rag_client = client(&root).await;
+        let watchers = Arc::new(RepoWatchers::new(rag_client.clone(), &IndexingConfig::default()));
+        let queue = JobQueue::in_memory(rag_client, watchers, 1);
+        let mut finished = queue.subscribe();
+
+        let job = queue.submit(request(&root)).await.unwrap();
+        let missing = IndexJobRequest {
+            repo_path: root.join("missing").display().to_string(),
+            ..request(&root)
+        };
+        let failing = queue.submit(missing).await.unwrap();
+
+        let done = tokio::time::timeout(Duration::from_secs(10), finished.recv()).await.unwrap().unwrap();
+        assert_eq!((done.id.as_str(), done.state), (job.id.as_str(), JobState::Completed));
+        assert_eq!(done.report.unwrap().added, 1);
+        let failed = tokio::time::timeout(Duration::from_secs(10), finished.recv()).await.unwrap().unwrap();
+        assert_eq!((failed.id.as_str(), failed.state), (failing.id.as_str(), JobState::Failed));
+        assert!(failed.error.is_some());
+
+        assert_eq!(queue.jobs().len(), 2);
+        assert!(matches!(queue.cancel(&job.id).await, Err(JobError::Finished(_))));
+        assert!(matches!(queue.cancel("unknown").await, Err(JobError::NotFound(_))));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unfinished_jobs_resume_after_restart() {
+        let root = temp_dir("jobs");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        let file = root.join("jobs.json");
+
+        // A job the previous process was running, and one it had cancelled
+        let interrupted = IndexJob {
+            id: "interrupted".to_string(),
+            request: request(&root),
+            state: JobState::Running,
+            submitted_at: Utc::now(),
+            started_at: Some(Utc::now()),
+            finished_at: None,
+            report: None,
+            summary: None,
+            commit: None,
+            watching: false,
+            error: None,
+        };
+        let cancelled = IndexJob {
+            id: "cancelled".to_string(),
+            state: JobState::Cancelled,
+            ..interrupted.clone()
+        };
+        std::fs::write(&file, serde_json::to_vec(&vec![interrupted, cancelled]).unwrap()).unwrap();
+
+        let rag_client = client(&root).await;
+        let watchers = Arc::new(RepoWatchers::new(rag_client.clone(), &IndexingConfig::default()));
+        let queue = JobQueue::open(rag_client, watchers, 2, &file).await.unwrap();
+        let mut finished = queue.subscribe();
+        // The job may have finished before the subscription
+        if queue.status("interrupted").is_some_and(|job| !job.state.is_finished()) {
+            tokio::time::timeout(Duration::from_secs(10), finished.recv()).await.unwrap().unwrap();
+        }
+
+        let resumed = queue.status("interrupted").unwrap();
+        assert_eq!(resumed.state, JobState::Completed);
+        assert_eq!(queue.status("cancelled").unwrap().state, JobState::Cancelled);
+
+        let saved: Vec<IndexJob> = serde_json::from_slice(&std::fs::read(&file).unwrap()).unwrap();
+        assert!(saved.iter().any(|job| job.id == "interrupted" && job.state == JobState::Completed));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_jobs_are_pruned_and_not_watched() {
+        let root = temp_dir("jobs");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let rag_client = client(&root).await;
+        let watchers = Arc::new(RepoWatchers::new(rag_client.clone(), &IndexingConfig::default()));
+        let queue = JobQueue::in_memory(rag_client.clone(), watchers.clone(), 1);
+
+        // Jobs wait for the client while it is locked, so they are all cancelled unfinished
+        let locked = rag_client.write().await;
+        let watched = IndexJobRequest {
+            watch: true,
+            ..request(&root)
+        };
+        let mut ids = Vec::new();
+        for _ in 0..=MAX_FINISHED_JOBS {
+            ids.push(queue.submit(watched.clone()).await.unwrap().id);
+        }
+        for id in &ids {
+            queue.cancel(id).await.unwrap();
+        }
+        drop(locked);
+
+        assert_eq!(queue.jobs().len(), MAX_FINISHED_JOBS);
+        assert!(!watchers.is_watching("repo"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
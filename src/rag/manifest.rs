@@ -0,0 +1,190 @@
+// src/rag/manifest.rs
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ManifestError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Corrupt index manifest: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// What was indexed for one file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub hash: String,
+    /// Collection holding the file's chunks
+    pub collection: String,
+    /// Point ids of the chunks, in chunk order
+    pub chunk_ids: Vec<String>,
+    pub indexed_at: DateTime<Utc>,
+    /// Fingerprint of the chunking and routing settings the file was indexed
+    /// with; empty for files indexed before fingerprints were recorded
+    #[serde(default)]
+    pub config: String,
+}
+
+/// Files indexed for a repository, keyed by path
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub repo_id: String,
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+/// How an indexing run changed a repository's index
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexReport {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+/// Per-repository manifests, kept in memory and optionally saved as
+/// `<directory>/<repo_id>.json`.
+///
+/// A repository's manifest is locked for a whole indexing run, so concurrent
+/// runs for the same repository cannot record stale chunk ids.
+#[derive(Default)]
+pub struct ManifestStore {
+    directory: Option<PathBuf>,
+    manifests: Mutex<HashMap<String, Arc<Mutex<Manifest>>>>,
+}
+
+impl ManifestStore {
+    /// Manifests that last as long as the process
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Manifests persisted in `directory`, so restarts keep skipping unchanged files
+    pub fn open(directory: impl AsRef<Path>) -> Self {
+        Self {
+            directory: Some(directory.as_ref().to_path_buf()),
+            manifests: Mutex::default(),
+        }
+    }
+
+    /// Lock a repository's manifest, loading it on first use
+    pub async fn lock(&self, repo_id: &str) -> Result<OwnedMutexGuard<Manifest>, ManifestError> {
+        let manifest = {
+            let mut manifests = self.manifests.lock().await;
+            match manifests.get(repo_id) {
+                Some(manifest) => manifest.clone(),
+                None => {
+                    let manifest = Arc::new(Mutex::new(self.load(repo_id).await?));
+                    manifests.insert(repo_id.to_string(), manifest.clone());
+                    manifest
+                }
+            }
+        };
+        // Waiting for another run on this repository must not hold up other repositories
+        Ok(manifest.lock_owned().await)
+    }
+
+    async fn load(&self, repo_id: &str) -> Result<Manifest, ManifestError> {
+        match self.file(repo_id) {
+            Some(file) if file.exists() => Ok(serde_json::from_slice(&tokio::fs::read(&file).await?)?),
+            _ => Ok(Manifest {
+                repo_id: repo_id.to_string(),
+                files: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Persist a manifest, if this store has a directory
+    pub async fn save(&self, manifest: &Manifest) -> Result<(), ManifestError> {
+        let Some(file) = self.file(&manifest.repo_id) else {
+            return Ok(());
+        };
+        if let Some(directory) = &self.directory {
+            tokio::fs::create_dir_all(directory).await?;
+        }
+        let temporary = file.with_extension("json.tmp");
+        tokio::fs::write(&temporary, serde_json::to_vec_pretty(manifest)?).await?;
+        tokio::fs::rename(&temporary, &file).await?;
+        Ok(())
+    }
+
+    /// Forget a repository's manifest and delete its file
+    pub async fn remove(&self, repo_id: &str) -> Result<(), ManifestError> {
+        self.manifests.lock().await.remove(repo_id);
+        match self.file(repo_id) {
+            Some(file) if file.exists() => Ok(tokio::fs::remove_file(&file).await?),
+            _ => Ok(()),
+        }
+    }
+
+    fn file(&self, repo_id: &str) -> Option<PathBuf> {
+        let directory = self.directory.as_ref()?;
+        // Repository ids are user input; only plain names are used as file names
+        let name = if is_plain_id(repo_id) {
+            repo_id.to_string()
+        } else {
+            uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, repo_id.as_bytes()).to_string()
+        };
+        Some(directory.join(format!("{}.json", name)))
+    }
+}
+
+/// Whether a repository id is a plain name: ASCII letters, digits, `-`, `_`
+/// and `.`, not starting with a dot
+pub fn is_plain_id(repo_id: &str) -> bool {
+    !repo_id.is_empty()
+        && !repo_id.starts_with('.')
+        && repo_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// SHA-256 of a file's content as lowercase hex
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_manifest_persists_across_stores() {
+        let directory = std::env::temp_dir().join(format!("cerebus-manifest-{}", uuid::Uuid::new_v4()));
+        {
+            let store = ManifestStore::open(&directory);
+            let mut manifest = store.lock("backend").await.unwrap();
+            manifest.files.insert(
+                "src/lib.rs".to_string(),
+                ManifestEntry {
+                    hash: content_hash("fn a() {}"),
+                    collection: "code".to_string(),
+                    chunk_ids: vec!["1".to_string()],
+                    indexed_at: Utc::now(),
+                    config: String::new(),
+                },
+            );
+            store.save(&manifest).await.unwrap();
+        }
+
+        let store = ManifestStore::open(&directory);
+        let manifest = store.lock("backend").await.unwrap();
+        assert_eq!(manifest.repo_id, "backend");
+        assert_eq!(manifest.files["src/lib.rs"].chunk_ids, vec!["1"]);
+        assert!(directory.join("backend.json").exists());
+        assert_ne!(store.file("../etc"), Some(directory.join("../etc.json")));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(content_hash("a"), content_hash("b"));
+    }
+}
@@ -0,0 +1,254 @@
+// src/rag/migration.rs
+use crate::rag::embedding::{Embedder, EmbeddingError};
+use crate::rag::hybrid;
+use crate::rag::store::{CollectionSpec, Point, SparseVector, StoreError, VectorStore};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Points read, re-embedded and written per step of a migration
+pub const MIGRATION_BATCH_SIZE: u32 = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error(transparent)]
+    Embedding(#[from] EmbeddingError),
+    #[error("Collection {0} is already being migrated")]
+    InProgress(String),
+}
+
+/// Collections that receive writes in place of the alias being migrated,
+/// keyed by alias. Shared between the client and running migrations.
+#[derive(Debug, Clone, Default)]
+pub struct WriteTargets(Arc<RwLock<HashMap<String, String>>>);
+
+impl WriteTargets {
+    /// Collection that writes to `collection` should go to
+    pub fn resolve(&self, collection: &str) -> String {
+        let targets = self.0.read().unwrap_or_else(|e| e.into_inner());
+        targets.get(collection).cloned().unwrap_or_else(|| collection.to_string())
+    }
+
+    fn claim(&self, alias: &str, target: &str) -> Result<(), MigrationError> {
+        let mut targets = self.0.write().unwrap_or_else(|e| e.into_inner());
+        if targets.contains_key(alias) {
+            return Err(MigrationError::InProgress(alias.to_string()));
+        }
+        targets.insert(alias.to_string(), target.to_string());
+        Ok(())
+    }
+
+    fn release(&self, alias: &str) {
+        self.0.write().unwrap_or_else(|e| e.into_inner()).remove(alias);
+    }
+}
+
+/// Outcome of a completed migration
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    pub alias: String,
+    pub collection: String,
+    /// Points re-embedded from the old collection
+    pub migrated: usize,
+    /// Points left out: already rewritten during the migration or without stored content
+    pub skipped: usize,
+}
+
+/// Rebuilds a collection with the current embedding model.
+///
+/// Every stored payload is re-embedded into a new versioned collection
+/// (`code` -> `code_v2` -> `code_v3`) while searches keep using the old one
+/// through the alias. Documents indexed in the meantime are written straight
+/// to the new collection and not overwritten by the copy. Once every point is
+/// copied the alias is switched over and the old collection dropped.
+pub struct Migration {
+    pub alias: String,
+    pub source: String,
+    pub target: String,
+    spec: CollectionSpec,
+    store: Arc<dyn VectorStore>,
+    embedder: Arc<dyn Embedder>,
+    write_targets: WriteTargets,
+}
+
+impl Migration {
+    /// Prepare the migration of `alias`, currently served by `source`. Writes
+    /// to the alias are redirected to the new collection from here on.
+    pub fn new(
+        alias: &str,
+        source: &str,
+        spec: CollectionSpec,
+        store: Arc<dyn VectorStore>,
+        embedder: Arc<dyn Embedder>,
+        write_targets: WriteTargets,
+    ) -> Result<Self, MigrationError> {
+        let target = next_version(alias, source);
+        write_targets.claim(alias, &target)?;
+        Ok(Self {
+            alias: alias.to_string(),
+            source: source.to_string(),
+            target,
+            spec: spec.with_embedding_model(Some(embedder.model_id().to_string())),
+            store,
+            embedder,
+            write_targets,
+        })
+    }
+
+    /// Copy, swap and clean up. On failure the alias still serves the old
+    /// collection and a later run continues into the same new collection.
+    pub async fn run(self) -> Result<MigrationReport, MigrationError> {
+        let result = self.migrate().await;
+        self.write_targets.release(&self.alias);
+        result
+    }
+
+    async fn migrate(&self) -> Result<MigrationReport, MigrationError> {
+        self.store.ensure_collection(&self.target, &self.spec).await?;
+
+        let mut report = MigrationReport {
+            alias: self.alias.clone(),
+            collection: self.target.clone(),
+            migrated: 0,
+            skipped: 0,
+        };
+        let mut offset = None;
+        loop {
+            let page = self.store.scroll(&self.source, offset, MIGRATION_BATCH_SIZE).await?;
+            let ids: Vec<String> = page.points.iter().map(|(id, _)| id.clone()).collect();
+            let rewritten = self.store.existing(&self.target, &ids).await?;
+
+            let mut texts = Vec::new();
+            let mut payloads = Vec::new();
+            for (id, payload) in page.points {
+                if rewritten.contains(&id) {
+                    continue;
+                }
+                if let Some(text) = embedding_text(&payload) {
+                    texts.push(text);
+                    payloads.push((id, payload));
+                }
+            }
+            report.skipped += ids.len() - texts.len();
+
+            if !texts.is_empty() {
+                let vectors = self.embedder.embed(&texts).await?;
+                let points = payloads
+                    .into_iter()
+                    .zip(vectors)
+                    .zip(&texts)
+                    .map(|(((id, payload), dense), text)| {
+                        let (indices, values) = hybrid::document_vector(text);
+                        Point {
+                            id,
+                            dense,
+                            sparse: SparseVector { indices, values },
+                            payload,
+                        }
+                    })
+                    .collect();
+                self.store.upsert(&self.target, points).await?;
+                report.migrated += texts.len();
+                tracing::debug!("Migrated {} points from {} to {}", report.migrated, self.source, self.target);
+            }
+
+            offset = page.next_offset;
+            if offset.is_none() {
+                break;
+            }
+        }
+
+        if self.source == self.alias {
+            // A collection created before aliases were used has to go before
+            // its name can become an alias, leaving a brief gap for searches.
+            // If the alias is never set, `resume_swap` sets it on the next start.
+            self.store.delete_collection(&self.source).await?;
+            self.store.set_alias(&self.alias, &self.target).await?;
+        } else {
+            self.store.set_alias(&self.alias, &self.target).await?;
+            self.store.delete_collection(&self.source).await?;
+        }
+
+        Ok(report)
+    }
+}
+
+/// Finish the swap of a first migration that stopped between dropping the
+/// original collection and creating the alias. Points the alias at the newest
+/// `<alias>_vN` collection when neither an alias nor a collection named
+/// `alias` exists, and returns that collection.
+pub async fn resume_swap(store: &dyn VectorStore, alias: &str) -> Result<Option<String>, StoreError> {
+    if store.alias_target(alias).await?.is_some() {
+        return Ok(None);
+    }
+    let collections = store.collections().await?;
+    if collections.iter().any(|name| name == alias) {
+        return Ok(None);
+    }
+
+    let newest = collections
+        .into_iter()
+        .filter_map(|name| {
+            let version = name.strip_prefix(alias)?.strip_prefix("_v")?.parse::<u32>().ok()?;
+            Some((version, name))
+        })
+        .max();
+    match newest {
+        Some((_, target)) => {
+            store.set_alias(alias, &target).await?;
+            Ok(Some(target))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Name of the collection succeeding `source` behind `alias`
+pub fn next_version(alias: &str, source: &str) -> String {
+    let version = source
+        .strip_prefix(alias)
+        .and_then(|suffix| suffix.strip_prefix("_v"))
+        .and_then(|version| version.parse::<u32>().ok())
+        .unwrap_or(1);
+    format!("{}_v{}", alias, version + 1)
+}
+
+/// Text the stored chunk was embedded from: its content, prefixed with the
+/// heading breadcrumb like `Chunk::embedding_text`
+fn embedding_text(payload: &HashMap<String, Value>) -> Option<String> {
+    let content = payload.get("content")?.as_str()?;
+    Some(match payload.get("breadcrumb").and_then(Value::as_str) {
+        Some(breadcrumb) => format!("{}\n\n{}", breadcrumb, content),
+        None => content.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_version() {
+        assert_eq!(next_version("code", "code"), "code_v2");
+        assert_eq!(next_version("code", "code_v2"), "code_v3");
+        assert_eq!(next_version("code", "imported"), "code_v2");
+    }
+
+    #[test]
+    fn test_embedding_text_and_write_targets() {
+        let payload = HashMap::from([
+            ("content".to_string(), serde_json::json!("Run the installer.")),
+            ("breadcrumb".to_string(), serde_json::json!("Guide > Install")),
+        ]);
+        assert_eq!(embedding_text(&payload).unwrap(), "Guide > Install\n\nRun the installer.");
+        assert!(embedding_text(&HashMap::new()).is_none());
+
+        let targets = WriteTargets::default();
+        targets.claim("code", "code_v2").unwrap();
+        assert_eq!(targets.resolve("code"), "code_v2");
+        assert!(matches!(targets.claim("code", "code_v3"), Err(MigrationError::InProgress(_))));
+        targets.release("code");
+        assert_eq!(targets.resolve("code"), "code");
+    }
+}
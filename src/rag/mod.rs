@@ -1,4 +1,22 @@
 // src/rag/mod.rs
 pub mod client;
+pub mod chunking;
+pub mod classify;
+pub mod embedding;
+pub mod filter;
+pub mod git;
+pub mod hybrid;
+pub mod jobs;
+pub mod manifest;
+pub mod migration;
+pub mod registry;
+pub mod repo_config;
+pub mod rerank;
+pub mod store;
+pub mod walker;
+pub mod watcher;
 
-pub use client::*;
\ No newline at end of file
+#[cfg(test)]
+pub(crate) mod test_support;
+
+pub use client::*;
@@ -0,0 +1,388 @@
+// src/rag/registry.rs
+use crate::rag::classify::FileClass;
+use crate::rag::git;
+use crate::rag::manifest::{self, IndexReport};
+use crate::rag::walker::WalkSummary;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    #[error("Invalid repository id {0:?}: use letters, digits, '-', '_' and '.'")]
+    InvalidId(String),
+    #[error("{0} is not a directory")]
+    NotADirectory(String),
+    #[error("Repository {0} is already registered at {1}")]
+    Conflict(String, String),
+    #[error("{0} is already registered as repository {1}")]
+    RootConflict(String, String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Corrupt repository registry: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// How a repository is indexed unless a call says otherwise
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepoSettings {
+    #[serde(default = "all_classes")]
+    pub classes: Vec<FileClass>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub watch: bool,
+}
+
+impl Default for RepoSettings {
+    fn default() -> Self {
+        Self {
+            classes: all_classes(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            watch: false,
+        }
+    }
+}
+
+fn all_classes() -> Vec<FileClass> {
+    vec![FileClass::Documentation, FileClass::Code, FileClass::Config]
+}
+
+/// A registered repository and the outcome of its last indexing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepoRecord {
+    pub id: String,
+    /// Checkout directory the repository's file paths are relative to
+    pub root: PathBuf,
+    /// URL of the `origin` remote, when the checkout is a git repository
+    #[serde(default)]
+    pub remote: Option<String>,
+    #[serde(default)]
+    pub settings: RepoSettings,
+    pub registered_at: DateTime<Utc>,
+    #[serde(default)]
+    pub last_indexed_at: Option<DateTime<Utc>>,
+    /// Commit last indexed, when it was indexed from a git revision
+    #[serde(default)]
+    pub last_revision: Option<String>,
+    #[serde(default)]
+    pub last_report: Option<IndexReport>,
+    #[serde(default)]
+    pub last_summary: Option<WalkSummary>,
+}
+
+/// Repositories known to the server, keyed by id, kept in memory and
+/// optionally saved to a file.
+///
+/// Ids are stable: registering a checkout again returns its existing record,
+/// so repeated indexing updates one index rather than creating another.
+#[derive(Default)]
+pub struct RepoRegistry {
+    file: Option<PathBuf>,
+    repos: Mutex<BTreeMap<String, RepoRecord>>,
+    // Saves write the whole file, so they take turns
+    saving: tokio::sync::Mutex<()>,
+}
+
+impl RepoRegistry {
+    /// A registry that lasts as long as the process
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// A registry persisted to `file`, loading the repositories recorded there
+    pub async fn open(file: impl AsRef<Path>) -> Result<Self, RegistryError> {
+        let file = file.as_ref().to_path_buf();
+        let saved: Vec<RepoRecord> = if file.exists() {
+            serde_json::from_slice(&tokio::fs::read(&file).await?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            file: Some(file),
+            repos: Mutex::new(saved.into_iter().map(|repo| (repo.id.clone(), repo)).collect()),
+            saving: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<RepoRecord> {
+        self.lock_repos().get(id).cloned()
+    }
+
+    /// Every registered repository, by id
+    pub fn repos(&self) -> Vec<RepoRecord> {
+        self.lock_repos().values().cloned().collect()
+    }
+
+    /// The repository registered at a checkout directory
+    pub fn find_by_root(&self, root: &Path) -> Option<RepoRecord> {
+        let root = root.canonicalize().ok()?;
+        self.lock_repos().values().find(|repo| repo.root == root).cloned()
+    }
+
+    /// Register the checkout at `root`, or update its registration. Without an
+    /// id, a checkout registered before keeps its id and a new one is named
+    /// after its `origin` remote or directory. Settings are kept when `None`.
+    pub async fn register(
+        &self,
+        id: Option<&str>,
+        root: &Path,
+        settings: Option<RepoSettings>,
+    ) -> Result<RepoRecord, RegistryError> {
+        if !root.is_dir() {
+            return Err(RegistryError::NotADirectory(root.display().to_string()));
+        }
+        let root = root.canonicalize()?;
+        let remote = git::origin_url(&root);
+        if let Some(id) = id.filter(|id| !manifest::is_plain_id(id)) {
+            return Err(RegistryError::InvalidId(id.to_string()));
+        }
+
+        let record = {
+            let mut repos = self.lock_repos();
+            let id = match id {
+                Some(id) => id.to_string(),
+                None => match repos.values().find(|repo| repo.root == root) {
+                    Some(repo) => repo.id.clone(),
+                    None => unique_id(&repos, &derive_id(&root, remote.as_deref())),
+                },
+            };
+            match repos.get_mut(&id) {
+                Some(repo) if repo.root != root => {
+                    return Err(RegistryError::Conflict(id, repo.root.display().to_string()));
+                }
+                Some(repo) => {
+                    repo.remote = remote;
+                    if let Some(settings) = settings {
+                        repo.settings = settings;
+                    }
+                    repo.clone()
+                }
+                None => {
+                    check_unique_root(&repos, &id, &root)?;
+                    let repo = new_record(id, root, remote, settings.unwrap_or_default());
+                    repos.insert(repo.id.clone(), repo.clone());
+                    repo
+                }
+            }
+        };
+        self.save().await?;
+        Ok(record)
+    }
+
+    /// Fail with [`RegistryError::Conflict`] if `id` is registered at a
+    /// directory other than `root`, or with [`RegistryError::RootConflict`]
+    /// if `root` is registered under another id
+    pub fn check_root(&self, id: &str, root: &Path) -> Result<(), RegistryError> {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let repos = self.lock_repos();
+        match repos.get(id) {
+            Some(repo) if repo.root != root => Err(RegistryError::Conflict(id.to_string(), repo.root.display().to_string())),
+            _ => check_unique_root(&repos, id, &root),
+        }
+    }
+
+    /// Record the outcome of indexing a repository from `root`, registering
+    /// it under `id` if it is not registered yet. Fails like `register` if
+    /// `id` is registered at another directory.
+    pub async fn record_index(
+        &self,
+        id: &str,
+        root: &Path,
+        report: IndexReport,
+        summary: WalkSummary,
+        revision: Option<String>,
+    ) -> Result<(), RegistryError> {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let remote = git::origin_url(&root);
+        {
+            let mut repos = self.lock_repos();
+            if !repos.contains_key(id) {
+                check_unique_root(&repos, id, &root)?;
+            }
+            let repo = repos
+                .entry(id.to_string())
+                .or_insert_with(|| new_record(id.to_string(), root.clone(), None, RepoSettings::default()));
+            if repo.root != root {
+                return Err(RegistryError::Conflict(id.to_string(), repo.root.display().to_string()));
+            }
+            repo.remote = remote;
+            repo.last_indexed_at = Some(Utc::now());
+            repo.last_revision = revision;
+            repo.last_report = Some(report);
+            repo.last_summary = Some(summary);
+        }
+        self.save().await
+    }
+
+    /// Forget a repository, returning its record if it was registered
+    pub async fn remove(&self, id: &str) -> Result<Option<RepoRecord>, RegistryError> {
+        let removed = self.lock_repos().remove(id);
+        if removed.is_some() {
+            self.save().await?;
+        }
+        Ok(removed)
+    }
+
+    async fn save(&self) -> Result<(), RegistryError> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let _saving = self.saving.lock().await;
+        let contents = serde_json::to_vec_pretty(&self.repos())?;
+        if let Some(directory) = file.parent() {
+            tokio::fs::create_dir_all(directory).await?;
+        }
+        let temporary = file.with_extension("json.tmp");
+        tokio::fs::write(&temporary, contents).await?;
+        tokio::fs::rename(&temporary, file).await?;
+        Ok(())
+    }
+
+    fn lock_repos(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, RepoRecord>> {
+        self.repos.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn new_record(id: String, root: PathBuf, remote: Option<String>, settings: RepoSettings) -> RepoRecord {
+    RepoRecord {
+        id,
+        root,
+        remote,
+        settings,
+        registered_at: Utc::now(),
+        last_indexed_at: None,
+        last_revision: None,
+        last_report: None,
+        last_summary: None,
+    }
+}
+
+/// Fail with [`RegistryError::RootConflict`] if `root` is registered under an id other than `id`,
+/// which would index the same checkout twice
+fn check_unique_root(repos: &BTreeMap<String, RepoRecord>, id: &str, root: &Path) -> Result<(), RegistryError> {
+    match repos.values().find(|repo| repo.root == root && repo.id != id) {
+        Some(repo) => Err(RegistryError::RootConflict(root.display().to_string(), repo.id.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Id for a repository named after its remote, e.g. `backend` for
+/// `git@github.com:acme/backend.git`, or else its directory
+pub fn derive_id(root: &Path, remote: Option<&str>) -> String {
+    let name = remote
+        .and_then(|remote| {
+            let remote = remote.trim_end_matches('/');
+            let remote = remote.strip_suffix(".git").unwrap_or(remote);
+            remote.rsplit(['/', ':']).next()
+        })
+        .filter(|name| !name.is_empty())
+        .or_else(|| root.file_name().and_then(|name| name.to_str()))
+        .unwrap_or_default();
+
+    let mut id = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || matches!(c, '_' | '.') {
+            id.push(c.to_ascii_lowercase());
+        } else if !id.ends_with('-') {
+            id.push('-');
+        }
+    }
+    let id = id.trim_matches(|c| c == '-' || c == '.');
+    if id.is_empty() {
+        "repo".to_string()
+    } else {
+        id.to_string()
+    }
+}
+
+/// `id`, or `id-2`, `id-3` and so on if it is taken
+fn unique_id(repos: &BTreeMap<String, RepoRecord>, id: &str) -> String {
+    if !repos.contains_key(id) {
+        return id.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}-{}", id, n))
+        .find(|candidate| !repos.contains_key(candidate))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_id() {
+        let root = Path::new("/work/My Service");
+        assert_eq!(derive_id(root, Some("git@github.com:acme/backend.git")), "backend");
+        assert_eq!(derive_id(root, Some("https://github.com/acme/Web-App/")), "web-app");
+        assert_eq!(derive_id(root, None), "my-service");
+        assert_eq!(derive_id(Path::new("/"), None), "repo");
+    }
+
+    #[tokio::test]
+    async fn test_register_reuses_ids_and_persists() {
+        let directory = std::env::temp_dir().join(format!("cerebus-registry-{}", uuid::Uuid::new_v4()));
+        let first = directory.join("one/backend");
+        let second = directory.join("two/backend");
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+        let file = directory.join("repos.json");
+
+        {
+            let registry = RepoRegistry::open(&file).await.unwrap();
+            let repo = registry.register(None, &first, None).await.unwrap();
+            assert_eq!(repo.id, "backend");
+            assert_eq!(registry.register(None, &first.join("."), None).await.unwrap().id, "backend");
+            assert_eq!(registry.register(None, &second, None).await.unwrap().id, "backend-2");
+
+            assert!(matches!(
+                registry.register(Some("backend"), &second, None).await,
+                Err(RegistryError::Conflict(_, _))
+            ));
+            // Another id for a registered checkout would index it twice
+            assert!(matches!(
+                registry.register(Some("api"), &first, None).await,
+                Err(RegistryError::RootConflict(_, _))
+            ));
+            assert!(matches!(registry.check_root("api", &first), Err(RegistryError::RootConflict(_, _))));
+            assert!(matches!(
+                registry.register(Some("../api"), &second, None).await,
+                Err(RegistryError::InvalidId(_))
+            ));
+
+            let settings = RepoSettings {
+                include: vec!["src/**".to_string()],
+                ..Default::default()
+            };
+            registry.register(Some("backend"), &first, Some(settings)).await.unwrap();
+            registry
+                .record_index("backend", &first, IndexReport::default(), WalkSummary::default(), Some("abc".to_string()))
+                .await
+                .unwrap();
+            assert!(matches!(registry.check_root("backend", &second), Err(RegistryError::Conflict(_, _))));
+            assert!(matches!(
+                registry
+                    .record_index("backend", &second, IndexReport::default(), WalkSummary::default(), None)
+                    .await,
+                Err(RegistryError::Conflict(_, _))
+            ));
+        }
+
+        let registry = RepoRegistry::open(&file).await.unwrap();
+        let ids: Vec<String> = registry.repos().into_iter().map(|repo| repo.id).collect();
+        assert_eq!(ids, vec!["backend", "backend-2"]);
+        let repo = registry.find_by_root(&first).unwrap();
+        assert_eq!(repo.settings.include, vec!["src/**"]);
+        assert_eq!(repo.last_revision.as_deref(), Some("abc"));
+        assert!(registry.remove("backend").await.unwrap().is_some());
+        assert!(registry.get("backend").is_none());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,330 @@
+// src/rag/repo_config.rs
+use crate::rag::chunking::Chunker;
+use crate::rag::classify::{ClassifyError, Classifier, FileClass};
+use crate::rag::manifest;
+use crate::rag::walker::{self, FileSelector, ReadError, WalkError};
+use crate::rag::watcher::IgnoreRules;
+use crate::utils::config::{ChunkingConfig, IndexingConfig, RouteConfig};
+use config::{Config, ConfigError, File, FileFormat};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Name of the optional configuration file at a repository's root
+pub const REPO_CONFIG_FILE: &str = ".cerebus.toml";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RepoConfigError {
+    #[error("Cannot read {0}: {1}")]
+    Read(String, ConfigError),
+    #[error("Invalid glob {0}: {1}")]
+    Glob(String, globset::Error),
+    #[error("Invalid docs directory {0}: use a path relative to the repository root")]
+    DocsDirectory(String),
+    #[error("Invalid chunking: {0}")]
+    Chunking(String),
+    #[error("max_file_size must be greater than 0")]
+    MaxFileSize,
+    #[error(transparent)]
+    Classify(#[from] ClassifyError),
+}
+
+/// Settings a repository's `.cerebus.toml` may set; anything left out
+/// falls back to the server's
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepoConfig {
+    /// Only index files matching one of these globs
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Skip files matching any of these globs, on top of the server's
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Directories whose files are all documentation, e.g. examples in `docs/`
+    #[serde(default)]
+    pub docs: Vec<String>,
+    /// Routes tried before the server's
+    #[serde(default)]
+    pub routes: Vec<RouteConfig>,
+    #[serde(default)]
+    pub chunking: RepoChunking,
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepoChunking {
+    pub max_tokens: Option<usize>,
+    pub overlap_tokens: Option<usize>,
+}
+
+/// How a repository is indexed: its `.cerebus.toml`, if any, merged over
+/// the server's `[indexing]` and `[chunking]` settings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectiveConfig {
+    /// The `.cerebus.toml` read, or `None` if only server settings apply
+    pub source: Option<PathBuf>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub docs: Vec<String>,
+    /// In the order they are tried: the repository's, its docs directories, then the server's
+    pub routes: Vec<RouteConfig>,
+    pub chunking: ChunkingConfig,
+    pub max_file_size: u64,
+}
+
+impl EffectiveConfig {
+    /// The server's settings alone
+    pub fn server(indexing: &IndexingConfig, chunking: &ChunkingConfig) -> Self {
+        Self {
+            source: None,
+            include: Vec::new(),
+            exclude: indexing.exclude.clone(),
+            docs: Vec::new(),
+            routes: indexing.routes.clone(),
+            chunking: chunking.clone(),
+            max_file_size: indexing.max_file_size,
+        }
+    }
+
+    /// Hash of the settings that decide how an indexed file is chunked and
+    /// routed; files indexed under another fingerprint must be indexed again
+    pub fn fingerprint(&self) -> String {
+        let settings = serde_json::to_string(&(&self.routes, &self.chunking)).unwrap_or_default();
+        manifest::content_hash(&settings)
+    }
+
+    /// Read the `.cerebus.toml` at `root`, if there is one, and merge it over
+    /// the server's settings
+    pub fn load(root: &Path, indexing: &IndexingConfig, chunking: &ChunkingConfig) -> Result<Self, RepoConfigError> {
+        let file = root.join(REPO_CONFIG_FILE);
+        if !file.is_file() {
+            return Ok(Self::server(indexing, chunking));
+        }
+        let read = |e| RepoConfigError::Read(file.display().to_string(), e);
+        let config: RepoConfig = Config::builder()
+            .add_source(File::from(file.as_path()).format(FileFormat::Toml))
+            .build()
+            .and_then(Config::try_deserialize)
+            .map_err(read)?;
+        Self::merge(config, Some(file.clone()), indexing, chunking)
+    }
+
+    /// Merge repository settings over the server's and validate the result
+    pub fn merge(
+        config: RepoConfig,
+        source: Option<PathBuf>,
+        indexing: &IndexingConfig,
+        chunking: &ChunkingConfig,
+    ) -> Result<Self, RepoConfigError> {
+        let docs: Vec<String> = config
+            .docs
+            .iter()
+            .map(|directory| directory.trim_start_matches("./").trim_end_matches('/').to_string())
+            .collect();
+        let docs_routes = docs.iter().map(|directory| RouteConfig {
+            glob: format!("{}/**", directory),
+            class: Some(FileClass::Documentation),
+            language: None,
+        });
+
+        let merged = Self {
+            source,
+            include: config.include,
+            exclude: indexing.exclude.iter().chain(&config.exclude).cloned().collect(),
+            routes: config.routes.into_iter().chain(docs_routes).chain(indexing.routes.clone()).collect(),
+            docs,
+            chunking: ChunkingConfig {
+                max_tokens: config.chunking.max_tokens.unwrap_or(chunking.max_tokens),
+                overlap_tokens: config.chunking.overlap_tokens.unwrap_or(chunking.overlap_tokens),
+            },
+            max_file_size: config.max_file_size.unwrap_or(indexing.max_file_size),
+        };
+        merged.validate()?;
+        Ok(merged)
+    }
+
+    fn validate(&self) -> Result<(), RepoConfigError> {
+        for pattern in self.include.iter().chain(&self.exclude) {
+            walker::glob(pattern).map_err(|e| RepoConfigError::Glob(pattern.clone(), e))?;
+        }
+        for directory in &self.docs {
+            let path = Path::new(directory);
+            if directory.is_empty() || !path.components().all(|part| matches!(part, Component::Normal(_))) {
+                return Err(RepoConfigError::DocsDirectory(directory.clone()));
+            }
+        }
+        if self.chunking.max_tokens == 0 {
+            return Err(RepoConfigError::Chunking("max_tokens must be greater than 0".to_string()));
+        }
+        if self.chunking.overlap_tokens >= self.chunking.max_tokens {
+            return Err(RepoConfigError::Chunking("overlap_tokens must be less than max_tokens".to_string()));
+        }
+        if self.max_file_size == 0 {
+            return Err(RepoConfigError::MaxFileSize);
+        }
+        Ok(())
+    }
+}
+
+/// Whether and how a repository file is indexed
+#[derive(Debug, Clone, Serialize)]
+pub struct FileExplanation {
+    pub path: String,
+    /// Whether the file is in the index now
+    pub indexed: bool,
+    /// Whether indexing the repository now would index the file
+    pub selected: bool,
+    /// Why it would not be, when it would not
+    pub reason: Option<String>,
+    pub class: FileClass,
+    pub collection: &'static str,
+    pub language: Option<String>,
+}
+
+/// An effective configuration with the classifier and chunker built from it
+pub struct RepoProfile {
+    pub config: EffectiveConfig,
+    pub classifier: Classifier,
+    pub chunker: Chunker,
+}
+
+impl RepoProfile {
+    pub fn new(config: EffectiveConfig) -> Result<Self, ClassifyError> {
+        Ok(Self {
+            classifier: Classifier::new(&config.routes)?,
+            chunker: Chunker::new(config.chunking.clone()),
+            config,
+        })
+    }
+
+    /// Selector for files of the given classes. Include globs given here
+    /// replace the configured ones; exclude globs add to them.
+    pub fn selector(&self, include: &[String], exclude: &[String], classes: Vec<FileClass>) -> Result<FileSelector, WalkError> {
+        let include = if include.is_empty() { &self.config.include } else { include };
+        let exclude: Vec<String> = exclude.iter().chain(&self.config.exclude).cloned().collect();
+        Ok(FileSelector::new(include, &exclude)?
+            .with_classes(classes)
+            .with_classifier(self.classifier.clone()))
+    }
+
+    /// Explain how indexing the repository at `root` with `selector` treats
+    /// a file, checking in the order indexing does
+    pub fn explain(&self, root: &Path, path: &str, selector: &FileSelector, indexed: bool) -> FileExplanation {
+        let path = walker::normalize_repo_path(Some(root), path);
+        let mut language = None;
+        let reason = if path.split('/').any(|part| part.starts_with('.')) {
+            Some("hidden files are not indexed".to_string())
+        } else if root
+            .canonicalize()
+            .is_ok_and(|root| IgnoreRules::new(&root).ignored(&root.join(&path), false))
+        {
+            Some("it is ignored by a .gitignore or .ignore file".to_string())
+        } else if let Some(rejection) = selector.rejection(&path) {
+            Some(rejection)
+        } else {
+            match walker::read_file(root, &path, self.config.max_file_size) {
+                Ok(file) => {
+                    language = self.classifier.language(&path, &file.content);
+                    None
+                }
+                Err(ReadError::TooLarge(max)) => Some(format!("it is larger than max_file_size ({} bytes)", max)),
+                Err(e) => Some(e.to_string()),
+            }
+        };
+        let language = language.or_else(|| self.classifier.language(&path, ""));
+        let class = self.classifier.file_class(&path, language.as_deref());
+
+        FileExplanation {
+            indexed,
+            selected: reason.is_none(),
+            reason,
+            class,
+            collection: class.collection(),
+            language,
+            path,
+        }
+    }
+}
+
+impl Default for RepoProfile {
+    fn default() -> Self {
+        let config = EffectiveConfig::server(&IndexingConfig::default(), &ChunkingConfig::default());
+        Self {
+            classifier: Classifier::default(),
+            chunker: Chunker::new(config.chunking.clone()),
+            config,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_merges_repo_config_over_server() {
+        let root = std::env::temp_dir().join(format!("cerebus-repo-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let indexing = IndexingConfig {
+            exclude: vec!["*.min.js".to_string()],
+            ..Default::default()
+        };
+        let chunking = ChunkingConfig::default();
+        assert_eq!(
+            EffectiveConfig::load(&root, &indexing, &chunking).unwrap(),
+            EffectiveConfig::server(&indexing, &chunking)
+        );
+
+        std::fs::write(
+            root.join(REPO_CONFIG_FILE),
+            r#"
+include = ["src/**", "docs/**"]
+exclude = ["*.snap"]
+docs = ["./docs/"]
+
+[chunking]
+max_tokens = 256
+
+[[routes]]
+glob = "*.tpl"
+language = "html"
+"#,
+        )
+        .unwrap();
+        let config = EffectiveConfig::load(&root, &indexing, &chunking).unwrap();
+        assert_eq!(config.source, Some(root.join(REPO_CONFIG_FILE)));
+        assert_eq!(config.exclude, vec!["*.min.js", "*.snap"]);
+        assert_eq!((config.chunking.max_tokens, config.chunking.overlap_tokens), (256, 64));
+        let globs: Vec<&str> = config.routes.iter().map(|route| route.glob.as_str()).collect();
+        assert_eq!(globs, vec!["*.tpl", "docs/**"]);
+
+        let profile = RepoProfile::new(config).unwrap();
+        assert_eq!(profile.classifier.class("docs/examples/main.rs"), FileClass::Documentation);
+        let selector = profile.selector(&[], &[], vec![FileClass::Code]).unwrap();
+        assert!(selector.selects("src/lib.rs"));
+        assert!(!selector.selects("src/lib.snap"));
+        assert!(!selector.selects("tests/lib.rs"));
+        assert!(profile.selector(&["tests/**".to_string()], &[], vec![FileClass::Code]).unwrap().selects("tests/lib.rs"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_repo_config_is_rejected() {
+        let root = std::env::temp_dir().join(format!("cerebus-repo-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let load = |toml: &str| {
+            std::fs::write(root.join(REPO_CONFIG_FILE), toml).unwrap();
+            EffectiveConfig::load(&root, &IndexingConfig::default(), &ChunkingConfig::default())
+        };
+
+        assert!(matches!(load("inclde = [\"src/**\"]"), Err(RepoConfigError::Read(_, _))));
+        assert!(matches!(load("exclude = [\"src/[\"]"), Err(RepoConfigError::Glob(_, _))));
+        assert!(matches!(load("docs = [\"../docs\"]"), Err(RepoConfigError::DocsDirectory(_))));
+        assert!(matches!(load("[chunking]\nmax_tokens = 32"), Err(RepoConfigError::Chunking(_))));
+        assert!(matches!(load("max_file_size = 0"), Err(RepoConfigError::MaxFileSize)));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
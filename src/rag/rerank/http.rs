@@ -0,0 +1,135 @@
+// src/rag/rerank/http.rs
+use super::{RerankError, Reranker};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankResult>,
+}
+
+#[derive(Deserialize)]
+struct RerankResult {
+    index: usize,
+    relevance_score: f32,
+}
+
+/// Reranker backed by a Cohere/Jina-compatible `POST /v1/rerank` endpoint,
+/// as served by e.g. text-embeddings-inference or Infinity
+pub struct HttpReranker {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    batch_size: usize,
+    auth: Option<(String, String)>,
+}
+
+impl HttpReranker {
+    pub fn new(endpoint: &str, model: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: format!("{}/v1/rerank", endpoint.trim_end_matches('/')),
+            model: model.to_string(),
+            batch_size: 32,
+            auth: None,
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Send `name: value` with every request, e.g. `Authorization: Bearer <key>`
+    pub fn with_auth_header(mut self, name: &str, value: &str) -> Self {
+        self.auth = Some((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self, RerankError> {
+        self.client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(self)
+    }
+
+    async fn score_batch(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, RerankError> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "query": query,
+            "documents": documents,
+            "top_n": documents.len(),
+            "return_documents": false,
+        });
+
+        let mut request = self.client.post(&self.url).json(&body);
+        if let Some((name, value)) = &self.auth {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(RerankError::Api(format!("{} returned {}: {}", self.url, status, message)));
+        }
+
+        let body = response.bytes().await?;
+        parse_response(&body, documents.len())
+    }
+}
+
+#[async_trait]
+impl Reranker for HttpReranker {
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, RerankError> {
+        let mut scores = Vec::with_capacity(documents.len());
+        for batch in documents.chunks(self.batch_size) {
+            scores.extend(self.score_batch(query, batch).await?);
+        }
+        Ok(scores)
+    }
+}
+
+/// Extract scores in input order; the service returns results sorted by relevance
+fn parse_response(body: &[u8], count: usize) -> Result<Vec<f32>, RerankError> {
+    let response: RerankResponse = serde_json::from_slice(body)
+        .map_err(|e| RerankError::Api(format!("invalid rerank response: {}", e)))?;
+
+    let mut scores = vec![None; count];
+    for result in response.results {
+        match scores.get_mut(result.index) {
+            Some(score) => *score = Some(result.relevance_score),
+            None => return Err(RerankError::Api(format!("rerank result index {} out of range", result.index))),
+        }
+    }
+    scores
+        .into_iter()
+        .enumerate()
+        .map(|(index, score)| score.ok_or_else(|| RerankError::Api(format!("no rerank score for document {}", index))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response_in_input_order() {
+        let body = br#"{"model":"rerank-v3.5","results":[
+            {"index":1,"relevance_score":0.92},
+            {"index":0,"relevance_score":0.15}
+        ]}"#;
+
+        let scores = parse_response(body, 2).unwrap();
+        assert_eq!(scores, vec![0.15, 0.92]);
+    }
+
+    #[test]
+    fn test_parse_response_missing_document() {
+        let body = br#"{"results":[{"index":0,"relevance_score":0.5}]}"#;
+        assert!(parse_response(body, 2).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+// src/rag/rerank/local.rs
+use super::{RerankError, Reranker};
+use crate::rag::embedding::local::{model_id, BertFiles};
+use async_trait::async_trait;
+use candle_core::{Device, IndexOp, Tensor};
+use candle_nn::{Linear, Module};
+use candle_transformers::models::bert::BertModel;
+use std::path::Path;
+use std::sync::Arc;
+use tokenizers::Tokenizer;
+
+/// BERT cross-encoder with a single-logit classification head
+struct CrossEncoder {
+    bert: BertModel,
+    /// Dense + tanh over the [CLS] token; absent in some exported checkpoints
+    pooler: Option<Linear>,
+    classifier: Linear,
+}
+
+/// CPU reranker for cross-encoders such as ms-marco-MiniLM-L-6-v2.
+///
+/// Reads the same model directory layout as the local embedder. The query and
+/// each document are encoded together as one pair, and the classifier logit
+/// for the pair is its score.
+pub struct LocalReranker {
+    model_id: String,
+    model: Arc<CrossEncoder>,
+    tokenizer: Arc<Tokenizer>,
+    device: Device,
+    batch_size: usize,
+}
+
+impl LocalReranker {
+    pub fn load(model_dir: impl AsRef<Path>, max_tokens: usize, batch_size: usize) -> Result<Self, RerankError> {
+        let model_dir = model_dir.as_ref();
+        let device = Device::Cpu;
+        let files = BertFiles::load(model_dir, max_tokens, &device).map_err(|e| RerankError::Model(e.to_string()))?;
+
+        let hidden_size = files.config.hidden_size;
+        let weights = files.weights;
+        let model = (|| {
+            let bert = BertModel::load(weights.clone(), &files.config)?;
+            let pooler = if weights.contains_tensor("bert.pooler.dense.weight") {
+                Some(candle_nn::linear(hidden_size, hidden_size, weights.pp("bert.pooler.dense"))?)
+            } else {
+                None
+            };
+            let classifier = candle_nn::linear(hidden_size, 1, weights.pp("classifier"))?;
+            Ok(CrossEncoder { bert, pooler, classifier })
+        })()
+        .map_err(model_error)?;
+
+        Ok(Self {
+            model_id: model_id(model_dir),
+            model: Arc::new(model),
+            tokenizer: Arc::new(files.tokenizer),
+            device,
+            batch_size: batch_size.max(1),
+        })
+    }
+
+    fn score_batch(
+        model: &CrossEncoder,
+        tokenizer: &Tokenizer,
+        device: &Device,
+        query: &str,
+        documents: &[String],
+    ) -> Result<Vec<f32>, RerankError> {
+        let pairs: Vec<(String, String)> = documents
+            .iter()
+            .map(|document| (query.to_string(), document.clone()))
+            .collect();
+        let encodings = tokenizer
+            .encode_batch(pairs, true)
+            .map_err(|e| RerankError::Model(format!("tokenizer: {}", e)))?;
+
+        let scores = (|| {
+            let stack = |rows: Vec<&[u32]>| -> candle_core::Result<Tensor> {
+                let rows = rows
+                    .into_iter()
+                    .map(|row| Tensor::new(row, device))
+                    .collect::<candle_core::Result<Vec<_>>>()?;
+                Tensor::stack(&rows, 0)
+            };
+            let input_ids = stack(encodings.iter().map(|encoding| encoding.get_ids()).collect())?;
+            let token_type_ids = stack(encodings.iter().map(|encoding| encoding.get_type_ids()).collect())?;
+            let attention_mask = stack(encodings.iter().map(|encoding| encoding.get_attention_mask()).collect())?;
+
+            let hidden = model.bert.forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+            let mut cls = hidden.i((.., 0))?;
+            if let Some(pooler) = &model.pooler {
+                cls = pooler.forward(&cls)?.tanh()?;
+            }
+            model.classifier.forward(&cls)?.squeeze(1)?.to_vec1::<f32>()
+        })();
+        scores.map_err(model_error)
+    }
+}
+
+#[async_trait]
+impl Reranker for LocalReranker {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, RerankError> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let model = self.model.clone();
+        let tokenizer = self.tokenizer.clone();
+        let device = self.device.clone();
+        let batch_size = self.batch_size;
+        let query = query.to_string();
+        let documents = documents.to_vec();
+
+        // Inference is CPU-bound; keep it off the async workers
+        tokio::task::spawn_blocking(move || {
+            let mut scores = Vec::with_capacity(documents.len());
+            for batch in documents.chunks(batch_size) {
+                scores.extend(Self::score_batch(&model, &tokenizer, &device, &query, batch)?);
+            }
+            Ok(scores)
+        })
+        .await
+        .map_err(|e| RerankError::Model(e.to_string()))?
+    }
+}
+
+fn model_error(e: candle_core::Error) -> RerankError {
+    RerankError::Model(e.to_string())
+}
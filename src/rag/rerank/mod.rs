@@ -0,0 +1,272 @@
+// src/rag/rerank/mod.rs
+pub mod http;
+#[cfg(feature = "local-embeddings")]
+pub mod local;
+
+#[cfg(feature = "local-embeddings")]
+pub use local::LocalReranker;
+pub use http::HttpReranker;
+
+use crate::mcp::types::SearchResult;
+use crate::utils::config::{CollectionConfig, RerankerConfig};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RerankError {
+    #[error("Reranker model error: {0}")]
+    Model(String),
+    #[error("Invalid reranker configuration: {0}")]
+    Config(String),
+    #[error("Rerank service error: {0}")]
+    Api(String),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+/// Scores how relevant each document is to a query, looking at both together
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// Identifier of the model producing the scores
+    fn model_id(&self) -> &str;
+
+    /// Score every document against the query, returning one score per input in order.
+    /// Higher is more relevant; scores are only comparable within one call.
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, RerankError>;
+}
+
+/// Second retrieval stage: re-scores the top candidates of a search with a
+/// reranker before the results are cut to the requested limit
+#[derive(Clone)]
+pub struct RerankStage {
+    reranker: Arc<dyn Reranker>,
+    top_n: usize,
+}
+
+impl RerankStage {
+    pub fn new(reranker: Arc<dyn Reranker>, top_n: usize) -> Self {
+        Self {
+            reranker,
+            top_n: top_n.max(1),
+        }
+    }
+
+    pub fn reranker(&self) -> &Arc<dyn Reranker> {
+        &self.reranker
+    }
+
+    /// Number of candidates to retrieve and re-score
+    pub fn top_n(&self) -> usize {
+        self.top_n
+    }
+
+    /// Re-score the first `top_n` results and order them by the reranker.
+    /// Results past `top_n` keep their retrieval order after the reranked ones.
+    pub async fn rerank(&self, query: &str, mut results: Vec<SearchResult>) -> Result<Vec<SearchResult>, RerankError> {
+        let rest = results.split_off(results.len().min(self.top_n));
+        if results.is_empty() {
+            return Ok(rest);
+        }
+
+        let documents: Vec<String> = results.iter().map(|result| result.content.clone()).collect();
+        let scores = self.reranker.score(query, &documents).await?;
+        if scores.len() != results.len() {
+            return Err(RerankError::Api(format!(
+                "expected {} scores, got {}",
+                results.len(),
+                scores.len()
+            )));
+        }
+
+        let mut reranked = apply_scores(results, scores);
+        reranked.extend(rest);
+        Ok(reranked)
+    }
+}
+
+/// The rerank stage of each collection: the `[reranker]` defaults unless the
+/// collection overrides them or turns reranking off
+#[derive(Clone)]
+pub struct RerankStages {
+    default: RerankStage,
+    collections: HashMap<String, Option<RerankStage>>,
+}
+
+impl RerankStages {
+    pub fn new(default: RerankStage) -> Self {
+        Self {
+            default,
+            collections: HashMap::new(),
+        }
+    }
+
+    /// Use this stage for the collection, or none to leave its results in retrieval order
+    pub fn with_collection(mut self, collection: &str, stage: Option<RerankStage>) -> Self {
+        self.collections.insert(collection.to_string(), stage);
+        self
+    }
+
+    pub fn default_stage(&self) -> &RerankStage {
+        &self.default
+    }
+
+    pub fn for_collection(&self, collection: &str) -> Option<&RerankStage> {
+        match self.collections.get(collection) {
+            Some(stage) => stage.as_ref(),
+            None => Some(&self.default),
+        }
+    }
+}
+
+/// Record each reranker score as `rerank_score` in metadata, leaving the
+/// retrieval score in `score`, and sort by the reranker score
+fn apply_scores(results: Vec<SearchResult>, scores: Vec<f32>) -> Vec<SearchResult> {
+    let mut scored: Vec<(f32, SearchResult)> = scores.into_iter().zip(results).collect();
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    scored
+        .into_iter()
+        .map(|(score, mut result)| {
+            result
+                .metadata
+                .get_or_insert_with(Default::default)
+                .insert("rerank_score".to_string(), serde_json::json!(score));
+            result
+        })
+        .collect()
+}
+
+/// Build the rerank stages selected by the `[reranker]` settings and the
+/// `rerank` overrides of each collection
+pub fn from_config(config: &RerankerConfig, collections: &[CollectionConfig]) -> Result<RerankStages, RerankError> {
+    let default = load(config)?;
+    let mut stages = RerankStages::new(RerankStage::new(default.clone(), config.top_n));
+
+    for collection in collections {
+        let overrides = &collection.rerank;
+        if !overrides.enabled {
+            stages = stages.with_collection(&collection.name, None);
+            continue;
+        }
+        if overrides.model.is_none() && overrides.model_path.is_none() && overrides.top_n.is_none() {
+            continue;
+        }
+
+        let reranker = if overrides.model.is_some() || overrides.model_path.is_some() {
+            load(&RerankerConfig {
+                model: overrides.model.clone().unwrap_or_else(|| config.model.clone()),
+                model_path: overrides.model_path.clone().unwrap_or_else(|| config.model_path.clone()),
+                ..config.clone()
+            })?
+        } else {
+            default.clone()
+        };
+        let top_n = overrides.top_n.unwrap_or(config.top_n);
+        stages = stages.with_collection(&collection.name, Some(RerankStage::new(reranker, top_n)));
+    }
+
+    Ok(stages)
+}
+
+/// Load the reranker model selected by the settings
+fn load(config: &RerankerConfig) -> Result<Arc<dyn Reranker>, RerankError> {
+    let reranker: Arc<dyn Reranker> = match config.provider.as_str() {
+        #[cfg(feature = "local-embeddings")]
+        "local" => Arc::new(LocalReranker::load(&config.model_path, config.max_tokens, config.batch_size)?),
+        #[cfg(not(feature = "local-embeddings"))]
+        "local" => {
+            return Err(RerankError::Config(
+                "built without the local-embeddings feature".to_string(),
+            ))
+        }
+        "http" => {
+            let endpoint = config
+                .endpoint
+                .as_deref()
+                .ok_or_else(|| RerankError::Config("the http reranker needs an endpoint".to_string()))?;
+            let mut reranker = HttpReranker::new(endpoint, &config.model)
+                .with_batch_size(config.batch_size)
+                .with_timeout(Duration::from_secs(config.timeout_seconds))?;
+            if let Some(value) = &config.auth_header {
+                reranker = reranker.with_auth_header(&config.auth_header_name, value);
+            }
+            Arc::new(reranker)
+        }
+        provider => return Err(RerankError::Config(format!("unknown reranker provider: {}", provider))),
+    };
+
+    Ok(reranker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct LengthReranker;
+
+    #[async_trait]
+    impl Reranker for LengthReranker {
+        fn model_id(&self) -> &str {
+            "length"
+        }
+
+        async fn score(&self, _query: &str, documents: &[String]) -> Result<Vec<f32>, RerankError> {
+            Ok(documents.iter().map(|document| document.len() as f32).collect())
+        }
+    }
+
+    fn result(id: &str, content: &str, score: f32) -> SearchResult {
+        SearchResult {
+            id: id.to_string(),
+            content: content.to_string(),
+            source: String::new(),
+            score,
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rerank_reorders_top_n_only() {
+        let stage = RerankStage::new(Arc::new(LengthReranker), 2);
+        let results = vec![result("a", "x", 0.9), result("b", "xxx", 0.8), result("c", "xxxxx", 0.7)];
+
+        let reranked = stage.rerank("query", results).await.unwrap();
+        let ids: Vec<&str> = reranked.iter().map(|result| result.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "c"]);
+
+        // Retrieval score stays in `score`, the reranker score goes to metadata
+        assert_eq!(reranked[0].score, 0.8);
+        assert_eq!(reranked[0].metadata.as_ref().unwrap()["rerank_score"], serde_json::json!(3.0f32));
+        assert!(reranked[2].metadata.is_none());
+    }
+
+    #[test]
+    fn test_collections_override_the_default_stage() {
+        let config = RerankerConfig {
+            provider: "http".to_string(),
+            model: "rerank-base".to_string(),
+            endpoint: Some("http://localhost:8080".to_string()),
+            top_n: 50,
+            ..RerankerConfig::default()
+        };
+        let mut documentation = CollectionConfig::new("documentation");
+        documentation.rerank.top_n = Some(20);
+        let mut code = CollectionConfig::new("code");
+        code.rerank.model = Some("rerank-code".to_string());
+        let mut config_files = CollectionConfig::new("config");
+        config_files.rerank.enabled = false;
+
+        let stages = from_config(&config, &[documentation, code, config_files]).unwrap();
+
+        let stage = stages.for_collection("documentation").unwrap();
+        assert_eq!((stage.top_n(), stage.reranker().model_id()), (20, "rerank-base"));
+        let stage = stages.for_collection("code").unwrap();
+        assert_eq!((stage.top_n(), stage.reranker().model_id()), (50, "rerank-code"));
+        assert!(stages.for_collection("config").is_none());
+
+        // Collections without a definition use the defaults
+        let stage = stages.for_collection("notes").unwrap();
+        assert_eq!((stage.top_n(), stage.reranker().model_id()), (50, "rerank-base"));
+    }
+}
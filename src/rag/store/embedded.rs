@@ -0,0 +1,514 @@
+// src/rag/store/embedded.rs
+use super::{CollectionSpec, Point, QueryVector, ScrollPage, SparseVector, StoreError, VectorStore, search_result};
+use crate::mcp::types::SearchResult;
+use crate::rag::filter::CompiledFilter;
+use crate::utils::config::DistanceMetric;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// File in the store directory mapping alias names to collections
+const ALIASES_FILE: &str = "aliases.json";
+
+/// Longest a write waits in memory before changed collections are saved
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// One collection as persisted in `<path>/<collection>.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Collection {
+    vector_size: u64,
+    #[serde(default)]
+    distance: DistanceMetric,
+    #[serde(default)]
+    embedding_model: Option<String>,
+    points: HashMap<String, Point>,
+}
+
+/// In-process vector store persisted as JSON files in a directory.
+///
+/// Queries scan every point, which is fast enough for a single developer's
+/// repositories and needs no external service. Dense vectors are compared with
+/// the collection's distance metric and sparse vectors by IDF-weighted dot
+/// product, matching how the Qdrant collections are configured. HNSW,
+/// on-disk and quantization settings do not apply and are accepted as given.
+///
+/// Writes mark their collection dirty instead of rewriting its file, and
+/// dirty collections are saved at most every [`FLUSH_INTERVAL`] or on
+/// [`VectorStore::flush`], so a large indexing run writes each file a
+/// handful of times rather than once per batch.
+pub struct EmbeddedStore {
+    path: PathBuf,
+    collections: RwLock<HashMap<String, Collection>>,
+    aliases: RwLock<HashMap<String, String>>,
+    dirty: Mutex<HashSet<String>>,
+    flushed_at: Mutex<Instant>,
+    // Flushes and collection deletes take turns, so a flush never recreates a deleted file
+    saving: tokio::sync::Mutex<()>,
+}
+
+impl EmbeddedStore {
+    /// Open the store in `path`, loading any collections saved there
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let path = path.as_ref().to_path_buf();
+        tokio::fs::create_dir_all(&path).await?;
+
+        let mut collections = HashMap::new();
+        let mut aliases = HashMap::new();
+        let mut entries = tokio::fs::read_dir(&path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let file = entry.path();
+            if file.file_name().is_some_and(|name| name == ALIASES_FILE) {
+                aliases = serde_json::from_slice(&tokio::fs::read(&file).await?)?;
+            } else if file.extension().is_some_and(|extension| extension == "json") {
+                if let Some(name) = file.file_stem().and_then(|name| name.to_str()) {
+                    let collection: Collection = serde_json::from_slice(&tokio::fs::read(&file).await?)?;
+                    collections.insert(name.to_string(), collection);
+                }
+            }
+        }
+
+        Ok(Self {
+            path,
+            collections: RwLock::new(collections),
+            aliases: RwLock::new(aliases),
+            dirty: Mutex::new(HashSet::new()),
+            flushed_at: Mutex::new(Instant::now()),
+            saving: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Collection behind a name that may be an alias
+    async fn resolve(&self, name: &str) -> String {
+        self.aliases.read().await.get(name).cloned().unwrap_or_else(|| name.to_string())
+    }
+
+    async fn save_aliases(&self, aliases: &HashMap<String, String>) -> Result<(), StoreError> {
+        let file = self.path.join(ALIASES_FILE);
+        let temporary = self.path.join(format!("{}.tmp", ALIASES_FILE));
+        tokio::fs::write(&temporary, serde_json::to_vec(aliases)?).await?;
+        tokio::fs::rename(&temporary, &file).await?;
+        Ok(())
+    }
+
+    /// Write a serialized collection to disk through a temporary file, so a
+    /// crash never leaves a truncated collection behind
+    async fn save(&self, name: &str, contents: Vec<u8>) -> Result<(), StoreError> {
+        let file = self.path.join(format!("{}.json", name));
+        let temporary = self.path.join(format!("{}.json.tmp", name));
+        tokio::fs::write(&temporary, contents).await?;
+        tokio::fs::rename(&temporary, &file).await?;
+        Ok(())
+    }
+
+    fn mark_dirty(&self, name: &str) {
+        self.dirty.lock().unwrap_or_else(|e| e.into_inner()).insert(name.to_string());
+    }
+
+    /// Flush if the last flush is older than [`FLUSH_INTERVAL`]
+    async fn flush_if_due(&self) -> Result<(), StoreError> {
+        let due = self.flushed_at.lock().unwrap_or_else(|e| e.into_inner()).elapsed() >= FLUSH_INTERVAL;
+        if due {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl VectorStore for EmbeddedStore {
+    fn name(&self) -> &str {
+        "embedded"
+    }
+
+    async fn ensure_collection(&self, collection: &str, spec: &CollectionSpec) -> Result<CollectionSpec, StoreError> {
+        let collection = &self.resolve(collection).await;
+        let mut collections = self.collections.write().await;
+        if !collections.contains_key(collection) {
+            let created = Collection {
+                vector_size: spec.vector_size,
+                distance: spec.distance,
+                embedding_model: spec.embedding_model.clone(),
+                points: HashMap::new(),
+            };
+            self.save(collection, serde_json::to_vec(&created)?).await?;
+            collections.insert(collection.to_string(), created);
+        }
+
+        let stored = &collections[collection];
+        Ok(CollectionSpec {
+            vector_size: stored.vector_size,
+            distance: stored.distance,
+            embedding_model: stored.embedding_model.clone(),
+            ..spec.clone()
+        })
+    }
+
+    async fn upsert(&self, collection: &str, points: Vec<Point>) -> Result<(), StoreError> {
+        let collection = &self.resolve(collection).await;
+        let mut collections = self.collections.write().await;
+        let stored = collections
+            .get_mut(collection)
+            .ok_or_else(|| StoreError::MissingCollection(collection.to_string()))?;
+
+        if let Some(point) = points.iter().find(|point| point.dense.len() as u64 != stored.vector_size) {
+            return Err(StoreError::Backend(format!(
+                "point {} has {} dimensions, but {} expects {}",
+                point.id,
+                point.dense.len(),
+                collection,
+                stored.vector_size
+            )));
+        }
+        for point in points {
+            stored.points.insert(point.id.clone(), point);
+        }
+        self.mark_dirty(collection);
+        drop(collections);
+
+        self.flush_if_due().await
+    }
+
+    async fn query(
+        &self,
+        collection: &str,
+        vector: QueryVector,
+        limit: u64,
+        score_threshold: Option<f32>,
+        filter: &CompiledFilter,
+    ) -> Result<Vec<SearchResult>, StoreError> {
+        let collection = &self.resolve(collection).await;
+        let collections = self.collections.read().await;
+        let stored = collections
+            .get(collection)
+            .ok_or_else(|| StoreError::MissingCollection(collection.to_string()))?;
+
+        let candidates = stored.points.values().filter(|point| filter.matches_payload(&point.payload));
+        let mut scored: Vec<(f32, &Point)> = match &vector {
+            QueryVector::Dense(query) => candidates
+                .map(|point| (similarity(stored.distance, query, &point.dense), point))
+                .collect(),
+            QueryVector::Sparse(query) => {
+                let weights = idf(query, stored.points.values());
+                candidates
+                    .map(|point| (sparse_score(query, &weights, &point.sparse), point))
+                    // Like Qdrant, sparse search only returns points sharing a term with the query
+                    .filter(|(score, _)| *score > 0.0)
+                    .collect()
+            }
+        };
+
+        if let Some(score_threshold) = score_threshold {
+            scored.retain(|(score, _)| *score >= score_threshold);
+        }
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        Ok(scored
+            .into_iter()
+            .take(limit as usize)
+            .map(|(score, point)| search_result(point.id.clone(), score, point.payload.clone()))
+            .collect())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<(), StoreError> {
+        let collection = &self.resolve(collection).await;
+        let mut collections = self.collections.write().await;
+        let stored = collections
+            .get_mut(collection)
+            .ok_or_else(|| StoreError::MissingCollection(collection.to_string()))?;
+
+        let count = stored.points.len();
+        for id in ids {
+            stored.points.remove(id);
+        }
+        if stored.points.len() == count {
+            return Ok(());
+        }
+        self.mark_dirty(collection);
+        drop(collections);
+
+        self.flush_if_due().await
+    }
+
+    async fn get(&self, collection: &str, id: &str) -> Result<Option<HashMap<String, Value>>, StoreError> {
+        let collection = &self.resolve(collection).await;
+        let collections = self.collections.read().await;
+        let stored = collections
+            .get(collection)
+            .ok_or_else(|| StoreError::MissingCollection(collection.to_string()))?;
+        Ok(stored.points.get(id).map(|point| point.payload.clone()))
+    }
+
+    async fn existing(&self, collection: &str, ids: &[String]) -> Result<HashSet<String>, StoreError> {
+        let collection = &self.resolve(collection).await;
+        let collections = self.collections.read().await;
+        let stored = collections
+            .get(collection)
+            .ok_or_else(|| StoreError::MissingCollection(collection.to_string()))?;
+        Ok(ids.iter().filter(|id| stored.points.contains_key(*id)).cloned().collect())
+    }
+
+    async fn scroll(&self, collection: &str, offset: Option<String>, limit: u32) -> Result<ScrollPage, StoreError> {
+        let collection = &self.resolve(collection).await;
+        let collections = self.collections.read().await;
+        let stored = collections
+            .get(collection)
+            .ok_or_else(|| StoreError::MissingCollection(collection.to_string()))?;
+
+        let mut ids: Vec<&String> = stored
+            .points
+            .keys()
+            .filter(|id| offset.as_ref().is_none_or(|offset| *id >= offset))
+            .collect();
+        ids.sort();
+        let next_offset = ids.get(limit as usize).map(|id| id.to_string());
+        let points = ids
+            .into_iter()
+            .take(limit as usize)
+            .map(|id| (id.clone(), stored.points[id].payload.clone()))
+            .collect();
+        Ok(ScrollPage { points, next_offset })
+    }
+
+    async fn collections(&self) -> Result<Vec<String>, StoreError> {
+        Ok(self.collections.read().await.keys().cloned().collect())
+    }
+
+    async fn alias_target(&self, alias: &str) -> Result<Option<String>, StoreError> {
+        Ok(self.aliases.read().await.get(alias).cloned())
+    }
+
+    async fn set_alias(&self, alias: &str, collection: &str) -> Result<(), StoreError> {
+        // The alias must not point at points that exist only in memory
+        self.flush().await?;
+        let collections = self.collections.read().await;
+        if !collections.contains_key(collection) {
+            return Err(StoreError::MissingCollection(collection.to_string()));
+        }
+        if collections.contains_key(alias) {
+            return Err(StoreError::Backend(format!("alias {} would hide the collection of that name", alias)));
+        }
+
+        let mut aliases = self.aliases.write().await;
+        aliases.insert(alias.to_string(), collection.to_string());
+        self.save_aliases(&aliases).await
+    }
+
+    async fn delete_collection(&self, collection: &str) -> Result<(), StoreError> {
+        let _saving = self.saving.lock().await;
+        let mut collections = self.collections.write().await;
+        if collections.remove(collection).is_none() {
+            return Err(StoreError::MissingCollection(collection.to_string()));
+        }
+        self.dirty.lock().unwrap_or_else(|e| e.into_inner()).remove(collection);
+        tokio::fs::remove_file(self.path.join(format!("{}.json", collection))).await?;
+
+        let mut aliases = self.aliases.write().await;
+        let count = aliases.len();
+        aliases.retain(|_, target| target != collection);
+        if aliases.len() != count {
+            self.save_aliases(&aliases).await?;
+        }
+        Ok(())
+    }
+
+    /// Save every dirty collection. Each is serialized under a read lock, so
+    /// searches carry on, and written to disk with no lock held.
+    async fn flush(&self) -> Result<(), StoreError> {
+        let _saving = self.saving.lock().await;
+        let dirty = std::mem::take(&mut *self.dirty.lock().unwrap_or_else(|e| e.into_inner()));
+        *self.flushed_at.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+
+        let mut names = dirty.into_iter();
+        while let Some(name) = names.next() {
+            let contents = match self.collections.read().await.get(&name) {
+                Some(collection) => serde_json::to_vec(collection),
+                None => continue,
+            };
+            let saved = match contents {
+                Ok(contents) => self.save(&name, contents).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = saved {
+                // Keep the collections that were not saved for the next flush
+                let mut pending = self.dirty.lock().unwrap_or_else(|e| e.into_inner());
+                pending.insert(name);
+                pending.extend(names);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Higher is closer for every metric; distances are negated so results
+/// always sort best first
+fn similarity(distance: DistanceMetric, a: &[f32], b: &[f32]) -> f32 {
+    let pairs = a.iter().zip(b);
+    match distance {
+        DistanceMetric::Cosine => {
+            let dot: f32 = pairs.map(|(a, b)| a * b).sum();
+            let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            let norms = norm(a) * norm(b);
+            if norms == 0.0 {
+                0.0
+            } else {
+                dot / norms
+            }
+        }
+        DistanceMetric::Dot => pairs.map(|(a, b)| a * b).sum(),
+        DistanceMetric::Euclid => -pairs.map(|(a, b)| (a - b) * (a - b)).sum::<f32>().sqrt(),
+        DistanceMetric::Manhattan => -pairs.map(|(a, b)| (a - b).abs()).sum::<f32>(),
+    }
+}
+
+/// Inverse document frequency of each query term over the collection,
+/// using the same formula as Qdrant's IDF modifier
+fn idf<'a>(query: &SparseVector, points: impl Iterator<Item = &'a Point>) -> HashMap<u32, f32> {
+    let mut document_counts: HashMap<u32, f32> = query.indices.iter().map(|index| (*index, 0.0)).collect();
+    let mut total = 0.0;
+    for point in points {
+        total += 1.0;
+        for index in &point.sparse.indices {
+            if let Some(count) = document_counts.get_mut(index) {
+                *count += 1.0;
+            }
+        }
+    }
+
+    document_counts
+        .into_iter()
+        .map(|(index, count)| (index, ((total - count + 0.5) / (count + 0.5) + 1.0f32).ln()))
+        .collect()
+}
+
+fn sparse_score(query: &SparseVector, weights: &HashMap<u32, f32>, document: &SparseVector) -> f32 {
+    let document: HashMap<u32, f32> = document.indices.iter().copied().zip(document.values.iter().copied()).collect();
+    query
+        .indices
+        .iter()
+        .zip(&query.values)
+        .filter_map(|(index, value)| Some(value * document.get(index)? * weights.get(index)?))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::types::SearchFilter;
+    use crate::rag::hybrid;
+
+    fn spec(vector_size: u64) -> CollectionSpec {
+        CollectionSpec::from_config(&crate::utils::config::CollectionConfig::new("test"), vector_size)
+    }
+
+    fn point(id: &str, dense: Vec<f32>, text: &str, repo_id: &str) -> Point {
+        let (indices, values) = hybrid::document_vector(text);
+        Point {
+            id: id.to_string(),
+            dense,
+            sparse: SparseVector { indices, values },
+            payload: HashMap::from([
+                ("content".to_string(), serde_json::json!(text)),
+                ("repo_id".to_string(), serde_json::json!(repo_id)),
+            ]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dense_and_sparse_query_with_filter() {
+        let directory = std::env::temp_dir().join(format!("cerebus-store-{}", uuid::Uuid::new_v4()));
+        let store = EmbeddedStore::open(&directory).await.unwrap();
+        store.ensure_collection("code", &spec(2)).await.unwrap();
+        store
+            .upsert(
+                "code",
+                vec![
+                    point("1", vec![1.0, 0.0], "parse the config file", "a"),
+                    point("2", vec![0.6, 0.8], "open a socket", "a"),
+                    point("3", vec![1.0, 0.1], "parse arguments", "b"),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let all = CompiledFilter::default();
+        let dense = store.query("code", QueryVector::Dense(vec![1.0, 0.0]), 2, None, &all).await.unwrap();
+        let ids: Vec<&str> = dense.iter().map(|result| result.id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "3"]);
+        assert_eq!(dense[0].content, "parse the config file");
+
+        let repo_a = CompiledFilter::new(&SearchFilter {
+            repo_id: Some("a".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let (indices, values) = hybrid::query_vector("parse");
+        let sparse = store
+            .query("code", QueryVector::Sparse(SparseVector { indices, values }), 10, None, &repo_a)
+            .await
+            .unwrap();
+        assert_eq!(sparse.len(), 1);
+        assert_eq!(sparse[0].id, "1");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_collections_persist_across_opens() {
+        let directory = std::env::temp_dir().join(format!("cerebus-store-{}", uuid::Uuid::new_v4()));
+        {
+            let store = EmbeddedStore::open(&directory).await.unwrap();
+            store.ensure_collection("documentation", &spec(2)).await.unwrap();
+            store
+                .upsert("documentation", vec![point("7", vec![0.0, 1.0], "install guide", "a")])
+                .await
+                .unwrap();
+            assert!(store.upsert("documentation", vec![point("8", vec![1.0], "bad", "a")]).await.is_err());
+            store.flush().await.unwrap();
+        }
+
+        let store = EmbeddedStore::open(&directory).await.unwrap();
+        // The stored size wins over a changed config, so the mismatch can be reported
+        let existing = store.ensure_collection("documentation", &spec(3)).await.unwrap();
+        assert_eq!(existing.vector_size, 2);
+        let payload = store.get("documentation", "7").await.unwrap().unwrap();
+        assert_eq!(payload["content"], "install guide");
+        assert!(store.get("documentation", "8").await.unwrap().is_none());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scroll_aliases_and_delete() {
+        let directory = std::env::temp_dir().join(format!("cerebus-store-{}", uuid::Uuid::new_v4()));
+        let store = EmbeddedStore::open(&directory).await.unwrap();
+        store.ensure_collection("code_v2", &spec(2)).await.unwrap();
+        let points = (1..=5).map(|id| point(&id.to_string(), vec![1.0, 0.0], "text", "a")).collect();
+        store.upsert("code_v2", points).await.unwrap();
+
+        let first = store.scroll("code_v2", None, 3).await.unwrap();
+        assert_eq!(first.points.len(), 3);
+        assert_eq!(first.next_offset.as_deref(), Some("4"));
+        let last = store.scroll("code_v2", first.next_offset, 3).await.unwrap();
+        assert_eq!(last.points.len(), 2);
+        assert!(last.next_offset.is_none());
+
+        store.set_alias("code", "code_v2").await.unwrap();
+        let reopened = EmbeddedStore::open(&directory).await.unwrap();
+        assert_eq!(reopened.alias_target("code").await.unwrap().as_deref(), Some("code_v2"));
+        assert!(reopened.get("code", "1").await.unwrap().is_some());
+        let ids = vec!["1".to_string(), "9".to_string()];
+        assert_eq!(reopened.existing("code", &ids).await.unwrap(), HashSet::from(["1".to_string()]));
+
+        reopened.delete_collection("code_v2").await.unwrap();
+        assert!(reopened.alias_target("code").await.unwrap().is_none());
+        assert!(!directory.join("code_v2.json").exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
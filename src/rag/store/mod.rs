@@ -0,0 +1,235 @@
+// src/rag/store/mod.rs
+pub mod embedded;
+pub mod qdrant;
+
+pub use embedded::EmbeddedStore;
+pub use qdrant::QdrantStore;
+
+use crate::mcp::types::SearchResult;
+use crate::rag::filter::{CompiledFilter, PATH_PREFIXES_FIELD};
+use crate::utils::config::{CollectionConfig, DistanceMetric, HnswConfig, QuantizationConfig, Settings};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("Vector store error: {0}")]
+    Backend(String),
+    #[error("Collection {0} does not exist")]
+    MissingCollection(String),
+    #[error("Invalid vector store configuration: {0}")]
+    Config(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Sparse vector as parallel index and value lists
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SparseVector {
+    pub indices: Vec<u32>,
+    pub values: Vec<f32>,
+}
+
+/// A stored point: both vectors of a document and its payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    /// Unsigned integer or UUID, as a string
+    pub id: String,
+    pub dense: Vec<f32>,
+    pub sparse: SparseVector,
+    pub payload: HashMap<String, Value>,
+}
+
+/// Layout of a collection's dense vector index
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionSpec {
+    pub vector_size: u64,
+    pub distance: DistanceMetric,
+    pub hnsw: HnswConfig,
+    pub on_disk: bool,
+    pub quantization: Option<QuantizationConfig>,
+    /// Model that produced the vectors, recorded in the collection's metadata.
+    /// `None` for collections created before models were recorded.
+    pub embedding_model: Option<String>,
+    /// The collection has the single unnamed vector of versions before hybrid
+    /// search; it cannot take new points until it is rebuilt
+    pub legacy: bool,
+}
+
+impl CollectionSpec {
+    /// Spec for a configured collection, taking the vector size from the
+    /// embedding model unless the config sets one
+    pub fn from_config(config: &CollectionConfig, default_vector_size: u64) -> Self {
+        Self {
+            vector_size: config.vector_size.unwrap_or(default_vector_size),
+            distance: config.distance,
+            hnsw: config.hnsw,
+            on_disk: config.on_disk,
+            quantization: config.quantization,
+            embedding_model: None,
+            legacy: false,
+        }
+    }
+
+    /// Record `model` as the producer of the collection's vectors
+    pub fn with_embedding_model(mut self, model: Option<String>) -> Self {
+        self.embedding_model = model;
+        self
+    }
+
+    /// Human-readable differences between this spec and an existing
+    /// collection's layout. A different embedding model is not a layout
+    /// difference; it is resolved by migrating the collection.
+    pub fn mismatches(&self, existing: &CollectionSpec) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        let mut check = |field: &str, expected: String, actual: String| {
+            if expected != actual {
+                mismatches.push(format!("{} is {} but {} is configured", field, actual, expected));
+            }
+        };
+        check("vector size", self.vector_size.to_string(), existing.vector_size.to_string());
+        check("distance", format!("{:?}", self.distance), format!("{:?}", existing.distance));
+        check("HNSW m", self.hnsw.m.to_string(), existing.hnsw.m.to_string());
+        check("HNSW ef_construct", self.hnsw.ef_construct.to_string(), existing.hnsw.ef_construct.to_string());
+        check("on-disk storage", self.on_disk.to_string(), existing.on_disk.to_string());
+        check("quantization", format!("{:?}", self.quantization), format!("{:?}", existing.quantization));
+        mismatches
+    }
+}
+
+/// Vector to rank points against; selects which of the point's vectors is used
+#[derive(Debug, Clone)]
+pub enum QueryVector {
+    Dense(Vec<f32>),
+    Sparse(SparseVector),
+}
+
+/// One page of stored payloads, in id order
+#[derive(Debug, Clone, Default)]
+pub struct ScrollPage {
+    pub points: Vec<(String, HashMap<String, Value>)>,
+    /// Id to continue from, or `None` after the last page
+    pub next_offset: Option<String>,
+}
+
+/// Storage and nearest-neighbour search for embedded documents
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Backend name for logs
+    fn name(&self) -> &str;
+
+    /// Create the collection with a dense vector laid out as in `spec` and a
+    /// sparse BM25 vector, unless it already exists. Returns the spec of the
+    /// collection as stored, which differs from `spec` for an existing
+    /// collection created with other settings.
+    async fn ensure_collection(&self, collection: &str, spec: &CollectionSpec) -> Result<CollectionSpec, StoreError>;
+
+    /// Insert points, replacing any with the same id
+    async fn upsert(&self, collection: &str, points: Vec<Point>) -> Result<(), StoreError>;
+
+    /// Best `limit` points for the vector that pass the filter, highest score first
+    async fn query(
+        &self,
+        collection: &str,
+        vector: QueryVector,
+        limit: u64,
+        score_threshold: Option<f32>,
+        filter: &CompiledFilter,
+    ) -> Result<Vec<SearchResult>, StoreError>;
+
+    /// Remove points by id; ids without a point are ignored
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<(), StoreError>;
+
+    /// Payload of a single point
+    async fn get(&self, collection: &str, id: &str) -> Result<Option<HashMap<String, Value>>, StoreError>;
+
+    /// The ids among `ids` that have a point in the collection
+    async fn existing(&self, collection: &str, ids: &[String]) -> Result<HashSet<String>, StoreError>;
+
+    /// Up to `limit` payloads starting at the point with id `offset`
+    async fn scroll(&self, collection: &str, offset: Option<String>, limit: u32) -> Result<ScrollPage, StoreError>;
+
+    /// Names of the stored collections; aliases are not included
+    async fn collections(&self) -> Result<Vec<String>, StoreError>;
+
+    /// Collection the alias points at, or `None` if `alias` is not an alias
+    async fn alias_target(&self, alias: &str) -> Result<Option<String>, StoreError>;
+
+    /// Create the alias or repoint it at `collection` in a single step, so
+    /// readers see either the old collection or the new one
+    async fn set_alias(&self, alias: &str, collection: &str) -> Result<(), StoreError>;
+
+    /// Drop a collection with its points and any aliases pointing at it
+    async fn delete_collection(&self, collection: &str) -> Result<(), StoreError>;
+
+    /// Persist writes the backend buffers; a no-op for backends that write through
+    async fn flush(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
+
+/// Open the backend selected by the `[vector_store]` settings
+pub async fn from_settings(settings: &Settings) -> Result<Arc<dyn VectorStore>, StoreError> {
+    match settings.vector_store.backend.as_str() {
+        "qdrant" => Ok(Arc::new(QdrantStore::connect(
+            &settings.qdrant.endpoint,
+            settings.qdrant.api_key.clone(),
+        )?)),
+        "embedded" => Ok(Arc::new(EmbeddedStore::open(&settings.vector_store.path).await?)),
+        backend => Err(StoreError::Config(format!("unknown vector store backend: {}", backend))),
+    }
+}
+
+/// Build a search result from a scored payload. The stored text becomes the
+/// content and the file path (or document id) the source.
+pub fn search_result(id: String, score: f32, mut metadata: HashMap<String, Value>) -> SearchResult {
+    let content = match metadata.remove("content") {
+        Some(Value::String(content)) => content,
+        _ => String::new(),
+    };
+    // Only there to make path prefixes filterable
+    metadata.remove(PATH_PREFIXES_FIELD);
+    let source = metadata
+        .get("file_path")
+        .or_else(|| metadata.get("document_id"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    SearchResult {
+        id,
+        content,
+        source,
+        score,
+        metadata: Some(metadata),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_mismatches() {
+        let mut config = CollectionConfig::new("code");
+        config.quantization = Some(QuantizationConfig::Binary { always_ram: true });
+        let expected = CollectionSpec::from_config(&config, 384);
+        assert_eq!(expected.vector_size, 384);
+        assert!(expected.mismatches(&expected).is_empty());
+
+        let existing = CollectionSpec {
+            vector_size: 768,
+            distance: DistanceMetric::Dot,
+            quantization: None,
+            ..expected.clone()
+        };
+        let mismatches = expected.mismatches(&existing);
+        assert_eq!(mismatches.len(), 3);
+        assert_eq!(mismatches[0], "vector size is 768 but 384 is configured");
+    }
+}
@@ -0,0 +1,468 @@
+// src/rag/store/qdrant.rs
+use super::{CollectionSpec, Point, QueryVector, ScrollPage, StoreError, VectorStore, search_result};
+use crate::mcp::types::SearchResult;
+use crate::rag::filter::{CompiledFilter, PAYLOAD_INDEXES};
+use crate::rag::hybrid::{DENSE_VECTOR, SPARSE_VECTOR};
+use crate::utils::config::{DistanceMetric, HnswConfig, QuantizationConfig};
+use async_trait::async_trait;
+use qdrant_client::{
+    Qdrant,
+    qdrant::{
+        self, BinaryQuantization, CreateAliasBuilder, CreateCollection, CreateFieldIndexCollectionBuilder,
+        DeletePointsBuilder, Distance, GetPointsBuilder, HnswConfigDiff, Modifier, NamedVectors, PointId, PointStruct, QuantizationType, Query,
+        QueryPointsBuilder, ScalarQuantization, ScoredPoint, ScrollPointsBuilder, SparseVectorParams,
+        UpsertPointsBuilder, Vector, VectorInput, VectorParams, point_id::PointIdOptions,
+        quantization_config::Quantization, vectors_config,
+    },
+};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Collection metadata key recording the model that produced the vectors
+const EMBEDDING_MODEL_KEY: &str = "embedding_model";
+
+/// Candidates fetched per requested result when a path glob is checked after retrieval
+pub const FILTER_CANDIDATE_FACTOR: u32 = 4;
+
+/// Vector store backed by a Qdrant server over gRPC
+pub struct QdrantStore {
+    client: Qdrant,
+}
+
+impl QdrantStore {
+    pub fn connect(endpoint: &str, api_key: Option<String>) -> Result<Self, StoreError> {
+        let mut client_builder = Qdrant::from_url(endpoint);
+        // Skip server version check to avoid REST/GRPC mismatch noise
+        client_builder.check_compatibility = false;
+        if let Some(api_key) = api_key {
+            client_builder = client_builder.api_key(api_key);
+        }
+        let client = client_builder.build().map_err(backend_error)?;
+        Ok(Self { client })
+    }
+
+    pub fn client(&self) -> &Qdrant {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl VectorStore for QdrantStore {
+    fn name(&self) -> &str {
+        "qdrant"
+    }
+
+    async fn ensure_collection(&self, collection: &str, spec: &CollectionSpec) -> Result<CollectionSpec, StoreError> {
+        let exists = self
+            .client
+            .collection_exists(collection)
+            .await
+            .map_err(|e| StoreError::Backend(format!("Failed to check collection existence: {}", e)))?;
+
+        if !exists {
+            self.client
+                .create_collection(create_request(collection, spec))
+                .await
+                .map_err(backend_error)?;
+        }
+
+        // Also covers collections created before these fields were indexed
+        for (field, field_type) in PAYLOAD_INDEXES {
+            self.client
+                .create_field_index(CreateFieldIndexCollectionBuilder::new(collection, *field, *field_type).wait(true))
+                .await
+                .map_err(|e| StoreError::Backend(format!("Failed to index {} in {}: {}", field, collection, e)))?;
+        }
+
+        let info = self.client.collection_info(collection).await.map_err(backend_error)?;
+        info.result
+            .and_then(|info| info.config)
+            .ok_or_else(|| StoreError::Backend(format!("No configuration returned for {}", collection)))
+            .and_then(|config| existing_spec(collection, config))
+    }
+
+    async fn upsert(&self, collection: &str, points: Vec<Point>) -> Result<(), StoreError> {
+        let count = points.len();
+        let points: Vec<PointStruct> = points
+            .into_iter()
+            .map(|point| {
+                let vectors = NamedVectors::default()
+                    .add_vector(DENSE_VECTOR, point.dense)
+                    .add_vector(SPARSE_VECTOR, Vector::new_sparse(point.sparse.indices, point.sparse.values));
+                PointStruct::new(point_id(&point.id), vectors, point.payload)
+            })
+            .collect();
+
+        self.client
+            .upsert_points(UpsertPointsBuilder::new(collection, points).wait(true))
+            .await
+            .map_err(|e| StoreError::Backend(format!("Failed to upsert {} points into {}: {}", count, collection, e)))?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        collection: &str,
+        vector: QueryVector,
+        limit: u64,
+        score_threshold: Option<f32>,
+        filter: &CompiledFilter,
+    ) -> Result<Vec<SearchResult>, StoreError> {
+        let (query, using) = match vector {
+            QueryVector::Dense(vector) => (Query::new_nearest(vector), DENSE_VECTOR),
+            QueryVector::Sparse(vector) => (
+                Query::new_nearest(VectorInput::new_sparse(vector.indices, vector.values)),
+                SPARSE_VECTOR,
+            ),
+        };
+
+        let candidates = if filter.needs_post_filter() {
+            limit * FILTER_CANDIDATE_FACTOR as u64
+        } else {
+            limit
+        };
+        let mut request = QueryPointsBuilder::new(collection)
+            .query(query)
+            .using(using)
+            .limit(candidates)
+            .with_payload(true);
+        if let Some(score_threshold) = score_threshold {
+            request = request.score_threshold(score_threshold);
+        }
+        if let Some(conditions) = &filter.conditions {
+            request = request.filter(conditions.clone());
+        }
+
+        let response = self
+            .client
+            .query(request)
+            .await
+            .map_err(|e| StoreError::Backend(format!("Search in {} failed: {}", collection, e)))?;
+
+        Ok(response
+            .result
+            .into_iter()
+            .map(scored_result)
+            .filter(|result| filter.matches(result))
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<(), StoreError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<PointId> = ids.iter().map(|id| point_id(id)).collect();
+        self.client
+            .delete_points(DeletePointsBuilder::new(collection).points(ids).wait(true))
+            .await
+            .map_err(|e| StoreError::Backend(format!("Failed to delete points from {}: {}", collection, e)))?;
+        Ok(())
+    }
+
+    async fn get(&self, collection: &str, id: &str) -> Result<Option<HashMap<String, Value>>, StoreError> {
+        let response = self
+            .client
+            .get_points(GetPointsBuilder::new(collection, vec![point_id(id)]).with_payload(true))
+            .await
+            .map_err(backend_error)?;
+
+        Ok(response.result.into_iter().next().map(|point| {
+            point
+                .payload
+                .into_iter()
+                .map(|(key, value)| (key, value.into_json()))
+                .collect()
+        }))
+    }
+
+    async fn existing(&self, collection: &str, ids: &[String]) -> Result<HashSet<String>, StoreError> {
+        let ids: Vec<PointId> = ids.iter().map(|id| point_id(id)).collect();
+        let response = self
+            .client
+            .get_points(GetPointsBuilder::new(collection, ids).with_payload(false).with_vectors(false))
+            .await
+            .map_err(backend_error)?;
+        Ok(response.result.into_iter().map(|point| point_id_string(point.id)).collect())
+    }
+
+    async fn scroll(&self, collection: &str, offset: Option<String>, limit: u32) -> Result<ScrollPage, StoreError> {
+        let mut request = ScrollPointsBuilder::new(collection)
+            .limit(limit)
+            .with_payload(true)
+            .with_vectors(false);
+        if let Some(offset) = offset {
+            request = request.offset(point_id(&offset));
+        }
+
+        let response = self
+            .client
+            .scroll(request)
+            .await
+            .map_err(|e| StoreError::Backend(format!("Failed to scroll {}: {}", collection, e)))?;
+        Ok(ScrollPage {
+            points: response
+                .result
+                .into_iter()
+                .map(|point| {
+                    let payload = point.payload.into_iter().map(|(key, value)| (key, value.into_json())).collect();
+                    (point_id_string(point.id), payload)
+                })
+                .collect(),
+            next_offset: response.next_page_offset.map(|id| point_id_string(Some(id))),
+        })
+    }
+
+    async fn collections(&self) -> Result<Vec<String>, StoreError> {
+        let response = self.client.list_collections().await.map_err(backend_error)?;
+        Ok(response.collections.into_iter().map(|description| description.name).collect())
+    }
+
+    async fn alias_target(&self, alias: &str) -> Result<Option<String>, StoreError> {
+        let response = self.client.list_aliases().await.map_err(backend_error)?;
+        Ok(response
+            .aliases
+            .into_iter()
+            .find(|description| description.alias_name == alias)
+            .map(|description| description.collection_name))
+    }
+
+    async fn set_alias(&self, alias: &str, collection: &str) -> Result<(), StoreError> {
+        // Creating an alias that already exists repoints it in one operation
+        self.client
+            .create_alias(CreateAliasBuilder::new(collection, alias))
+            .await
+            .map_err(|e| StoreError::Backend(format!("Failed to point alias {} at {}: {}", alias, collection, e)))?;
+        Ok(())
+    }
+
+    async fn delete_collection(&self, collection: &str) -> Result<(), StoreError> {
+        self.client
+            .delete_collection(collection)
+            .await
+            .map_err(|e| StoreError::Backend(format!("Failed to delete {}: {}", collection, e)))?;
+        Ok(())
+    }
+}
+
+// The `memory` placement fields replacing these flags are not understood by
+// older Qdrant servers
+#[allow(deprecated)]
+/// Collection with a dense embedding and a sparse BM25 vector per point
+fn create_request(collection: &str, spec: &CollectionSpec) -> CreateCollection {
+    let dense = VectorParams {
+        size: spec.vector_size,
+        distance: distance(spec.distance) as i32,
+        on_disk: Some(spec.on_disk),
+        ..Default::default()
+    };
+    let sparse = SparseVectorParams {
+        modifier: Some(Modifier::Idf as i32),
+        ..Default::default()
+    };
+    CreateCollection {
+        collection_name: collection.to_string(),
+        vectors_config: Some(
+            vectors_config::Config::ParamsMap(
+                HashMap::from([(DENSE_VECTOR.to_string(), dense)]).into()
+            ).into()
+        ),
+        sparse_vectors_config: Some(
+            HashMap::from([(SPARSE_VECTOR.to_string(), sparse)]).into()
+        ),
+        hnsw_config: Some(HnswConfigDiff {
+            m: Some(spec.hnsw.m),
+            ef_construct: Some(spec.hnsw.ef_construct),
+            ..Default::default()
+        }),
+        on_disk_payload: Some(spec.on_disk),
+        quantization_config: spec.quantization.map(quantization),
+        metadata: spec
+            .embedding_model
+            .iter()
+            .map(|model| (EMBEDDING_MODEL_KEY.to_string(), model.clone().into()))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn distance(metric: DistanceMetric) -> Distance {
+    match metric {
+        DistanceMetric::Cosine => Distance::Cosine,
+        DistanceMetric::Dot => Distance::Dot,
+        DistanceMetric::Euclid => Distance::Euclid,
+        DistanceMetric::Manhattan => Distance::Manhattan,
+    }
+}
+
+#[allow(deprecated)]
+fn quantization(config: QuantizationConfig) -> qdrant::QuantizationConfig {
+    let quantization = match config {
+        QuantizationConfig::Scalar { quantile, always_ram } => Quantization::Scalar(ScalarQuantization {
+            r#type: QuantizationType::Int8 as i32,
+            quantile,
+            always_ram: Some(always_ram),
+            ..Default::default()
+        }),
+        QuantizationConfig::Binary { always_ram } => Quantization::Binary(BinaryQuantization {
+            always_ram: Some(always_ram),
+            ..Default::default()
+        }),
+    };
+    qdrant::QuantizationConfig {
+        quantization: Some(quantization),
+    }
+}
+
+/// Read the dense vector layout back out of a collection's configuration.
+/// A single unnamed vector is read as the dense one of a legacy collection.
+#[allow(deprecated)]
+fn existing_spec(collection: &str, config: qdrant::CollectionConfig) -> Result<CollectionSpec, StoreError> {
+    let (dense, legacy) = config
+        .params
+        .and_then(|params| params.vectors_config)
+        .and_then(|vectors| vectors.config)
+        .and_then(|vectors| match vectors {
+            vectors_config::Config::ParamsMap(map) => map.map.get(DENSE_VECTOR).cloned().map(|dense| (dense, false)),
+            vectors_config::Config::Params(dense) => Some((dense, true)),
+        })
+        .ok_or_else(|| {
+            StoreError::Backend(format!(
+                "{} has no '{}' vector; recreate it to index documents",
+                collection, DENSE_VECTOR
+            ))
+        })?;
+
+    let metric = match Distance::try_from(dense.distance) {
+        Ok(Distance::Dot) => DistanceMetric::Dot,
+        Ok(Distance::Euclid) => DistanceMetric::Euclid,
+        Ok(Distance::Manhattan) => DistanceMetric::Manhattan,
+        _ => DistanceMetric::Cosine,
+    };
+    let embedding_model = config
+        .metadata
+        .get(EMBEDDING_MODEL_KEY)
+        .and_then(|model| model.clone().into_json().as_str().map(str::to_string));
+    let hnsw = config.hnsw_config.unwrap_or_default();
+    let defaults = HnswConfig::default();
+    // Quantization may be set for the whole collection or for the vector alone
+    let quantization = dense
+        .quantization_config
+        .or(config.quantization_config)
+        .and_then(|config| config.quantization)
+        .and_then(|quantization| match quantization {
+            Quantization::Scalar(scalar) => Some(QuantizationConfig::Scalar {
+                quantile: scalar.quantile,
+                always_ram: scalar.always_ram.unwrap_or(false),
+            }),
+            Quantization::Binary(binary) => Some(QuantizationConfig::Binary {
+                always_ram: binary.always_ram.unwrap_or(false),
+            }),
+            _ => None,
+        });
+
+    Ok(CollectionSpec {
+        vector_size: dense.size,
+        distance: metric,
+        hnsw: HnswConfig {
+            m: hnsw.m.unwrap_or(defaults.m),
+            ef_construct: hnsw.ef_construct.unwrap_or(defaults.ef_construct),
+        },
+        on_disk: dense.on_disk.unwrap_or(false),
+        quantization,
+        embedding_model,
+        legacy,
+    })
+}
+
+/// Qdrant only accepts unsigned integers and UUIDs as point ids
+fn point_id(id: &str) -> PointId {
+    match id.parse::<u64>() {
+        Ok(id) => id.into(),
+        Err(_) => id.to_string().into(),
+    }
+}
+
+fn point_id_string(id: Option<PointId>) -> String {
+    match id.and_then(|id| id.point_id_options) {
+        Some(PointIdOptions::Num(id)) => id.to_string(),
+        Some(PointIdOptions::Uuid(id)) => id,
+        None => String::new(),
+    }
+}
+
+fn scored_result(point: ScoredPoint) -> SearchResult {
+    let metadata = point
+        .payload
+        .into_iter()
+        .map(|(key, value)| (key, value.into_json()))
+        .collect();
+
+    search_result(point_id_string(point.id), point.score, metadata)
+}
+
+fn backend_error(e: qdrant_client::QdrantError) -> StoreError {
+    StoreError::Backend(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_id_accepts_numbers_and_uuids() {
+        assert_eq!(point_id("42"), PointId::from(42));
+        let uuid = "5b1f3c2e-7d43-4a8e-9a55-0c6f0d6f9b10";
+        assert_eq!(point_id(uuid), PointId::from(uuid.to_string()));
+    }
+
+    #[test]
+    fn test_existing_spec_detects_legacy_layout() {
+        let dense = VectorParams {
+            size: 384,
+            distance: Distance::Dot as i32,
+            ..Default::default()
+        };
+        let config = |vectors: vectors_config::Config| qdrant::CollectionConfig {
+            params: Some(qdrant::CollectionParams {
+                vectors_config: Some(vectors.into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let legacy = existing_spec("code", config(vectors_config::Config::Params(dense))).unwrap();
+        assert!(legacy.legacy);
+        assert_eq!((legacy.vector_size, legacy.distance), (384, DistanceMetric::Dot));
+
+        let named = HashMap::from([(DENSE_VECTOR.to_string(), dense)]);
+        let current = existing_spec("code", config(vectors_config::Config::ParamsMap(named.into()))).unwrap();
+        assert!(!current.legacy);
+        let unnamed = existing_spec("code", config(vectors_config::Config::ParamsMap(HashMap::new().into())));
+        assert!(unnamed.is_err());
+    }
+
+    #[test]
+    fn test_search_result_from_payload() {
+        let payload = HashMap::from([
+            ("content".to_string(), serde_json::json!("fn a() {}")),
+            ("file_path".to_string(), serde_json::json!("src/lib.rs")),
+            ("path_prefixes".to_string(), serde_json::json!(["src", "src/lib.rs"])),
+        ]);
+        let point = ScoredPoint {
+            id: Some(PointId::from(7)),
+            payload: qdrant_client::Payload::from(payload).into(),
+            score: 0.75,
+            ..Default::default()
+        };
+
+        let result = scored_result(point);
+        assert_eq!(result.id, "7");
+        assert_eq!(result.content, "fn a() {}");
+        assert_eq!(result.source, "src/lib.rs");
+        assert_eq!(result.score, 0.75);
+        let metadata = result.metadata.unwrap();
+        assert!(!metadata.contains_key("content"));
+        assert!(!metadata.contains_key("path_prefixes"));
+    }
+}
@@ -0,0 +1,63 @@
+// src/rag/test_support.rs
+use crate::rag::client::RagClient;
+use crate::rag::embedding::{Embedder, EmbeddingError};
+use crate::rag::store::{EmbeddedStore, VectorStore};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Embeds every text to the same vector, so tests need no model
+pub(crate) struct FixedEmbedder {
+    model: &'static str,
+    dimension: usize,
+}
+
+impl FixedEmbedder {
+    pub(crate) fn new(model: &'static str, dimension: usize) -> Self {
+        Self { model, dimension }
+    }
+}
+
+impl Default for FixedEmbedder {
+    fn default() -> Self {
+        Self::new("fixed", 768)
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for FixedEmbedder {
+    fn model_id(&self) -> &str {
+        self.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        Ok(texts.iter().map(|_| vec![0.1; self.dimension]).collect())
+    }
+}
+
+/// A fresh directory under the system temp dir
+pub(crate) fn temp_dir(prefix: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("cerebus-{}-{}", prefix, uuid::Uuid::new_v4()))
+}
+
+/// An initialized client with the fixed embedder and an embedded store in `root/.vectors`
+pub(crate) async fn test_client(root: &Path) -> (RagClient, Arc<dyn VectorStore>) {
+    test_client_with(root, |client| client).await
+}
+
+/// Like `test_client`, with the client adjusted by `configure` before it is initialized
+pub(crate) async fn test_client_with(
+    root: &Path,
+    configure: impl FnOnce(RagClient) -> RagClient,
+) -> (RagClient, Arc<dyn VectorStore>) {
+    let store: Arc<dyn VectorStore> = Arc::new(EmbeddedStore::open(root.join(".vectors")).await.unwrap());
+    let client = RagClient::default()
+        .with_embedder(Arc::new(FixedEmbedder::default()))
+        .with_store(store.clone());
+    let mut client = configure(client);
+    client.initialize().await.unwrap();
+    (client, store)
+}
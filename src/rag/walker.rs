@@ -0,0 +1,329 @@
+// src/rag/walker.rs
+use crate::rag::classify::{Classifier, FileClass};
+use chrono::{DateTime, Utc};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::fs::Metadata;
+use std::path::Path;
+
+/// Leading bytes checked for NUL when deciding whether a file is binary
+const BINARY_SNIFF_LEN: usize = 8192;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReadError {
+    #[error("path is outside the repository")]
+    OutsideRoot,
+    #[error("file is larger than {0} bytes")]
+    TooLarge(u64),
+    #[error("file is binary")]
+    Binary,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WalkError {
+    #[error("Invalid glob: {0}")]
+    Glob(#[from] globset::Error),
+    #[error("{0} is not a directory")]
+    NotADirectory(String),
+}
+
+/// Which repository files an indexing run covers: the selected classes,
+/// narrowed by include globs and minus exclude globs. A glob without a `/`
+/// matches the file name in any directory, as in .gitignore.
+#[derive(Debug, Clone)]
+pub struct FileSelector {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    // Kept to name the glob that excluded a path
+    exclude_patterns: Vec<String>,
+    classes: Vec<FileClass>,
+    classifier: Classifier,
+}
+
+impl Default for FileSelector {
+    fn default() -> Self {
+        Self {
+            include: None,
+            exclude: None,
+            exclude_patterns: Vec::new(),
+            classes: vec![FileClass::Documentation, FileClass::Code, FileClass::Config],
+            classifier: Classifier::default(),
+        }
+    }
+}
+
+impl FileSelector {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, WalkError> {
+        Ok(Self {
+            include: glob_set(include)?,
+            exclude: glob_set(exclude)?,
+            exclude_patterns: exclude.to_vec(),
+            ..Default::default()
+        })
+    }
+
+    /// Only select files of these classes
+    pub fn with_classes(mut self, classes: Vec<FileClass>) -> Self {
+        self.classes = classes;
+        self
+    }
+
+    /// Decide file classes with configured routes rather than by name alone
+    pub fn with_classifier(mut self, classifier: Classifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    /// Whether a repository-relative path is covered
+    pub fn selects(&self, path: &str) -> bool {
+        self.classes.contains(&self.classifier.class(path))
+            && self.include.as_ref().is_none_or(|include| include.is_match(path))
+            && !self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(path))
+    }
+
+    /// Why a repository-relative path is not covered, or `None` if it is
+    pub fn rejection(&self, path: &str) -> Option<String> {
+        let class = self.classifier.class(path);
+        if !self.classes.contains(&class) {
+            return Some(format!("{} files are not selected", class.collection()));
+        }
+        if self.include.as_ref().is_some_and(|include| !include.is_match(path)) {
+            return Some("it matches no include glob".to_string());
+        }
+        let excluded = self.exclude.as_ref().and_then(|exclude| exclude.matches(path).first().copied());
+        excluded.map(|index| format!("it matches the exclude glob {}", self.exclude_patterns[index]))
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<Option<GlobSet>, WalkError> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(glob(pattern)?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Compile a repository path glob; one without a `/` matches the file name
+/// in any directory
+pub(crate) fn glob(pattern: &str) -> Result<Glob, globset::Error> {
+    let pattern = pattern.trim_start_matches("./");
+    let pattern = if pattern.contains('/') {
+        pattern.to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    GlobBuilder::new(&pattern).literal_separator(true).build()
+}
+
+/// A text file found in a repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkedFile {
+    /// Path relative to the repository root, with `/` separators
+    pub path: String,
+    pub content: String,
+    /// Last modification time, where the filesystem records one
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+/// Files found by a walk and those passed over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct WalkSummary {
+    pub files: usize,
+    pub skipped_binary: usize,
+    pub skipped_large: usize,
+    pub unreadable: usize,
+}
+
+/// Collect the selected text files under `root`, honouring .gitignore,
+/// .ignore and global git excludes even outside a git checkout. Hidden files,
+/// binaries and files over `max_file_size` bytes are skipped.
+pub fn walk(root: &Path, selector: &FileSelector, max_file_size: u64) -> Result<(Vec<WalkedFile>, WalkSummary), WalkError> {
+    if !root.is_dir() {
+        return Err(WalkError::NotADirectory(root.display().to_string()));
+    }
+
+    let mut files = Vec::new();
+    let mut summary = WalkSummary::default();
+    let walker = WalkBuilder::new(root)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::debug!("Skipping unreadable entry under {}: {}", root.display(), e);
+                summary.unreadable += 1;
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+            continue;
+        }
+        let Some(path) = relative_path(root, entry.path()) else {
+            continue;
+        };
+        if !selector.selects(&path) {
+            continue;
+        }
+
+        let metadata = entry.metadata().ok();
+        if metadata.as_ref().is_some_and(|metadata| metadata.len() > max_file_size) {
+            summary.skipped_large += 1;
+            continue;
+        }
+        match std::fs::read(entry.path()) {
+            Ok(bytes) => match decode_text(bytes) {
+                Some(content) => files.push(WalkedFile {
+                    path,
+                    content,
+                    modified_at: metadata.as_ref().and_then(modified_at),
+                }),
+                None => summary.skipped_binary += 1,
+            },
+            Err(e) => {
+                tracing::debug!("Failed to read {}: {}", entry.path().display(), e);
+                summary.unreadable += 1;
+            }
+        }
+    }
+
+    summary.files = files.len();
+    Ok((files, summary))
+}
+
+/// Read a text file given relative to `root`, refusing paths that resolve
+/// outside it, including through symlinks
+pub fn read_file(root: &Path, path: &str, max_file_size: u64) -> Result<WalkedFile, ReadError> {
+    let root = root.canonicalize()?;
+    let file = root.join(path).canonicalize()?;
+    if !file.starts_with(&root) {
+        return Err(ReadError::OutsideRoot);
+    }
+    let metadata = file.metadata()?;
+    if metadata.len() > max_file_size {
+        return Err(ReadError::TooLarge(max_file_size));
+    }
+    Ok(WalkedFile {
+        path: path.to_string(),
+        content: decode_text(std::fs::read(&file)?).ok_or(ReadError::Binary)?,
+        modified_at: modified_at(&metadata),
+    })
+}
+
+fn modified_at(metadata: &Metadata) -> Option<DateTime<Utc>> {
+    metadata.modified().ok().map(DateTime::from)
+}
+
+/// A path as given by a client, relative to the repository root with `/`
+/// separators. Absolute paths inside `root` are made relative.
+pub fn normalize_repo_path(root: Option<&Path>, path: &str) -> String {
+    let relative = root
+        .and_then(|root| Path::new(path).strip_prefix(root).ok())
+        .and_then(|relative| relative.to_str())
+        .unwrap_or(path);
+    relative.replace('\\', "/").trim_start_matches("./").to_string()
+}
+
+/// File content as text, or `None` for binary data. UTF-8 and UTF-16 with a
+/// byte order mark are decoded; content with a NUL byte near the start is
+/// binary; anything else that is not UTF-8 is read as Latin-1.
+pub fn decode_text(bytes: Vec<u8>) -> Option<String> {
+    if let Some(text) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        return String::from_utf8(text.to_vec()).ok();
+    }
+    if let Some(text) = bytes.strip_prefix(b"\xFF\xFE") {
+        return decode_utf16(text, u16::from_le_bytes);
+    }
+    if let Some(text) = bytes.strip_prefix(b"\xFE\xFF") {
+        return decode_utf16(text, u16::from_be_bytes);
+    }
+    if bytes[..bytes.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+        return None;
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        Err(e) => Some(e.into_bytes().into_iter().map(char::from).collect()),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Option<String> {
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units).collect::<Result<String, _>>().ok()
+}
+
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Option<Vec<&str>> = relative.components().map(|component| component.as_os_str().to_str()).collect();
+    Some(parts?.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector_globs_and_classes() {
+        let selector = FileSelector::new(&["src/**".to_string(), "*.md".to_string()], &["*_test.rs".to_string()])
+            .unwrap()
+            .with_classes(vec![FileClass::Code, FileClass::Documentation]);
+        assert!(selector.selects("src/main.rs"));
+        assert!(selector.selects("docs/guide/intro.md"));
+        assert!(!selector.selects("src/parser_test.rs"));
+        assert!(!selector.selects("tests/integration.rs"));
+        assert!(!selector.selects("src/settings.toml"));
+        assert!(FileSelector::default().selects("Cargo.toml"));
+    }
+
+    #[test]
+    fn test_walk_honours_ignore_files_and_limits() {
+        let root = std::env::temp_dir().join(format!("cerebus-walk-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join(".ignore"), "*.log\n").unwrap();
+        std::fs::write(root.join("README.md"), "# Project\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("src/logo.png"), [0x89, b'P', b'N', b'G', 0, 0]).unwrap();
+        std::fs::write(root.join("src/big.rs"), "x".repeat(2048)).unwrap();
+        std::fs::write(root.join("target/out.rs"), "fn generated() {}\n").unwrap();
+        std::fs::write(root.join("debug.log"), "noise\n").unwrap();
+
+        let (files, summary) = walk(&root, &FileSelector::default(), 1024).unwrap();
+        let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["README.md", "src/main.rs"]);
+        assert_eq!(summary.skipped_binary, 1);
+        assert_eq!(summary.skipped_large, 1);
+        assert!(matches!(walk(&root.join("README.md"), &FileSelector::default(), 1024), Err(WalkError::NotADirectory(_))));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_read_file_and_decode_text() {
+        let root = std::env::temp_dir().join(format!("cerebus-read-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(root.join("notes.txt"), [0xFF, 0xFE, b'h', 0, b'i', 0]).unwrap();
+
+        let file = read_file(&root, "src/lib.rs", 1024).unwrap();
+        assert_eq!(file.content, "fn a() {}\n");
+        assert!(file.modified_at.is_some());
+        assert_eq!(read_file(&root, "notes.txt", 1024).unwrap().content, "hi");
+        assert!(matches!(read_file(&root, "src/lib.rs", 4), Err(ReadError::TooLarge(4))));
+        assert!(matches!(read_file(&root.join("src"), "../notes.txt", 1024), Err(ReadError::OutsideRoot)));
+        assert!(matches!(read_file(&root, "missing.rs", 1024), Err(ReadError::Io(_))));
+
+        assert_eq!(decode_text(b"caf\xE9".to_vec()).unwrap(), "café");
+        assert_eq!(normalize_repo_path(Some(&root), &root.join("src/lib.rs").to_string_lossy()), "src/lib.rs");
+        assert_eq!(normalize_repo_path(None, "./src/lib.rs"), "src/lib.rs");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
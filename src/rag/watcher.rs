@@ -0,0 +1,669 @@
+// src/rag/watcher.rs
+use crate::mcp::types::RepoFile;
+use crate::rag::classify::FileClass;
+use crate::rag::client::RagClient;
+use crate::rag::manifest::IndexReport;
+use crate::rag::repo_config::{RepoConfigError, REPO_CONFIG_FILE};
+use crate::rag::walker::{self, FileSelector, WalkError};
+use crate::utils::config::IndexingConfig;
+use chrono::{DateTime, Utc};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WatchError {
+    #[error("Repository {0} has no registered root directory; index it with initialize_context first")]
+    Unregistered(String),
+    #[error(transparent)]
+    Walk(#[from] WalkError),
+    #[error(transparent)]
+    RepoConfig(#[from] RepoConfigError),
+    #[error(transparent)]
+    Notify(#[from] notify::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// What a repository watcher is doing
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchState {
+    /// Waiting for changes
+    Idle,
+    /// Collecting changes until they settle
+    Pending,
+    /// Re-indexing changed files
+    Indexing,
+}
+
+/// A repository watcher as reported to agents
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchStatus {
+    pub repo_id: String,
+    pub root: PathBuf,
+    pub state: WatchState,
+    /// Paths changed since the last re-index
+    pub pending_paths: usize,
+    pub last_change_at: Option<DateTime<Utc>>,
+    pub last_indexed_at: Option<DateTime<Utc>>,
+    pub last_report: Option<IndexReport>,
+    pub last_error: Option<String>,
+    /// Files re-indexed or removed since the watcher started
+    pub files_reindexed: usize,
+}
+
+struct RepoWatcher {
+    // Kept alive for as long as events should be delivered
+    _watcher: RecommendedWatcher,
+    task: JoinHandle<()>,
+    status: Arc<Mutex<WatchStatus>>,
+}
+
+impl Drop for RepoWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// File watchers keeping indexed repositories current.
+///
+/// Changes under a repository's root are filtered by ignore files and the
+/// watcher's selector, collected until no more arrive for the debounce
+/// period, and then re-indexed incrementally: changed files are re-embedded
+/// and deleted files and directories removed from the index. The selector is
+/// rebuilt for every batch, so edits to `.cerebus.toml` apply to the next
+/// one, and files such an edit or an ignore file now leaves out are removed.
+pub struct RepoWatchers {
+    rag_client: Arc<RwLock<RagClient>>,
+    debounce: Duration,
+    watch_by_default: bool,
+    watchers: Mutex<HashMap<String, RepoWatcher>>,
+}
+
+impl RepoWatchers {
+    pub fn new(rag_client: Arc<RwLock<RagClient>>, config: &IndexingConfig) -> Self {
+        Self {
+            rag_client,
+            debounce: Duration::from_millis(config.watch_debounce_ms),
+            watch_by_default: config.watch,
+            watchers: Mutex::default(),
+        }
+    }
+
+    /// Whether repositories are watched unless a request says otherwise
+    pub fn watch_by_default(&self) -> bool {
+        self.watch_by_default
+    }
+
+    /// Watch a repository's registered root for changes to the selected
+    /// files, replacing any watcher the repository already has
+    pub async fn watch(
+        &self,
+        repo_id: &str,
+        include: &[String],
+        exclude: &[String],
+        classes: Vec<FileClass>,
+    ) -> Result<WatchStatus, WatchError> {
+        let root = self
+            .rag_client
+            .read()
+            .await
+            .repo_root(repo_id)
+            .ok_or_else(|| WatchError::Unregistered(repo_id.to_string()))?;
+        let root = root.canonicalize()?;
+        if !root.is_dir() {
+            return Err(WalkError::NotADirectory(root.display().to_string()).into());
+        }
+        let changes = Changes {
+            rag_client: self.rag_client.clone(),
+            repo_id: repo_id.to_string(),
+            root: root.clone(),
+            include: include.to_vec(),
+            exclude: exclude.to_vec(),
+            classes,
+            status: Arc::new(Mutex::new(WatchStatus {
+                repo_id: repo_id.to_string(),
+                root: root.clone(),
+                state: WatchState::Idle,
+                pending_paths: 0,
+                last_change_at: None,
+                last_indexed_at: None,
+                last_report: None,
+                last_error: None,
+                files_reindexed: 0,
+            })),
+        };
+        // Refuse a broken configuration up front rather than on the first change
+        changes.selection().await?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                for path in event.paths {
+                    let _ = sender.send(path);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("File watcher error: {}", e),
+        })?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        let status = changes.status.clone();
+        let task = tokio::spawn(changes.run(receiver, self.debounce));
+
+        let current = lock(&status).clone();
+        let watcher = RepoWatcher {
+            _watcher: watcher,
+            task,
+            status,
+        };
+        self.watchers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(repo_id.to_string(), watcher);
+        tracing::info!("Watching {} at {}", repo_id, current.root.display());
+        Ok(current)
+    }
+
+    pub fn is_watching(&self, repo_id: &str) -> bool {
+        self.watchers.lock().unwrap_or_else(|e| e.into_inner()).contains_key(repo_id)
+    }
+
+    /// Stop watching a repository. Returns whether it was watched.
+    pub fn unwatch(&self, repo_id: &str) -> bool {
+        let mut watchers = self.watchers.lock().unwrap_or_else(|e| e.into_inner());
+        watchers.remove(repo_id).is_some()
+    }
+
+    /// Status of every watcher, ordered by repository id
+    pub fn statuses(&self) -> Vec<WatchStatus> {
+        let watchers = self.watchers.lock().unwrap_or_else(|e| e.into_inner());
+        let mut statuses: Vec<WatchStatus> = watchers.values().map(|watcher| lock(&watcher.status).clone()).collect();
+        statuses.sort_by(|a, b| a.repo_id.cmp(&b.repo_id));
+        statuses
+    }
+}
+
+fn lock(status: &Mutex<WatchStatus>) -> std::sync::MutexGuard<'_, WatchStatus> {
+    status.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The re-indexing side of one repository's watcher
+struct Changes {
+    rag_client: Arc<RwLock<RagClient>>,
+    repo_id: String,
+    root: PathBuf,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    classes: Vec<FileClass>,
+    status: Arc<Mutex<WatchStatus>>,
+}
+
+impl Changes {
+    /// Selection from the repository's current `.cerebus.toml`
+    async fn selection(&self) -> Result<Selection, WatchError> {
+        let profile = self.rag_client.read().await.repo_profile(Some(&self.root))?;
+        Ok(Selection {
+            selector: profile.selector(&self.include, &self.exclude, self.classes.clone())?,
+            scope: profile.selector(&[], &[], vec![FileClass::Documentation, FileClass::Code, FileClass::Config])?,
+            max_file_size: profile.config.max_file_size,
+        })
+    }
+
+    async fn run(self, mut receiver: mpsc::UnboundedReceiver<PathBuf>, debounce: Duration) {
+        // Checked when the watcher started; kept until a batch brings a valid replacement
+        let mut selection = self.selection().await.ok();
+        loop {
+            let mut ignores = IgnoreRules::new(&self.root);
+            let mut paths = BTreeSet::new();
+            // Changes nothing would index, such as build output, neither start a batch
+            // nor hold one back, so a busy ignored directory cannot delay re-indexing
+            while paths.is_empty() {
+                let Some(path) = receiver.recv().await else {
+                    return;
+                };
+                if self.relevant(&path, selection.as_ref(), &mut ignores) {
+                    paths.insert(path);
+                }
+            }
+            self.pending(paths.len());
+            // A save often touches a file several times; index once things settle
+            let mut deadline = Instant::now() + debounce;
+            while let Ok(Some(path)) = tokio::time::timeout_at(deadline, receiver.recv()).await {
+                if self.relevant(&path, selection.as_ref(), &mut ignores) {
+                    paths.insert(path);
+                    self.pending(paths.len());
+                    deadline = Instant::now() + debounce;
+                }
+            }
+
+            let current = match self.selection().await {
+                Ok(current) => current,
+                Err(e) => {
+                    tracing::warn!("Not re-indexing {}: {}", self.repo_id, e);
+                    let mut status = lock(&self.status);
+                    status.state = WatchState::Idle;
+                    status.pending_paths = 0;
+                    status.last_error = Some(e.to_string());
+                    continue;
+                }
+            };
+            // Files a changed .cerebus.toml or ignore file now leaves out are dropped from the index
+            let reselected = paths
+                .iter()
+                .any(|path| repo_path(&self.root, path).is_some_and(|relative| selection_file(&relative)));
+            let indexed = if reselected {
+                match self.rag_client.read().await.repo_paths(&self.repo_id).await {
+                    Ok(indexed) => indexed,
+                    Err(e) => {
+                        tracing::warn!("Failed to read the indexed files of {}: {}", self.repo_id, e);
+                        Vec::new()
+                    }
+                }
+            } else {
+                Vec::new()
+            };
+            let Selection {
+                selector,
+                scope,
+                max_file_size,
+            } = current.clone();
+            selection = Some(current);
+            let root = self.root.clone();
+            let sorted = tokio::task::spawn_blocking(move || {
+                let (changed, mut deleted) = sort_changes(&root, paths, &selector, max_file_size);
+                deleted.extend(deselected(&root, indexed, &scope));
+                (changed, deleted)
+            })
+            .await;
+            let (changed, deleted) = match sorted {
+                Ok(sorted) => sorted,
+                Err(e) => {
+                    tracing::warn!("Failed to read changes in {}: {}", self.repo_id, e);
+                    (Vec::new(), Vec::new())
+                }
+            };
+            if changed.is_empty() && deleted.is_empty() {
+                let mut status = lock(&self.status);
+                status.state = WatchState::Idle;
+                status.pending_paths = 0;
+                continue;
+            }
+
+            lock(&self.status).state = WatchState::Indexing;
+            let result = self.reindex(changed, deleted).await;
+            let mut status = lock(&self.status);
+            status.state = WatchState::Idle;
+            status.pending_paths = 0;
+            status.last_indexed_at = Some(Utc::now());
+            match result {
+                Ok(report) => {
+                    tracing::debug!("Re-indexed {} after changes: {:?}", self.repo_id, report);
+                    status.files_reindexed += report.added + report.updated + report.removed;
+                    status.last_report = Some(report);
+                    status.last_error = None;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to re-index {}: {}", self.repo_id, e);
+                    status.last_error = Some(e);
+                }
+            }
+        }
+    }
+
+    /// Whether a changed path can affect the index: a deleted path, a
+    /// directory that is not ignored, a selected file, or a file that
+    /// changes what is selected
+    fn relevant(&self, path: &Path, selection: Option<&Selection>, ignores: &mut IgnoreRules) -> bool {
+        let Some(relative) = repo_path(&self.root, path) else {
+            return false;
+        };
+        if selection_file(&relative) {
+            return true;
+        }
+        if relative.split('/').any(|part| part.starts_with('.')) || ignores.ignored(path, path.is_dir()) {
+            return false;
+        }
+        match selection {
+            Some(selection) if path.is_file() => selection.selector.selects(&relative),
+            _ => true,
+        }
+    }
+
+    fn pending(&self, paths: usize) {
+        let mut status = lock(&self.status);
+        status.state = WatchState::Pending;
+        status.pending_paths = paths;
+        status.last_change_at = Some(Utc::now());
+    }
+
+    async fn reindex(&self, changed: Vec<RepoFile>, deleted: Vec<String>) -> Result<IndexReport, String> {
+        let client = self.rag_client.read().await;
+        let mut report = IndexReport::default();
+        if !changed.is_empty() {
+            let (indexed, failures) = client
+                .index_repo_paths(&self.repo_id, changed)
+                .await
+                .map_err(|e| e.to_string())?;
+            for failure in failures {
+                tracing::debug!("Not re-indexing {}: {}", failure.path, failure.error);
+            }
+            report = indexed;
+        }
+        if !deleted.is_empty() {
+            let removed = client
+                .remove_repo_paths(&self.repo_id, &deleted)
+                .await
+                .map_err(|e| e.to_string())?;
+            report.removed += removed.removed;
+        }
+        Ok(report)
+    }
+}
+
+/// What a watcher re-indexes, rebuilt for every batch of changes
+#[derive(Clone)]
+struct Selection {
+    selector: FileSelector,
+    /// The repository's own globs for every class; indexed files it
+    /// rejects are removed
+    scope: FileSelector,
+    max_file_size: u64,
+}
+
+/// Whether a repository path is a file that changes the selection
+fn selection_file(relative: &str) -> bool {
+    let name = relative.rsplit('/').next().unwrap_or(relative);
+    relative == REPO_CONFIG_FILE || name == ".gitignore" || name == ".ignore"
+}
+
+/// Indexed files that the repository's current globs or ignore files leave out
+fn deselected(root: &Path, indexed: Vec<String>, scope: &FileSelector) -> Vec<String> {
+    let mut ignores = IgnoreRules::new(root);
+    indexed
+        .into_iter()
+        .filter(|path| !scope.selects(path) || ignores.ignored(&root.join(path), false))
+        .collect()
+}
+
+/// Split changed paths into selected text files to re-index, with their
+/// content, and repository paths that no longer exist
+fn sort_changes(
+    root: &Path,
+    paths: BTreeSet<PathBuf>,
+    selector: &FileSelector,
+    max_file_size: u64,
+) -> (Vec<RepoFile>, Vec<String>) {
+    let mut ignores = IgnoreRules::new(root);
+    let mut changed = Vec::new();
+    let mut deleted = Vec::new();
+    for path in paths {
+        let Some(relative) = repo_path(root, &path) else {
+            continue;
+        };
+        // Hidden files, including everything under .git, are never indexed
+        if relative.split('/').any(|part| part.starts_with('.')) {
+            continue;
+        }
+
+        if path.is_dir() {
+            if ignores.ignored(&path, true) {
+                continue;
+            }
+            // A directory moved into place brings files that raise no events of their own
+            match walker::walk(&path, &FileSelector::default(), max_file_size) {
+                Ok((files, _)) => changed.extend(
+                    files
+                        .into_iter()
+                        .map(|file| RepoFile {
+                            path: format!("{}/{}", relative, file.path),
+                            content: Some(file.content),
+                            modified_at: file.modified_at,
+                        })
+                        .filter(|file| selector.selects(&file.path)),
+                ),
+                Err(e) => tracing::debug!("Failed to walk {}: {}", path.display(), e),
+            }
+        } else if path.is_file() {
+            if !selector.selects(&relative) || ignores.ignored(&path, false) {
+                continue;
+            }
+            match walker::read_file(root, &relative, max_file_size) {
+                Ok(file) => changed.push(RepoFile {
+                    path: relative,
+                    content: Some(file.content),
+                    modified_at: file.modified_at,
+                }),
+                Err(e) => tracing::debug!("Not re-indexing {}: {}", relative, e),
+            }
+        } else {
+            deleted.push(relative);
+        }
+    }
+    // A new directory's files may also have been reported one by one
+    changed.sort_by(|a, b| a.path.cmp(&b.path));
+    changed.dedup_by(|a, b| a.path == b.path);
+    (changed, deleted)
+}
+
+/// Path relative to the repository root with `/` separators
+fn repo_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Option<Vec<&str>> = relative.components().map(|component| component.as_os_str().to_str()).collect();
+    let parts = parts?;
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// .gitignore and .ignore files between the repository root and a path,
+/// read once per batch of changes so edits to them take effect
+pub(crate) struct IgnoreRules {
+    root: PathBuf,
+    directories: HashMap<PathBuf, Gitignore>,
+}
+
+impl IgnoreRules {
+    pub(crate) fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            directories: HashMap::new(),
+        }
+    }
+
+    /// Whether a path is ignored; rules in deeper directories take precedence
+    pub(crate) fn ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+        let mut directory = self.root.clone();
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let mut parents: Vec<_> = relative.components().collect();
+        parents.pop();
+
+        for next in std::iter::once(None).chain(parents.into_iter().map(Some)) {
+            if let Some(component) = next {
+                directory.push(component);
+            }
+            let rules = self.directories.entry(directory.clone()).or_insert_with(|| {
+                let mut builder = GitignoreBuilder::new(&directory);
+                for name in [".gitignore", ".ignore"] {
+                    let file = directory.join(name);
+                    if file.is_file() {
+                        if let Some(e) = builder.add(&file) {
+                            tracing::debug!("Bad ignore file {}: {}", file.display(), e);
+                        }
+                    }
+                }
+                builder.build().unwrap_or_else(|_| Gitignore::empty())
+            });
+            let matched = rules.matched_path_or_any_parents(path, is_dir);
+            if matched.is_ignore() {
+                ignored = true;
+            } else if matched.is_whitelist() {
+                ignored = false;
+            }
+        }
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::test_support::{temp_dir, test_client};
+
+    #[test]
+    fn test_sort_changes_honours_ignore_files() {
+        let root = temp_dir("watch");
+        std::fs::create_dir_all(root.join("src/generated")).unwrap();
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(root.join("src/.gitignore"), "generated/\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(root.join("src/generated/out.rs"), "fn b() {}\n").unwrap();
+        std::fs::write(root.join("debug.log"), "noise\n").unwrap();
+        std::fs::write(root.join("docs/guide.md"), "# Guide\n").unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let paths = BTreeSet::from([
+            root.join("src/lib.rs"),
+            root.join("src/generated/out.rs"),
+            root.join("debug.log"),
+            root.join(".git/index"),
+            root.join("src/removed.rs"),
+            root.join("docs"),
+            root.join("docs/guide.md"),
+        ]);
+        let (changed, deleted) = sort_changes(&root, paths, &FileSelector::default(), 1024);
+        let changed: Vec<&str> = changed.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(changed, vec!["docs/guide.md", "src/lib.rs"]);
+        assert_eq!(deleted, vec!["src/removed.rs"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watcher_reindexes_changed_and_deleted_files() {
+        let root = temp_dir("watch");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(root.join("src/old.rs"), "fn old() {}\n").unwrap();
+        std::fs::write(root.join("src/legacy.rs"), "fn legacy() {}\n").unwrap();
+
+        let (client, store) = test_client(&root).await;
+        let classes = vec![FileClass::Code];
+        client.index_directory("repo", &root, &[], &[], classes.clone()).await.unwrap();
+
+        let config = IndexingConfig {
+            watch_debounce_ms: 50,
+            ..Default::default()
+        };
+        let watchers = RepoWatchers::new(Arc::new(RwLock::new(client)), &config);
+        watchers.watch("repo", &[], &[], classes).await.unwrap();
+
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\n\nfn b() {}\n").unwrap();
+        std::fs::remove_file(root.join("src/old.rs")).unwrap();
+
+        let mut reindexed = None;
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let status = watchers.statuses().remove(0);
+            if status.state == WatchState::Idle && status.files_reindexed >= 2 {
+                reindexed = Some(status);
+                break;
+            }
+        }
+        let status = reindexed.expect("watcher did not re-index the changes");
+        assert_eq!(status.last_error, None);
+
+        let old = crate::rag::client::chunk_point_id("repo", "src/old.rs", 0);
+        assert!(store.get("code", &old).await.unwrap().is_none());
+
+        // An exclude added to .cerebus.toml while watching applies to the next change
+        std::fs::write(root.join(".cerebus.toml"), "exclude = [\"*.gen.rs\", \"src/legacy.rs\"]\n").unwrap();
+        std::fs::write(root.join("src/schema.gen.rs"), "struct Schema;\n").unwrap();
+        std::fs::write(root.join("src/new.rs"), "fn new() {}\n").unwrap();
+        let new = crate::rag::client::chunk_point_id("repo", "src/new.rs", 0);
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            if store.get("code", &new).await.unwrap().is_some() && watchers.statuses()[0].state == WatchState::Idle {
+                break;
+            }
+        }
+        assert!(store.get("code", &new).await.unwrap().is_some());
+        let generated = crate::rag::client::chunk_point_id("repo", "src/schema.gen.rs", 0);
+        assert!(store.get("code", &generated).await.unwrap().is_none());
+        // ...and files it now leaves out are dropped from the index
+        let legacy = crate::rag::client::chunk_point_id("repo", "src/legacy.rs", 0);
+        assert!(store.get("code", &legacy).await.unwrap().is_none());
+
+        assert!(watchers.is_watching("repo"));
+        assert!(watchers.unwatch("repo"));
+        assert!(watchers.statuses().is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ignored_changes_do_not_hold_back_reindexing() {
+        let root = temp_dir("watch-ignored");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(root.join("src/skip.rs"), "fn skip() {}\n").unwrap();
+
+        let (client, store) = test_client(&root).await;
+        let classes = vec![FileClass::Code];
+        client.index_directory("repo", &root, &[], &[], classes.clone()).await.unwrap();
+
+        let config = IndexingConfig {
+            watch_debounce_ms: 200,
+            ..Default::default()
+        };
+        let watchers = RepoWatchers::new(Arc::new(RwLock::new(client)), &config);
+        watchers.watch("repo", &[], &[], classes).await.unwrap();
+
+        // A build writing faster than the debounce period
+        let output = root.join("target/build.log");
+        let build = tokio::spawn(async move {
+            for i in 0.. {
+                let _ = std::fs::write(&output, format!("line {}\n", i));
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\n\nfn b() {}\n").unwrap();
+        let mut reindexed = false;
+        for _ in 0..60 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            if watchers.statuses()[0].files_reindexed >= 1 {
+                reindexed = true;
+                break;
+            }
+        }
+        assert!(reindexed, "ignored changes held back re-indexing");
+
+        // A file the .gitignore comes to ignore is dropped from the index
+        std::fs::write(root.join(".gitignore"), "target/\nsrc/skip.rs\n").unwrap();
+        let skip = crate::rag::client::chunk_point_id("repo", "src/skip.rs", 0);
+        for _ in 0..60 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            if store.get("code", &skip).await.unwrap().is_none() {
+                break;
+            }
+        }
+        build.abort();
+        assert!(store.get("code", &skip).await.unwrap().is_none());
+        let lib = crate::rag::client::chunk_point_id("repo", "src/lib.rs", 0);
+        assert!(store.get("code", &lib).await.unwrap().is_some());
+
+        assert!(watchers.unwatch("repo"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
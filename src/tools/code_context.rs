@@ -21,6 +21,12 @@ pub struct CodeContextResponse {
 // since we can't safely modify state without synchronization
 pub struct CodeContextTool;
 
+impl Default for CodeContextTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CodeContextTool {
     pub fn new() -> Self {
         Self
@@ -93,8 +99,17 @@ impl CodeContextTool {
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Stored context entries: key -> (value, metadata)
+type ContextStore = HashMap<String, (serde_json::Value, HashMap<String, serde_json::Value>)>;
+
 pub struct ThreadsafeCodeContextTool {
-    context_store: Arc<RwLock<HashMap<String, (serde_json::Value, HashMap<String, serde_json::Value>)>>>,
+    context_store: Arc<RwLock<ContextStore>>,
+}
+
+impl Default for ThreadsafeCodeContextTool {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ThreadsafeCodeContextTool {
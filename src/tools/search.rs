@@ -1,13 +1,28 @@
 // src/tools/search.rs
 use crate::rag::RagClient;
-use crate::mcp::types::{SearchRequest as McpSearchRequest, SearchResult};
+use crate::rag::classify::FileClass;
+use crate::rag::git::Revision;
+use crate::rag::manifest::IndexReport;
+use crate::rag::registry::{RegistryError, RepoRecord, RepoSettings};
+use crate::rag::repo_config::{EffectiveConfig, FileExplanation, RepoConfigError};
+use crate::rag::walker::WalkSummary;
+use crate::mcp::types::{FileFailure, RepoFile, SearchFilter, SearchMode, SearchRequest as McpSearchRequest, SearchResult};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SearchRequest {
     pub query: String,
     pub context: Option<String>,
     pub limit: Option<u32>,
+    #[serde(default)]
+    pub score_threshold: Option<f32>,
+    #[serde(default)]
+    pub mode: Option<SearchMode>,
+    #[serde(default)]
+    pub filter: Option<SearchFilter>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -18,11 +33,11 @@ pub struct SearchResponse {
 }
 
 pub struct SearchTool {
-    pub rag_client: RagClient,
+    pub rag_client: Arc<RwLock<RagClient>>,
 }
 
 impl SearchTool {
-    pub fn new(rag_client: RagClient) -> Self {
+    pub fn new(rag_client: Arc<RwLock<RagClient>>) -> Self {
         Self { rag_client }
     }
 
@@ -33,9 +48,12 @@ impl SearchTool {
             query: request.query.clone(),
             context: request.context,
             limit: request.limit,
+            score_threshold: request.score_threshold,
+            mode: request.mode,
+            filter: request.filter,
         };
 
-        let results = self.rag_client.search(&mcp_request).await?;
+        let results = self.rag_client.read().await.search(&mcp_request).await?;
 
         let took_ms = start_time.elapsed().as_millis() as u64;
 
@@ -46,12 +64,109 @@ impl SearchTool {
         })
     }
 
-    pub async fn index_repo_files(
+    /// Index the selected files of a git revision, or only those changed
+    /// since `base_rev`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn index_revision(
         &self,
         repo_id: &str,
-        files: Vec<(String, String)>, // (file_path, content)
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.rag_client.index_repo_files(repo_id, files).await?;
-        Ok(())
+        root: &Path,
+        rev: &str,
+        base_rev: Option<&str>,
+        include: &[String],
+        exclude: &[String],
+        classes: Vec<FileClass>,
+    ) -> Result<(IndexReport, Revision), Box<dyn std::error::Error>> {
+        self.rag_client
+            .read()
+            .await
+            .index_revision(repo_id, root, rev, base_rev, include, exclude, classes)
+            .await
+    }
+
+    /// Index the named repository files, reporting those that could not be read
+    pub async fn index_repo_paths(
+        &self,
+        repo_id: &str,
+        files: Vec<RepoFile>,
+    ) -> Result<(IndexReport, Vec<FileFailure>), Box<dyn std::error::Error>> {
+        self.rag_client.read().await.index_repo_paths(repo_id, files).await
+    }
+
+    /// Record the checkout directory a repository's file paths are relative to
+    pub async fn register_root(&self, repo_id: &str, root: &Path) -> Result<RepoRecord, RegistryError> {
+        self.rag_client.read().await.register_root(repo_id, root).await
+    }
+
+    /// Register a repository checkout, keeping the id it was registered under
+    /// before unless one is given
+    pub async fn register_repo(
+        &self,
+        repo_id: Option<&str>,
+        root: &Path,
+        settings: Option<RepoSettings>,
+    ) -> Result<RepoRecord, RegistryError> {
+        let registry = self.rag_client.read().await.registry().clone();
+        registry.register(repo_id, root, settings).await
+    }
+
+    pub async fn repo(&self, repo_id: &str) -> Option<RepoRecord> {
+        self.rag_client.read().await.registry().get(repo_id)
+    }
+
+    /// The repository registered at a checkout directory
+    pub async fn repo_at(&self, root: &Path) -> Option<RepoRecord> {
+        self.rag_client.read().await.registry().find_by_root(root)
+    }
+
+    /// Registered repositories, by id
+    pub async fn repos(&self) -> Vec<RepoRecord> {
+        self.rag_client.read().await.registry().repos()
+    }
+
+    /// Indexing configuration of the repository checked out at `root`
+    pub async fn repo_config(&self, root: &Path) -> Result<EffectiveConfig, RepoConfigError> {
+        let profile = self.rag_client.read().await.repo_profile(Some(root))?;
+        Ok(profile.config.clone())
+    }
+
+    /// Explain whether and how a repository file is indexed
+    pub async fn explain_path(
+        &self,
+        repo_id: &str,
+        root: &Path,
+        path: &str,
+        settings: &RepoSettings,
+    ) -> Result<FileExplanation, Box<dyn std::error::Error>> {
+        self.rag_client
+            .read()
+            .await
+            .explain_path(repo_id, root, path, &settings.include, &settings.exclude, settings.classes.clone())
+            .await
+    }
+
+    /// Purge a repository's indexed chunks and forget it
+    pub async fn remove_repo(
+        &self,
+        repo_id: &str,
+    ) -> Result<(IndexReport, Option<RepoRecord>), Box<dyn std::error::Error>> {
+        self.rag_client.read().await.remove_repo(repo_id).await
+    }
+
+    /// Walk a repository checkout and index the selected files, removing
+    /// previously indexed ones that are gone
+    pub async fn index_directory(
+        &self,
+        repo_id: &str,
+        root: &Path,
+        include: &[String],
+        exclude: &[String],
+        classes: Vec<FileClass>,
+    ) -> Result<(IndexReport, WalkSummary), Box<dyn std::error::Error>> {
+        self.rag_client
+            .read()
+            .await
+            .index_directory(repo_id, root, include, exclude, classes)
+            .await
     }
 }
\ No newline at end of file
@@ -1,12 +1,25 @@
 // src/utils/config.rs
+use crate::rag::classify::FileClass;
 use config::{Config, ConfigError, Environment, File};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
     pub server: ServerConfig,
     pub qdrant: QdrantConfig,
     pub agents: AgentConfig,
+    #[serde(default)]
+    pub embedding: EmbeddingConfig,
+    #[serde(default)]
+    pub chunking: ChunkingConfig,
+    #[serde(default)]
+    pub reranker: RerankerConfig,
+    #[serde(default)]
+    pub vector_store: VectorStoreConfig,
+    #[serde(default)]
+    pub rag: RagConfig,
+    #[serde(default)]
+    pub indexing: IndexingConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -15,6 +28,13 @@ pub struct ServerConfig {
     pub port: u16,
     pub cors_origins: Vec<String>,
     pub max_connections: usize,
+    /// MCP transport to serve: "websocket" or "stdio"
+    #[serde(default = "default_transport")]
+    pub transport: String,
+}
+
+fn default_transport() -> String {
+    "websocket".to_string()
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -29,6 +49,473 @@ pub struct AgentConfig {
     pub max_agents: usize,
     pub session_timeout_seconds: u64,
     pub sync_interval_seconds: u64,
+    #[serde(default = "default_session_grace_period_seconds")]
+    pub session_grace_period_seconds: u64,
+    #[serde(default = "default_max_pending_notifications")]
+    pub max_pending_notifications: usize,
+}
+
+fn default_session_grace_period_seconds() -> u64 {
+    300
+}
+
+fn default_max_pending_notifications() -> usize {
+    256
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmbeddingConfig {
+    /// Embedding backend: "local" runs the model in-process on the CPU,
+    /// "openai" and "ollama" call an inference server over HTTP
+    #[serde(default = "default_embedding_provider")]
+    pub provider: String,
+    /// Model name sent to HTTP providers
+    #[serde(default = "default_embedding_model")]
+    pub model: String,
+    /// Base URL of the HTTP provider; defaults to the provider's public endpoint
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Value of the auth header sent to HTTP providers, e.g. "Bearer <key>"
+    #[serde(default)]
+    pub auth_header: Option<String>,
+    #[serde(default = "default_embedding_auth_header_name")]
+    pub auth_header_name: String,
+    #[serde(default = "default_embedding_timeout_seconds")]
+    pub timeout_seconds: u64,
+    /// Directory holding config.json, tokenizer.json and the model weights
+    #[serde(default = "default_embedding_model_path")]
+    pub model_path: String,
+    #[serde(default = "default_embedding_dimension")]
+    pub dimension: usize,
+    #[serde(default = "default_embedding_batch_size")]
+    pub batch_size: usize,
+    /// Inputs are truncated to this many tokens
+    #[serde(default = "default_embedding_max_tokens")]
+    pub max_tokens: usize,
+}
+
+fn default_embedding_provider() -> String {
+    "local".to_string()
+}
+
+fn default_embedding_model() -> String {
+    "all-MiniLM-L6-v2".to_string()
+}
+
+fn default_embedding_auth_header_name() -> String {
+    "Authorization".to_string()
+}
+
+fn default_embedding_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_embedding_model_path() -> String {
+    "models/all-MiniLM-L6-v2".to_string()
+}
+
+fn default_embedding_dimension() -> usize {
+    384
+}
+
+fn default_embedding_batch_size() -> usize {
+    32
+}
+
+fn default_embedding_max_tokens() -> usize {
+    256
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            provider: default_embedding_provider(),
+            model: default_embedding_model(),
+            endpoint: None,
+            auth_header: None,
+            auth_header_name: default_embedding_auth_header_name(),
+            timeout_seconds: default_embedding_timeout_seconds(),
+            model_path: default_embedding_model_path(),
+            dimension: default_embedding_dimension(),
+            batch_size: default_embedding_batch_size(),
+            max_tokens: default_embedding_max_tokens(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChunkingConfig {
+    /// Upper bound on the estimated tokens in a chunk
+    #[serde(default = "default_chunk_max_tokens")]
+    pub max_tokens: usize,
+    /// Tokens repeated between consecutive pieces of a split definition
+    #[serde(default = "default_chunk_overlap_tokens")]
+    pub overlap_tokens: usize,
+}
+
+fn default_chunk_max_tokens() -> usize {
+    512
+}
+
+fn default_chunk_overlap_tokens() -> usize {
+    64
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            max_tokens: default_chunk_max_tokens(),
+            overlap_tokens: default_chunk_overlap_tokens(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RerankerConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Reranking backend: "local" runs a cross-encoder in-process on the CPU,
+    /// "http" calls a Cohere/Jina-compatible `/v1/rerank` endpoint
+    #[serde(default = "default_reranker_provider")]
+    pub provider: String,
+    /// Model name sent to the HTTP endpoint
+    #[serde(default = "default_reranker_model")]
+    pub model: String,
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Value of the auth header sent to the HTTP endpoint, e.g. "Bearer <key>"
+    #[serde(default)]
+    pub auth_header: Option<String>,
+    #[serde(default = "default_reranker_auth_header_name")]
+    pub auth_header_name: String,
+    #[serde(default = "default_reranker_timeout_seconds")]
+    pub timeout_seconds: u64,
+    /// Directory holding config.json, tokenizer.json and the cross-encoder weights
+    #[serde(default = "default_reranker_model_path")]
+    pub model_path: String,
+    /// Number of retrieved candidates re-scored before truncating to the limit
+    #[serde(default = "default_reranker_top_n")]
+    pub top_n: usize,
+    #[serde(default = "default_reranker_batch_size")]
+    pub batch_size: usize,
+    /// Query and document pairs are truncated to this many tokens
+    #[serde(default = "default_reranker_max_tokens")]
+    pub max_tokens: usize,
+}
+
+fn default_reranker_provider() -> String {
+    "local".to_string()
+}
+
+fn default_reranker_model() -> String {
+    "ms-marco-MiniLM-L-6-v2".to_string()
+}
+
+fn default_reranker_auth_header_name() -> String {
+    "Authorization".to_string()
+}
+
+fn default_reranker_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_reranker_model_path() -> String {
+    "models/ms-marco-MiniLM-L-6-v2".to_string()
+}
+
+fn default_reranker_top_n() -> usize {
+    50
+}
+
+fn default_reranker_batch_size() -> usize {
+    32
+}
+
+fn default_reranker_max_tokens() -> usize {
+    512
+}
+
+impl Default for RerankerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: default_reranker_provider(),
+            model: default_reranker_model(),
+            endpoint: None,
+            auth_header: None,
+            auth_header_name: default_reranker_auth_header_name(),
+            timeout_seconds: default_reranker_timeout_seconds(),
+            model_path: default_reranker_model_path(),
+            top_n: default_reranker_top_n(),
+            batch_size: default_reranker_batch_size(),
+            max_tokens: default_reranker_max_tokens(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct VectorStoreConfig {
+    /// "qdrant" uses the server in `[qdrant]`, "embedded" keeps vectors in
+    /// files under `path` with no external service
+    #[serde(default = "default_vector_store_backend")]
+    pub backend: String,
+    /// Directory of the embedded store
+    #[serde(default = "default_vector_store_path")]
+    pub path: String,
+}
+
+fn default_vector_store_backend() -> String {
+    "qdrant".to_string()
+}
+
+fn default_vector_store_path() -> String {
+    "data/vectors".to_string()
+}
+
+impl Default for VectorStoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: default_vector_store_backend(),
+            path: default_vector_store_path(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct IndexingConfig {
+    /// Files larger than this many bytes are skipped when walking a repository
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: u64,
+    /// Globs excluded from every walk, on top of .gitignore and .ignore files
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Class and language overrides for matching files; the first match wins
+    #[serde(default)]
+    pub routes: Vec<RouteConfig>,
+    /// Watch repositories indexed by initialize_context and re-index files as they change
+    #[serde(default)]
+    pub watch: bool,
+    /// Quiet period after the last change before changed files are re-indexed
+    #[serde(default = "default_watch_debounce_ms")]
+    pub watch_debounce_ms: u64,
+    /// Indexing jobs run at the same time; further jobs wait in the queue
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    /// File recording indexing jobs so interrupted ones resume after a restart
+    #[serde(default = "default_jobs_path")]
+    pub jobs_path: String,
+    /// File recording registered repositories and their last indexing
+    #[serde(default = "default_registry_path")]
+    pub registry_path: String,
+}
+
+/// Files matching `glob` are indexed as `class` and parsed as `language`
+/// instead of what their name and content suggest
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RouteConfig {
+    pub glob: String,
+    #[serde(default)]
+    pub class: Option<FileClass>,
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+fn default_max_file_size() -> u64 {
+    1024 * 1024
+}
+
+fn default_watch_debounce_ms() -> u64 {
+    500
+}
+
+fn default_workers() -> usize {
+    2
+}
+
+fn default_jobs_path() -> String {
+    "data/jobs.json".to_string()
+}
+
+fn default_registry_path() -> String {
+    "data/repos.json".to_string()
+}
+
+impl Default for IndexingConfig {
+    fn default() -> Self {
+        Self {
+            max_file_size: default_max_file_size(),
+            exclude: Vec::new(),
+            routes: Vec::new(),
+            watch: false,
+            watch_debounce_ms: default_watch_debounce_ms(),
+            workers: default_workers(),
+            jobs_path: default_jobs_path(),
+            registry_path: default_registry_path(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RagConfig {
+    /// Collections created at startup and checked against existing ones
+    #[serde(default = "default_rag_collections")]
+    pub collections: Vec<CollectionConfig>,
+    /// Re-embed collections built with another embedding model in the background
+    #[serde(default = "default_migrate_on_model_change")]
+    pub migrate_on_model_change: bool,
+    /// Directory of the per-repository manifests used to skip unchanged files
+    #[serde(default = "default_manifest_path")]
+    pub manifest_path: String,
+}
+
+fn default_manifest_path() -> String {
+    "data/manifests".to_string()
+}
+
+fn default_migrate_on_model_change() -> bool {
+    true
+}
+
+fn default_rag_collections() -> Vec<CollectionConfig> {
+    ["documentation", "code", "config"]
+        .into_iter()
+        .map(CollectionConfig::new)
+        .collect()
+}
+
+impl Default for RagConfig {
+    fn default() -> Self {
+        Self {
+            collections: default_rag_collections(),
+            migrate_on_model_change: default_migrate_on_model_change(),
+            manifest_path: default_manifest_path(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct CollectionConfig {
+    pub name: String,
+    /// Dense vector size; the embedding model's dimension when omitted
+    #[serde(default)]
+    pub vector_size: Option<u64>,
+    /// Embedding model the collection is built for; checked against the loaded model
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    #[serde(default)]
+    pub distance: DistanceMetric,
+    #[serde(default)]
+    pub hnsw: HnswConfig,
+    /// Keep vectors and payloads on disk instead of in memory
+    #[serde(default)]
+    pub on_disk: bool,
+    #[serde(default)]
+    pub quantization: Option<QuantizationConfig>,
+    /// Reranking of this collection's searches; unset fields use `[reranker]`
+    #[serde(default)]
+    pub rerank: CollectionRerankConfig,
+}
+
+impl CollectionConfig {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            vector_size: None,
+            embedding_model: None,
+            distance: DistanceMetric::default(),
+            hnsw: HnswConfig::default(),
+            on_disk: false,
+            quantization: None,
+            rerank: CollectionRerankConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct CollectionRerankConfig {
+    /// Rerank searches against this collection when `[reranker]` is enabled
+    #[serde(default = "default_collection_rerank_enabled")]
+    pub enabled: bool,
+    /// Candidates re-scored for this collection instead of `[reranker].top_n`
+    #[serde(default)]
+    pub top_n: Option<usize>,
+    /// Model sent to the HTTP endpoint instead of `[reranker].model`
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Local cross-encoder directory instead of `[reranker].model_path`
+    #[serde(default)]
+    pub model_path: Option<String>,
+}
+
+fn default_collection_rerank_enabled() -> bool {
+    true
+}
+
+impl Default for CollectionRerankConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_collection_rerank_enabled(),
+            top_n: None,
+            model: None,
+            model_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceMetric {
+    #[default]
+    Cosine,
+    Dot,
+    Euclid,
+    Manhattan,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct HnswConfig {
+    /// Edges per node in the graph
+    #[serde(default = "default_hnsw_m")]
+    pub m: u64,
+    /// Neighbours considered while building the graph
+    #[serde(default = "default_hnsw_ef_construct")]
+    pub ef_construct: u64,
+}
+
+fn default_hnsw_m() -> u64 {
+    16
+}
+
+fn default_hnsw_ef_construct() -> u64 {
+    100
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        Self {
+            m: default_hnsw_m(),
+            ef_construct: default_hnsw_ef_construct(),
+        }
+    }
+}
+
+/// Vector compression; originals are kept on disk for rescoring
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum QuantizationConfig {
+    /// int8 per dimension, about 4x smaller
+    Scalar {
+        /// Quantile used to clip outliers before scaling, e.g. 0.99
+        #[serde(default)]
+        quantile: Option<f32>,
+        #[serde(default)]
+        always_ram: bool,
+    },
+    /// One bit per dimension, about 32x smaller; suits large models
+    Binary {
+        #[serde(default)]
+        always_ram: bool,
+    },
 }
 
 impl Settings {
@@ -55,6 +542,7 @@ impl Default for Settings {
                 port: 3000,
                 cors_origins: vec!["http://localhost:3000".to_string(), "http://localhost:3001".to_string()],
                 max_connections: 100,
+                transport: default_transport(),
             },
             qdrant: QdrantConfig {
                 endpoint: "http://localhost:6334".to_string(),
@@ -65,7 +553,15 @@ impl Default for Settings {
                 max_agents: 10,
                 session_timeout_seconds: 3600, // 1 hour
                 sync_interval_seconds: 30,
+                session_grace_period_seconds: default_session_grace_period_seconds(),
+                max_pending_notifications: default_max_pending_notifications(),
             },
+            embedding: EmbeddingConfig::default(),
+            chunking: ChunkingConfig::default(),
+            reranker: RerankerConfig::default(),
+            vector_store: VectorStoreConfig::default(),
+            rag: RagConfig::default(),
+            indexing: IndexingConfig::default(),
         }
     }
 }
@@ -78,7 +574,8 @@ pub fn init_tracing() {
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new("cerebus_rex=debug,axum=debug"));
 
-    fmt().with_env_filter(env_filter).init();
+    // Log to stderr so stdout stays free for the stdio transport
+    fmt().with_env_filter(env_filter).with_writer(std::io::stderr).init();
 }
 
 #[cfg(test)]
@@ -92,4 +589,33 @@ mod tests {
         assert_eq!(settings.server.port, 3000);
         assert_eq!(settings.qdrant.endpoint, "http://localhost:6334");
     }
+
+    #[test]
+    fn test_collections_from_toml() {
+        let toml = r#"
+            [[collections]]
+            name = "code"
+            vector_size = 768
+            distance = "dot"
+            on_disk = true
+            quantization = { type = "scalar", quantile = 0.99 }
+
+            [[collections]]
+            name = "documentation"
+            hnsw = { m = 32 }
+        "#;
+        let rag: RagConfig = Config::builder()
+            .add_source(File::from_str(toml, config::FileFormat::Toml))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .unwrap();
+
+        assert_eq!(rag.collections.len(), 2);
+        let code = &rag.collections[0];
+        assert_eq!(code.vector_size, Some(768));
+        assert_eq!(code.distance, DistanceMetric::Dot);
+        assert_eq!(code.quantization, Some(QuantizationConfig::Scalar { quantile: Some(0.99), always_ram: false }));
+        assert_eq!(rag.collections[1].hnsw, HnswConfig { m: 32, ef_construct: 100 });
+        assert_eq!(rag.collections[1].quantization, None);
+    }
 }
\ No newline at end of file